@@ -0,0 +1,135 @@
+//Transactions are timestamped in seconds since the unix epoch (UTC), these helpers convert
+//between those timestamps and calendar dates without needing a date library
+//p03-media/src/content/dates.rs has a copy of the calendar conversions below, keep the two the same
+
+pub const SECONDS_PER_MINUTE: u64 = 60;
+pub const SECONDS_PER_DAY: u64 = 86_400;
+
+//dates are written with four digit years, and bounding the year keeps the calendar arithmetic from overflowing
+const MAX_YEAR: i64 = 9999;
+
+///Returns the number of days from 1970-01-01 to the given date, using the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    //shifting the year to start in March puts the leap day at the very end of the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+///Returns the (year, month, day) that the given number of days since 1970-01-01 falls on.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+///Returns the timestamp at the start (00:00:00 UTC) of the given date, or None if the date doesn't exist
+///or isn't between 1970 and 9999.
+pub fn timestamp_from_date(year: i64, month: u32, day: u32) -> Option<u64> {
+    if !(1970..=MAX_YEAR).contains(&year)
+        || !(1..=12).contains(&month)
+        || day == 0
+        || day > days_in_month(year, month)
+    {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    u64::try_from(days)
+        .ok()
+        .and_then(|days| days.checked_mul(SECONDS_PER_DAY))
+}
+
+///Parses a date written as YYYY-MM-DD into the timestamp at the start of that day.
+pub fn parse_date(text: &str) -> Option<u64> {
+    let mut parts = text.trim().splitn(3, '-');
+    let year = parts.next()?.parse().ok()?;
+    let month = parts.next()?.parse().ok()?;
+    let day = parts.next()?.parse().ok()?;
+
+    timestamp_from_date(year, month, day)
+}
+
+///Formats the date that a timestamp falls on as YYYY-MM-DD.
+pub fn format_date(timestamp: u64) -> String {
+    let (year, month, day) = civil_from_days((timestamp / SECONDS_PER_DAY) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_dates_and_timestamps() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date(" 2000-03-01 "), Some(951_868_800));
+        assert_eq!(format_date(951_868_800 + SECONDS_PER_DAY - 1), "2000-03-01");
+        assert_eq!(format_date(0), "1970-01-01");
+
+        //every day for a few leap years round trips, including the leap days
+        let start = parse_date("2023-12-25").unwrap();
+        for day in 0..(4 * 366) {
+            let timestamp = start + day * SECONDS_PER_DAY;
+            assert_eq!(parse_date(&format_date(timestamp)), Some(timestamp));
+        }
+    }
+
+    #[test]
+    fn refuses_dates_that_do_not_exist() {
+        assert_eq!(
+            parse_date("2024-02-29").map(format_date).as_deref(),
+            Some("2024-02-29")
+        );
+        for date in [
+            "2023-02-29",
+            "1900-02-29",
+            "2024-04-31",
+            "2024-13-01",
+            "2024-00-10",
+            "2024-01-00",
+            "1969-12-31",
+            "10000-01-01",
+            "300000000000-01-01",
+            "9000000000000000000-01-01",
+            "2024-01",
+            "yesterday",
+        ] {
+            assert_eq!(parse_date(date), None, "{}", date);
+        }
+        assert_eq!(days_in_month(2000, 2), 29);
+        assert_eq!(timestamp_from_date(2024, 1, 32), None);
+        assert_eq!(timestamp_from_date(i64::MIN, 1, 1), None);
+        assert_eq!(
+            parse_date("9999-12-31").map(format_date).as_deref(),
+            Some("9999-12-31")
+        );
+    }
+}
@@ -0,0 +1,300 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Error, ErrorKind, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::dates::SECONDS_PER_DAY;
+use super::{Account, Transaction};
+
+///Every change to a Bank's state, in the order that it happened.
+///The Bank is rebuilt by replaying these, so nothing should change an account without one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event")]
+pub enum Event {
+    AccountOpened { id: u32, holder: String },
+    FundsCredited { id: u32, amount: isize },
+    FundsDebited { id: u32, amount: isize },
+    TransferCompleted { from: u32, to: u32, amount: isize },
+}
+
+///An Event as it is written to the log, one JSON object per line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub sequence: u64,
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub event: Event,
+    //the reason the rules flagged the transaction for review, if they did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review: Option<String>,
+}
+
+///The balances as they were after a given event, so that loading only has to replay the events after it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub sequence: u64,
+    pub timestamp: u64,
+    //how far into the log file the snapshot's events go, so the rest can be read without parsing them all again
+    pub log_offset: u64,
+    pub accounts: Vec<AccountSnapshot>,
+}
+
+///An account's balance when a snapshot was taken.  Only the transactions from the day before it are kept, since
+///those are all the daily and velocity rules look at, so a snapshot doesn't grow with the account's history.
+///Flagged transactions are kept however old they are, so they are still listed for review after reopening.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountSnapshot {
+    pub id: u32,
+    pub holder: String,
+    pub balance: isize,
+    #[serde(default)]
+    pub recent: Vec<Transaction>,
+}
+
+impl AccountSnapshot {
+    fn new(account: &Account, timestamp: u64) -> Self {
+        AccountSnapshot {
+            id: account.id,
+            holder: account.holder.clone(),
+            balance: account.balance,
+            recent: account
+                .transactions
+                .iter()
+                .filter(|transaction| {
+                    transaction.review.is_some()
+                        || transaction.timestamp + SECONDS_PER_DAY > timestamp
+                })
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+///An append-only event log file, along with a snapshot file that is rewritten every `snapshot_every` events.
+#[derive(Debug)]
+pub struct EventStore {
+    log_path: PathBuf,
+    snapshot_path: PathBuf,
+    snapshot_every: u64,
+}
+
+impl EventStore {
+    ///A store with its log and snapshot in the given directory, for a bank that shouldn't share the saved one.
+    pub fn in_directory(directory: &std::path::Path, snapshot_every: u64) -> Self {
+        EventStore {
+            log_path: directory.join("events.log"),
+            snapshot_path: directory.join("snapshot.json"),
+            snapshot_every,
+        }
+    }
+
+    pub fn new(log_path: &str, snapshot_path: &str, snapshot_every: u64) -> Self {
+        EventStore {
+            log_path: PathBuf::from(log_path),
+            snapshot_path: PathBuf::from(snapshot_path),
+            snapshot_every,
+        }
+    }
+
+    ///Adds a record to the end of the log, creating the log file if it doesn't exist yet.
+    pub fn append(&self, record: &Record) -> Result<(), Error> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.log_path)?;
+
+        let line = serde_json::to_string(record)?;
+        writeln!(file, "{}", line)?;
+        file.sync_data()
+    }
+
+    ///Returns whether a snapshot should be taken now that the record with this sequence number has been appended.
+    pub fn snapshot_due(&self, sequence: u64) -> bool {
+        self.snapshot_every > 0 && sequence.is_multiple_of(self.snapshot_every)
+    }
+
+    ///Reads the records in the log starting at the given byte offset, or none if there is no log yet.
+    pub fn read_from(&self, offset: u64) -> Result<Vec<Record>, Error> {
+        let mut file = match File::open(&self.log_path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(error) => return Err(error),
+        };
+        file.seek(SeekFrom::Start(offset))?;
+
+        let mut records = vec![];
+
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let record = serde_json::from_str(&line).map_err(|error| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "event log entry {} after offset {}: {}",
+                        index + 1,
+                        offset,
+                        error
+                    ),
+                )
+            })?;
+            records.push(record);
+        }
+
+        Ok(records)
+    }
+
+    ///Reads the latest snapshot, or None if one hasn't been taken yet.
+    pub fn read_snapshot(&self) -> Result<Option<Snapshot>, Error> {
+        match fs::read_to_string(&self.snapshot_path) {
+            Ok(text) => Ok(Some(serde_json::from_str(&text)?)),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    ///Replaces the snapshot with the balances of the given accounts.  It is written to a temporary file first and then renamed,
+    ///so a crash part way through leaves the old snapshot in place rather than half of a new one.
+    pub fn write_snapshot(
+        &self,
+        sequence: u64,
+        timestamp: u64,
+        accounts: &[Account],
+    ) -> Result<(), Error> {
+        let log_offset = fs::metadata(&self.log_path)?.len();
+
+        let snapshot = Snapshot {
+            sequence,
+            timestamp,
+            log_offset,
+            accounts: accounts
+                .iter()
+                .map(|account| AccountSnapshot::new(account, timestamp))
+                .collect(),
+        };
+
+        let temporary_path = self.snapshot_path.with_extension("tmp");
+        fs::write(&temporary_path, serde_json::to_string(&snapshot)?)?;
+        fs::rename(&temporary_path, &self.snapshot_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::RuleSet;
+    use crate::{Bank, TransactionKind};
+
+    #[test]
+    fn flagged_transfer_is_listed_once() {
+        let rules = RuleSet::parse("max_transaction = 100 flag").unwrap();
+        let mut bank = Bank::with_rules(rules);
+        bank.open_account(1, String::from("TestName")).unwrap();
+        bank.open_account(2, String::from("Jeffy")).unwrap();
+        bank.credit(1, 50).unwrap();
+        bank.credit(1, 500).unwrap();
+        bank.transfer(1, 2, 200).unwrap();
+
+        let flagged: Vec<(u32, TransactionKind)> = bank
+            .flagged_transactions()
+            .into_iter()
+            .map(|(id, transaction)| (id, transaction.kind))
+            .collect();
+        assert_eq!(
+            flagged,
+            vec![
+                (1, TransactionKind::Credit),
+                (1, TransactionKind::TransferOut)
+            ]
+        );
+    }
+
+    #[test]
+    fn reopening_replays_after_the_snapshot() {
+        let directory = tempfile::tempdir().unwrap();
+        let store = EventStore::in_directory(directory.path(), 3);
+        let mut bank = Bank::open(store, RuleSet::default()).unwrap();
+        bank.open_account(1, String::from("TestName")).unwrap();
+        bank.open_account(2, String::from("Jeffy")).unwrap();
+        bank.credit(1, 1000).unwrap();
+        bank.debit(1, 100).unwrap();
+        bank.transfer(1, 2, 250).unwrap();
+
+        let store = bank.store.as_ref().unwrap();
+        let snapshot = store.read_snapshot().unwrap().unwrap();
+        assert_eq!(snapshot.sequence, 3);
+        assert!(snapshot.log_offset < fs::metadata(&store.log_path).unwrap().len());
+
+        let reopened = Bank::open(
+            EventStore::in_directory(directory.path(), 3),
+            RuleSet::default(),
+        )
+        .unwrap();
+        assert_eq!(reopened.sequence, 5);
+        assert_eq!(reopened.accounts_summary(), bank.accounts_summary());
+        assert_eq!(reopened.history(1).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn snapshot_keeps_balances_and_only_recent_transactions() {
+        let directory = tempfile::tempdir().unwrap();
+        let store = EventStore::in_directory(directory.path(), 3);
+        let now = 1_700_000_000;
+        let transaction = |timestamp| Transaction {
+            sequence: 1,
+            kind: TransactionKind::Credit,
+            amount: 10,
+            timestamp,
+            review: None,
+        };
+        let account = Account {
+            id: 1,
+            balance: 20,
+            holder: String::from("TestName"),
+            transactions: vec![transaction(now - SECONDS_PER_DAY), transaction(now - 60)],
+        };
+        fs::write(&store.log_path, "").unwrap();
+
+        store.write_snapshot(2, now, &[account]).unwrap();
+
+        let snapshot = store.read_snapshot().unwrap().unwrap();
+        assert_eq!(snapshot.accounts.len(), 1);
+        assert_eq!(snapshot.accounts[0].balance, 20);
+        assert_eq!(snapshot.accounts[0].recent.len(), 1);
+        assert_eq!(snapshot.accounts[0].recent[0].timestamp, now - 60);
+    }
+
+    #[test]
+    fn old_flagged_transactions_are_kept_after_reopening() {
+        let directory = tempfile::tempdir().unwrap();
+        let store = EventStore::in_directory(directory.path(), 3);
+        let now = 1_700_000_000;
+        let old = now - 30 * SECONDS_PER_DAY;
+        let transaction = |sequence, review: Option<&str>| Transaction {
+            sequence,
+            kind: TransactionKind::Credit,
+            amount: 10,
+            timestamp: old,
+            review: review.map(String::from),
+        };
+        let account = Account {
+            id: 1,
+            balance: 20,
+            holder: String::from("TestName"),
+            transactions: vec![transaction(2, Some("too large")), transaction(3, None)],
+        };
+        fs::write(&store.log_path, "").unwrap();
+
+        store.write_snapshot(3, now, &[account]).unwrap();
+
+        let reopened = Bank::open(store, RuleSet::default()).unwrap();
+        let flagged = reopened.flagged_transactions();
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].1.sequence, 2);
+        assert_eq!(flagged[0].1.review.as_deref(), Some("too large"));
+    }
+}
@@ -6,11 +6,104 @@
                 and to understand how data moves around inside of a Rust application differently than in other languages
 */
 
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+mod dates;
+
+mod events;
+use events::{Event, EventStore, Record};
+
+mod rules;
+use rules::{Decision, RuleSet};
+
+mod reconcile;
+
+mod server;
+
+///The kinds of balance changes that the Bank's rules are checked against.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum TransactionKind {
+    Credit,
+    Debit,
+    TransferIn,
+    TransferOut,
+}
+
+impl TransactionKind {
+    ///Debits and outgoing transfers both take money out of an account, so both count towards withdrawal limits.
+    fn is_withdrawal(&self) -> bool {
+        matches!(self, TransactionKind::Debit | TransactionKind::TransferOut)
+    }
+}
+
+///A record of a transaction that was applied to an account.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Transaction {
+    #[serde(default)]
+    sequence: u64, //the sequence number of the event that applied it, used as the transaction's reference
+    kind: TransactionKind,
+    amount: isize,
+    timestamp: u64,         //seconds since the unix epoch
+    review: Option<String>, //the reason it was flagged by the rules, if it was
+}
+
+///Reasons that the Bank can refuse a transaction.
 #[derive(Debug)]
+enum TransactionError {
+    AccountNotFound(u32),
+    AccountExists(u32),
+    InvalidAmount(isize),
+    InsufficientFunds,
+    BalanceTooLarge,
+    Denied(String),
+    Storage(io::Error), //the event could not be written to the event log, so nothing was changed
+}
+
+impl From<io::Error> for TransactionError {
+    fn from(error: io::Error) -> Self {
+        TransactionError::Storage(error)
+    }
+}
+
+impl fmt::Display for TransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransactionError::AccountNotFound(id) => write!(f, "no account with ID {}", id),
+            TransactionError::AccountExists(id) => {
+                write!(f, "an account with ID {} already exists", id)
+            }
+            TransactionError::InvalidAmount(amount) => write!(f, "invalid amount {}", amount),
+            TransactionError::InsufficientFunds => write!(f, "insufficient funds"),
+            TransactionError::BalanceTooLarge => write!(f, "the balance would be too large"),
+            TransactionError::Denied(reason) => write!(f, "denied: {}", reason),
+            TransactionError::Storage(error) => {
+                write!(f, "could not record transaction: {}", error)
+            }
+        }
+    }
+}
+
+///Seconds since the unix epoch, used to timestamp transactions.
+fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 struct Account {
     id: u32,
     balance: isize, //balance will represent $10.23 as 1023 so integer is used
     holder: String,
+    transactions: Vec<Transaction>,
 }
 
 impl Account {
@@ -20,6 +113,7 @@ impl Account {
             id,
             holder,
             balance: 0,
+            transactions: vec![],
         }
     }
 
@@ -56,18 +150,305 @@ impl Account {
 #[derive(Debug)]
 struct Bank {
     accounts: Vec<Account>,
+    rules: RuleSet,
+    store: Option<EventStore>, //where events are recorded, or None to keep the Bank in memory only
+    sequence: u64,             //the sequence number of the last event applied
 }
 
 impl Bank {
     fn new() -> Self {
-        Bank { accounts: vec![] }
+        Bank::with_rules(RuleSet::default())
+    }
+
+    ///Makes an empty Bank that checks every credit, debit and transfer against the given rules.
+    fn with_rules(rules: RuleSet) -> Self {
+        Bank {
+            accounts: vec![],
+            rules,
+            store: None,
+            sequence: 0,
+        }
+    }
+
+    ///Rebuilds a Bank from its event log, starting from the latest snapshot if there is one.
+    ///Every change made to the returned Bank is appended to the same log.
+    fn open(store: EventStore, rules: RuleSet) -> Result<Self, io::Error> {
+        let mut bank = Bank::with_rules(rules);
+        let mut log_offset = 0;
+
+        if let Some(snapshot) = store.read_snapshot()? {
+            bank.accounts = snapshot
+                .accounts
+                .into_iter()
+                .map(|account| Account {
+                    id: account.id,
+                    holder: account.holder,
+                    balance: account.balance,
+                    transactions: account.recent,
+                })
+                .collect();
+            bank.sequence = snapshot.sequence;
+            log_offset = snapshot.log_offset;
+        }
+
+        for record in store.read_from(log_offset)? {
+            //skipping anything the snapshot already covers makes this safe even if the offset is stale
+            if record.sequence > bank.sequence {
+                bank.apply_event(&record);
+            }
+        }
+
+        bank.store = Some(store);
+        Ok(bank)
+    }
+
+    ///Rebuilds the Bank as it was at the given timestamp by replaying the log from the beginning,
+    ///which allows questions like "what was this balance at the end of last month".
+    fn as_of(store: &EventStore, timestamp: u64) -> Result<Self, io::Error> {
+        let mut bank = Bank::new();
+
+        for record in store.read_from(0)? {
+            if record.timestamp > timestamp {
+                break;
+            }
+            bank.apply_event(&record);
+        }
+
+        Ok(bank)
+    }
+
+    ///Rebuilds the Bank as it was at the end of the given YYYY-MM-DD date (UTC).
+    fn as_of_date(store: &EventStore, date: &str) -> Result<Self, io::Error> {
+        let start_of_day = dates::parse_date(date).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("'{}' is not a YYYY-MM-DD date", date),
+            )
+        })?;
+
+        Bank::as_of(store, start_of_day + dates::SECONDS_PER_DAY - 1)
     }
 
     ///Takes a given Account instance and adds it to the Bank's accounts vector.
+    ///This is not recorded as an event, use `open_account` for a Bank that has an event log.
     fn add_account(&mut self, account: Account) {
         self.accounts.push(account);
     }
 
+    ///Opens a new empty account with the given ID and holder.
+    fn open_account(&mut self, id: u32, holder: String) -> Result<(), TransactionError> {
+        if self.find_account(id).is_ok() {
+            return Err(TransactionError::AccountExists(id));
+        }
+
+        self.record(
+            Event::AccountOpened { id, holder },
+            current_timestamp(),
+            None,
+        )
+    }
+
+    fn find_account(&self, id: u32) -> Result<&Account, TransactionError> {
+        self.accounts
+            .iter()
+            .find(|account| account.id == id)
+            .ok_or(TransactionError::AccountNotFound(id))
+    }
+
+    ///Checks one side of a transaction against the rules without applying it.
+    fn review(
+        &self,
+        id: u32,
+        kind: TransactionKind,
+        amount: isize,
+        now: u64,
+    ) -> Result<Decision, TransactionError> {
+        if amount < 0 {
+            return Err(TransactionError::InvalidAmount(amount));
+        }
+
+        let account = self.find_account(id)?;
+
+        //checked here, before the event is written, since apply_event can't refuse one that is already in the log
+        if kind.is_withdrawal() {
+            if account
+                .balance
+                .checked_sub(amount)
+                .is_none_or(|balance| balance < 0)
+            {
+                return Err(TransactionError::InsufficientFunds);
+            }
+        } else if account.balance.checked_add(amount).is_none() {
+            return Err(TransactionError::BalanceTooLarge);
+        }
+
+        match self.rules.check(account, kind, amount, now) {
+            Decision::Deny(reason) => Err(TransactionError::Denied(reason)),
+            decision => Ok(decision),
+        }
+    }
+
+    ///Writes the event to the log (if there is one) and then applies it.
+    ///Nothing changes if the event couldn't be written, so the log and the Bank never disagree.
+    fn record(
+        &mut self,
+        event: Event,
+        timestamp: u64,
+        decision: Option<Decision>,
+    ) -> Result<(), TransactionError> {
+        let review = match decision {
+            Some(Decision::Flag(reason)) => Some(reason),
+            _ => None,
+        };
+
+        let record = Record {
+            sequence: self.sequence + 1,
+            timestamp,
+            event,
+            review,
+        };
+
+        if let Some(store) = &self.store {
+            store.append(&record)?;
+        }
+
+        self.apply_event(&record);
+
+        if let Some(store) = &self.store {
+            if store.snapshot_due(record.sequence) {
+                //the event is already safely in the log, so a failed snapshot only makes the next load slower
+                if let Err(error) =
+                    store.write_snapshot(record.sequence, record.timestamp, &self.accounts)
+                {
+                    println!("Could not write snapshot: {}", error);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    ///Changes the Bank's state to reflect an event.  This is the only place that balances are changed
+    ///for a Bank's own operations, so replaying the log always ends up with the same accounts.
+    fn apply_event(&mut self, record: &Record) {
+        self.sequence = record.sequence;
+
+        let mut add_transaction = |id: u32, kind: TransactionKind, amount: isize| {
+            if let Some(account) = self.accounts.iter_mut().find(|account| account.id == id) {
+                if kind.is_withdrawal() {
+                    account.balance -= amount;
+                } else {
+                    account.balance += amount;
+                }
+
+                account.transactions.push(Transaction {
+                    sequence: record.sequence,
+                    kind,
+                    amount,
+                    timestamp: record.timestamp,
+                    review: record.review.clone(),
+                });
+            }
+        };
+
+        match &record.event {
+            Event::AccountOpened { id, holder } => self.accounts.push(Account {
+                id: *id,
+                holder: holder.clone(),
+                balance: 0,
+                transactions: vec![],
+            }),
+            Event::FundsCredited { id, amount } => {
+                add_transaction(*id, TransactionKind::Credit, *amount)
+            }
+            Event::FundsDebited { id, amount } => {
+                add_transaction(*id, TransactionKind::Debit, *amount)
+            }
+            Event::TransferCompleted { from, to, amount } => {
+                add_transaction(*from, TransactionKind::TransferOut, *amount);
+                add_transaction(*to, TransactionKind::TransferIn, *amount);
+            }
+        }
+    }
+
+    ///Checks a deposit against the rules and applies it unless it is denied.
+    fn credit(&mut self, id: u32, amount: isize) -> Result<Decision, TransactionError> {
+        let now = current_timestamp();
+        let decision = self.review(id, TransactionKind::Credit, amount, now)?;
+        self.record(
+            Event::FundsCredited { id, amount },
+            now,
+            Some(decision.clone()),
+        )?;
+        Ok(decision)
+    }
+
+    ///Checks a withdrawal against the balance and the rules and applies it unless it is denied.
+    fn debit(&mut self, id: u32, amount: isize) -> Result<Decision, TransactionError> {
+        let now = current_timestamp();
+        let decision = self.review(id, TransactionKind::Debit, amount, now)?;
+        self.record(
+            Event::FundsDebited { id, amount },
+            now,
+            Some(decision.clone()),
+        )?;
+        Ok(decision)
+    }
+
+    ///Moves funds between two accounts.  Both sides are checked before either is applied,
+    ///so a transfer is never half done.
+    fn transfer(
+        &mut self,
+        from: u32,
+        to: u32,
+        amount: isize,
+    ) -> Result<Decision, TransactionError> {
+        let now = current_timestamp();
+        let outgoing = self.review(from, TransactionKind::TransferOut, amount, now)?;
+        let incoming = self.review(to, TransactionKind::TransferIn, amount, now)?;
+
+        //report whichever side was flagged, if either was
+        let decision = match outgoing {
+            Decision::Allow => incoming,
+            _ => outgoing,
+        };
+
+        self.record(
+            Event::TransferCompleted { from, to, amount },
+            now,
+            Some(decision.clone()),
+        )?;
+        Ok(decision)
+    }
+
+    ///Returns the ID of the account and each transaction that the rules flagged for review.
+    ///A flagged transfer is listed once, on the account it came out of.
+    fn flagged_transactions(&self) -> Vec<(u32, &Transaction)> {
+        self.accounts
+            .iter()
+            .flat_map(|account| {
+                account
+                    .transactions
+                    .iter()
+                    .map(move |transaction| (account.id, transaction))
+            })
+            .filter(|(_, transaction)| transaction.review.is_some())
+            .filter(|(_, transaction)| transaction.kind != TransactionKind::TransferIn)
+            .collect()
+    }
+
+    ///Every transaction on an account.  A Bank loaded from a snapshot only has the transactions since the day
+    ///before it, so the rest are read back from the event log.
+    fn history(&self, id: u32) -> Result<Vec<Transaction>, TransactionError> {
+        match &self.store {
+            Some(store) => {
+                let bank = Bank::as_of(store, u64::MAX)?;
+                Ok(bank.find_account(id)?.transactions.clone())
+            }
+            None => Ok(self.find_account(id)?.transactions.clone()),
+        }
+    }
+
     ///Returns the sum of all the accounts in the Bank
     fn sum_accounts(&self) -> isize {
         //I initially did this for loop, but the guide demonstrates using an iterator as shown below this
@@ -115,7 +496,105 @@ impl Bank {
     }
 }
 
+///Reads the rules in rules.conf.  A missing rules.conf means no rules, but one that can't be read or parsed is an error.
+fn load_rules() -> Result<RuleSet, io::Error> {
+    match RuleSet::from_file("rules.conf") {
+        Ok(rules) => Ok(rules),
+        //without a rules file every transaction is allowed, like a Bank made with Bank::new
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(RuleSet::default()),
+        //but a broken one is an error, rather than quietly running with no fraud rules at all
+        Err(error) => Err(io::Error::new(
+            error.kind(),
+            format!("could not load rules.conf: {}", error),
+        )),
+    }
+}
+
+///Loads the bank that the API and reconcile share from bank_events.log, along with the rules in rules.conf.
+fn open_saved_bank() -> Result<Bank, io::Error> {
+    let store = EventStore::new("bank_events.log", "bank_snapshot.json", 10);
+    Bank::open(store, load_rules()?)
+}
+
+///`serve [port]` serves the saved bank as a JSON API on localhost, on port 8080 if none is given.
+fn run_server(args: &[String]) {
+    let port = match args.first().map(|port| port.parse::<u16>()) {
+        None => 8080,
+        Some(Ok(port)) => port,
+        Some(Err(_)) => {
+            println!("Usage: p02-bank serve [port]");
+            return;
+        }
+    };
+
+    let result = open_saved_bank().and_then(|mut bank| server::serve(&mut bank, port));
+
+    if let Err(error) = result {
+        println!("Bank API stopped: {}", error);
+    }
+}
+
+///`reconcile <account id> <statement file> [window days]` matches a CSV or OFX statement against
+///one of the saved bank's accounts, allowing the dates to be 3 days apart if no window is given.
+fn run_reconcile(args: &[String]) {
+    let usage =
+        "Usage: p02-bank reconcile <account id> <statement.csv|statement.ofx> [window days]";
+
+    let (id, path, window_days) = match args {
+        [id, path] => (id.parse::<u32>(), path, Ok(3)),
+        [id, path, window_days] => (id.parse::<u32>(), path, window_days.parse::<u64>()),
+        _ => {
+            println!("{}", usage);
+            return;
+        }
+    };
+    let (Ok(id), Ok(window_days)) = (id, window_days) else {
+        println!("{}", usage);
+        return;
+    };
+    if window_days.checked_mul(dates::SECONDS_PER_DAY).is_none() {
+        println!(
+            "The window can be at most {} days\n{}",
+            u64::MAX / dates::SECONDS_PER_DAY,
+            usage
+        );
+        return;
+    }
+
+    let bank = match open_saved_bank() {
+        Ok(bank) => bank,
+        Err(error) => {
+            println!("Could not load the bank: {}", error);
+            return;
+        }
+    };
+    let transactions = match bank.history(id) {
+        Ok(transactions) => transactions,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+
+    match reconcile::read_statement(path) {
+        Ok(statement) => print!(
+            "{}",
+            reconcile::reconcile(&transactions, statement, window_days).summary()
+        ),
+        Err(error) => println!("Could not read {}: {}", path, error),
+    }
+}
+
 fn main() {
+    //`cargo run -- serve [port]` and `cargo run -- reconcile ...` work with the bank in bank_events.log
+    //instead of running the demo below
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("serve") => return run_server(&args[2..]),
+        Some("reconcile") => return run_reconcile(&args[2..]),
+        _ => {}
+    }
+
     let mut bank = Bank::new();
 
     //putting in a string like "" is seen as &str, or a string slice,
@@ -153,6 +632,67 @@ fn main() {
     //rather than just the account struct, it caused the issue mentioned above to go away
     println!("Bank details:\n {:#?}", bank.accounts_summary());
 
+    //a second bank that checks every transaction against the rules in rules.conf before applying it,
+    //and records every change to an event log so it can be rebuilt from it.  The log is kept in a scratch
+    //directory that is removed at the end, so the demo never touches the bank in bank_events.log
+    let rules = match load_rules() {
+        Ok(rules) => rules,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+    let scratch = env::temp_dir().join(format!("p02-bank-demo-{}", process::id()));
+    if let Err(error) = fs::create_dir_all(&scratch) {
+        println!("Could not create {}: {}", scratch.display(), error);
+        return;
+    }
+    let mut checked_bank = match Bank::open(EventStore::in_directory(&scratch, 10), rules) {
+        Ok(bank) => bank,
+        Err(error) => {
+            println!("Could not load the demo's event log: {}", error);
+            return;
+        }
+    };
+
+    let results = [
+        checked_bank
+            .open_account(1, String::from("TestName"))
+            .map(|_| Decision::Allow),
+        checked_bank
+            .open_account(2, String::from("Mallory"))
+            .map(|_| Decision::Allow),
+        checked_bank.credit(1, 200_000),
+        checked_bank.debit(1, 40_000),
+        checked_bank.debit(1, 20_000),
+        checked_bank.transfer(1, 2, 5_000),
+        checked_bank.debit(1, 500_000),
+    ];
+
+    for result in results {
+        match result {
+            Ok(Decision::Flag(reason)) => println!("Applied, flagged for review: {}", reason),
+            Ok(_) => println!("Applied"),
+            Err(error) => println!("Refused: {}", error),
+        }
+    }
+
+    println!(
+        "Flagged for review: {:#?}",
+        checked_bank.flagged_transactions()
+    );
+
+    //replaying the log only up to the end of a given date shows what the balances were back then
+    let today = dates::format_date(current_timestamp());
+    match Bank::as_of_date(&EventStore::in_directory(&scratch, 10), &today) {
+        Ok(bank) => println!("Balances as of {}: {:#?}", today, bank.accounts_summary()),
+        Err(error) => println!("Could not replay the demo's event log: {}", error),
+    }
+
+    if let Err(error) = fs::remove_dir_all(&scratch) {
+        println!("Could not remove {}: {}", scratch.display(), error);
+    }
+
     /*
 
         The following code is being left as a reminder comment
@@ -181,3 +721,159 @@ fn main() {
         */
     */
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bank_with_two_accounts(rules: &str) -> Bank {
+        let mut bank = Bank::with_rules(RuleSet::parse(rules).unwrap());
+        bank.open_account(1, String::from("TestName")).unwrap();
+        bank.open_account(2, String::from("Jeffy")).unwrap();
+        bank
+    }
+
+    fn balances(bank: &Bank) -> Vec<isize> {
+        bank.accounts
+            .iter()
+            .map(|account| account.balance)
+            .collect()
+    }
+
+    #[test]
+    fn refuses_transactions_it_cannot_apply() {
+        let mut bank = bank_with_two_accounts("");
+        bank.credit(1, 500).unwrap();
+
+        assert!(matches!(
+            bank.open_account(1, String::from("Again")),
+            Err(TransactionError::AccountExists(1))
+        ));
+        assert!(matches!(
+            bank.credit(3, 10),
+            Err(TransactionError::AccountNotFound(3))
+        ));
+        assert!(matches!(
+            bank.credit(1, -10),
+            Err(TransactionError::InvalidAmount(-10))
+        ));
+        assert!(matches!(
+            bank.debit(1, 501),
+            Err(TransactionError::InsufficientFunds)
+        ));
+        assert!(matches!(
+            bank.transfer(1, 3, 100),
+            Err(TransactionError::AccountNotFound(3))
+        ));
+        //none of the refused transactions changed anything
+        assert_eq!(balances(&bank), vec![500, 0]);
+        assert_eq!(bank.sequence, 3);
+
+        //the whole balance can be taken out
+        bank.debit(1, 500).unwrap();
+        assert_eq!(balances(&bank), vec![0, 0]);
+    }
+
+    #[test]
+    fn refuses_a_credit_that_would_overflow_the_balance() {
+        let directory = tempfile::tempdir().unwrap();
+        let store = || EventStore::in_directory(directory.path(), 100);
+
+        let mut bank = Bank::open(store(), RuleSet::default()).unwrap();
+        bank.open_account(1, String::from("TestName")).unwrap();
+        bank.open_account(2, String::from("Jeffy")).unwrap();
+        bank.credit(1, 10).unwrap();
+        bank.credit(2, isize::MAX).unwrap();
+
+        assert!(matches!(
+            bank.credit(1, isize::MAX),
+            Err(TransactionError::BalanceTooLarge)
+        ));
+        assert!(matches!(
+            bank.transfer(2, 1, isize::MAX),
+            Err(TransactionError::BalanceTooLarge)
+        ));
+        assert_eq!(balances(&bank), vec![10, isize::MAX]);
+
+        //nothing was written for the refused credits, so the log still replays
+        let reopened = Bank::open(store(), RuleSet::default()).unwrap();
+        assert_eq!(balances(&reopened), vec![10, isize::MAX]);
+        assert_eq!(reopened.sequence, 4);
+    }
+
+    #[test]
+    fn a_transfer_is_checked_on_both_sides_before_either_is_applied() {
+        let mut bank = bank_with_two_accounts("blocked_holder = Jeffy\nmax_transaction = 300 flag");
+        bank.credit(1, 1000).unwrap();
+
+        match bank.transfer(1, 2, 100) {
+            Err(TransactionError::Denied(reason)) => assert!(reason.contains("Jeffy")),
+            _ => panic!("a transfer to a blocked holder should be denied"),
+        }
+        assert_eq!(balances(&bank), vec![1000, 0]);
+
+        let mut bank = bank_with_two_accounts("max_transaction = 300 flag");
+        bank.credit(1, 1000).unwrap();
+        assert_eq!(bank.transfer(1, 2, 100).unwrap(), Decision::Allow);
+        assert!(matches!(
+            bank.transfer(1, 2, 400).unwrap(),
+            Decision::Flag(_)
+        ));
+        assert_eq!(balances(&bank), vec![500, 500]);
+        assert_eq!(bank.sum_accounts(), 1000);
+        assert_eq!(
+            bank.accounts_summary(),
+            vec![
+                "TestName has a balance of 500",
+                "Jeffy has a balance of 500"
+            ]
+        );
+        assert_eq!(bank.history(2).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn rebuilds_the_bank_as_it_was_on_a_date() {
+        let directory = tempfile::tempdir().unwrap();
+        let store = || EventStore::in_directory(directory.path(), 100);
+
+        let mut bank = Bank::open(store(), RuleSet::default()).unwrap();
+        let day = |date: &str| dates::parse_date(date).unwrap();
+        let events = [
+            (
+                Event::AccountOpened {
+                    id: 1,
+                    holder: String::from("TestName"),
+                },
+                day("2024-01-31"),
+            ),
+            (
+                Event::FundsCredited { id: 1, amount: 100 },
+                day("2024-01-31") + 3_600,
+            ),
+            //the very last second of January still counts as January
+            (
+                Event::FundsCredited { id: 1, amount: 20 },
+                day("2024-02-01") - 1,
+            ),
+            (Event::FundsDebited { id: 1, amount: 50 }, day("2024-02-01")),
+        ];
+        for (event, timestamp) in events {
+            bank.record(event, timestamp, None).unwrap();
+        }
+
+        assert_eq!(
+            balances(&Bank::as_of_date(&store(), "2024-01-31").unwrap()),
+            vec![120]
+        );
+        assert_eq!(
+            balances(&Bank::as_of_date(&store(), "2024-02-01").unwrap()),
+            vec![70]
+        );
+        assert!(Bank::as_of_date(&store(), "2024-01-30")
+            .unwrap()
+            .accounts
+            .is_empty());
+        let error = Bank::as_of_date(&store(), "31/01/2024").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
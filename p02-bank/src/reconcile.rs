@@ -0,0 +1,521 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use super::dates::{self, SECONDS_PER_DAY};
+use super::Transaction;
+
+///One entry from an external bank statement.
+///Amounts are in cents like Account balances, positive for money in and negative for money out.
+#[derive(Debug, Clone)]
+pub struct StatementLine {
+    pub line_number: usize, //where the entry was found in the statement file, for pointing people at it
+    pub date: u64,          //timestamp of the start of the day it was posted
+    pub amount: isize,
+    pub reference: Option<String>,
+    pub description: String,
+}
+
+///The result of matching a statement against an account's transactions.
+#[derive(Debug)]
+pub struct Report<'a> {
+    pub matched: Vec<(StatementLine, &'a Transaction)>,
+    pub missing: Vec<&'a Transaction>, //in the ledger but not on the statement
+    pub unexpected: Vec<StatementLine>, //on the statement but not in the ledger
+}
+
+///Reads a statement file, as OFX if its extension is .ofx or .qfx and as CSV otherwise.
+pub fn read_statement(path: &str) -> Result<Vec<StatementLine>, Error> {
+    let text = fs::read_to_string(path)?;
+
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+
+    match extension.as_str() {
+        "ofx" | "qfx" => parse_ofx(&text),
+        _ => parse_csv(&text),
+    }
+}
+
+///Parses a CSV statement.  The first row must be a header naming the columns, which can be in any order:
+///`date` (YYYY-MM-DD) and `amount` (like -12.34) are required, `reference` and `description` are optional.
+pub fn parse_csv(text: &str) -> Result<Vec<StatementLine>, Error> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    let header = match lines.next() {
+        Some((_, header)) => split_csv_row(header),
+        None => return Ok(vec![]),
+    };
+    let column = |names: &[&str]| {
+        header
+            .iter()
+            .position(|name| names.contains(&name.trim().to_ascii_lowercase().as_str()))
+    };
+
+    let date_column = column(&["date", "posted", "date posted"])
+        .ok_or_else(|| invalid(1, "header has no 'date' column"))?;
+    let amount_column =
+        column(&["amount"]).ok_or_else(|| invalid(1, "header has no 'amount' column"))?;
+    let reference_column = column(&["reference", "ref", "id", "transaction id"]);
+    let description_column = column(&["description", "memo", "name", "payee"]);
+
+    let mut statement = vec![];
+
+    for (index, line) in lines {
+        let line_number = index + 1;
+        let fields = split_csv_row(line);
+        let field = |column: usize| fields.get(column).map(|field| field.trim()).unwrap_or("");
+
+        let date = dates::parse_date(field(date_column)).ok_or_else(|| {
+            invalid(
+                line_number,
+                &format!("'{}' is not a YYYY-MM-DD date", field(date_column)),
+            )
+        })?;
+        let amount = parse_amount(field(amount_column)).ok_or_else(|| {
+            invalid(
+                line_number,
+                &format!("'{}' is not a valid amount", field(amount_column)),
+            )
+        })?;
+
+        statement.push(StatementLine {
+            line_number,
+            date,
+            amount,
+            reference: reference_column
+                .map(field)
+                .filter(|reference| !reference.is_empty())
+                .map(String::from),
+            description: description_column.map(field).unwrap_or("").to_string(),
+        });
+    }
+
+    Ok(statement)
+}
+
+///Parses the <STMTTRN> entries out of an OFX statement, using DTPOSTED, TRNAMT, FITID and NAME or MEMO.
+///This works for both the older SGML style where tags aren't closed and the newer XML style.
+pub fn parse_ofx(text: &str) -> Result<Vec<StatementLine>, Error> {
+    let mut statement = vec![];
+    let mut current: Option<(usize, Vec<(String, String)>)> = None;
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+
+        //a line can hold several tags, so each '<' starts a new tag whose value runs up to the next '<'
+        for part in line.split('<').skip(1) {
+            let (tag, value) = part.split_once('>').unwrap_or((part, ""));
+            let tag = tag.trim().to_ascii_uppercase();
+            let value = value.trim();
+
+            match tag.as_str() {
+                "STMTTRN" => current = Some((line_number, vec![])),
+                "/STMTTRN" => {
+                    if let Some((start, fields)) = current.take() {
+                        statement.push(ofx_transaction(start, &fields)?);
+                    }
+                }
+                _ if !tag.starts_with('/') => {
+                    if let Some((_, fields)) = &mut current {
+                        fields.push((tag, value.to_string()));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if let Some((start, _)) = current {
+        return Err(invalid(start, "<STMTTRN> is never closed"));
+    }
+
+    Ok(statement)
+}
+
+fn ofx_transaction(
+    line_number: usize,
+    fields: &[(String, String)],
+) -> Result<StatementLine, Error> {
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    //DTPOSTED looks like 20261019120000[-5:EST], only the date part is needed
+    let posted =
+        field("DTPOSTED").ok_or_else(|| invalid(line_number, "transaction has no DTPOSTED"))?;
+    let date = posted
+        .get(0..8)
+        .and_then(|date| {
+            let year = date.get(0..4)?.parse().ok()?;
+            let month = date.get(4..6)?.parse().ok()?;
+            let day = date.get(6..8)?.parse().ok()?;
+            dates::timestamp_from_date(year, month, day)
+        })
+        .ok_or_else(|| {
+            invalid(
+                line_number,
+                &format!("'{}' is not a valid DTPOSTED", posted),
+            )
+        })?;
+
+    let amount_text =
+        field("TRNAMT").ok_or_else(|| invalid(line_number, "transaction has no TRNAMT"))?;
+    let amount = parse_amount(amount_text).ok_or_else(|| {
+        invalid(
+            line_number,
+            &format!("'{}' is not a valid TRNAMT", amount_text),
+        )
+    })?;
+
+    Ok(StatementLine {
+        line_number,
+        date,
+        amount,
+        reference: field("FITID").map(String::from),
+        description: field("NAME").or(field("MEMO")).unwrap_or("").to_string(),
+    })
+}
+
+///Matches each statement line to the closest ledger transaction in time that has the same amount and is within
+///`window_days` of the line.  The bank's transactions don't carry the references that statements use, so a line's
+///reference is only shown in the report and never used for matching.  Each transaction is only matched once.
+pub fn reconcile<'a>(
+    transactions: &'a [Transaction],
+    statement: Vec<StatementLine>,
+    window_days: u64,
+) -> Report<'a> {
+    //a window too big to count in seconds is as good as no window at all
+    let window = window_days.saturating_mul(SECONDS_PER_DAY);
+    let mut unmatched: Vec<&Transaction> = transactions.iter().collect();
+    let mut matched = vec![];
+    let mut unexpected = vec![];
+
+    for line in statement {
+        let closest = unmatched
+            .iter()
+            .enumerate()
+            .filter(|(_, transaction)| signed_amount(transaction) == line.amount)
+            .filter(|(_, transaction)| {
+                //the statement only has a date, so compare against the start of the transaction's day
+                let day = transaction.timestamp - transaction.timestamp % SECONDS_PER_DAY;
+                day.abs_diff(line.date) <= window
+            })
+            .min_by_key(|(_, transaction)| transaction.timestamp.abs_diff(line.date))
+            .map(|(index, _)| index);
+
+        match closest {
+            Some(index) => matched.push((line, unmatched.remove(index))),
+            None => unexpected.push(line),
+        }
+    }
+
+    Report {
+        matched,
+        missing: unmatched,
+        unexpected,
+    }
+}
+
+impl Report<'_> {
+    ///Formats the report as plain text, one section each for matched, missing and unexpected entries.
+    pub fn summary(&self) -> String {
+        let mut text = format!("Matched ({}):\n", self.matched.len());
+        for (line, transaction) in &self.matched {
+            text += &format!(
+                "  line {}: {} {} {} matches #{} {:?}\n",
+                line.line_number,
+                dates::format_date(line.date),
+                line.amount,
+                line.description,
+                transaction.sequence,
+                transaction.kind
+            );
+        }
+
+        text += &format!("Missing from statement ({}):\n", self.missing.len());
+        for transaction in &self.missing {
+            text += &format!(
+                "  #{} {} {} {:?}\n",
+                transaction.sequence,
+                dates::format_date(transaction.timestamp),
+                signed_amount(transaction),
+                transaction.kind
+            );
+        }
+
+        text += &format!("Unexpected on statement ({}):\n", self.unexpected.len());
+        for line in &self.unexpected {
+            text += &format!(
+                "  line {}: {} {} {} {}\n",
+                line.line_number,
+                dates::format_date(line.date),
+                line.amount,
+                line.reference.as_deref().unwrap_or("-"),
+                line.description
+            );
+        }
+
+        text
+    }
+}
+
+///The transaction's amount the way a statement shows it, negative when money left the account.
+fn signed_amount(transaction: &Transaction) -> isize {
+    if transaction.kind.is_withdrawal() {
+        -transaction.amount
+    } else {
+        transaction.amount
+    }
+}
+
+///Parses an amount like "1,234.5", "-12.34" or "(12.34)" into cents.
+fn parse_amount(text: &str) -> Option<isize> {
+    let text = text.trim().replace([',', '$'], "");
+    let (negative, text) = match text
+        .strip_prefix('(')
+        .and_then(|text| text.strip_suffix(')'))
+    {
+        Some(inner) => (true, inner.to_string()),
+        None => (false, text),
+    };
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(rest) => (!negative, rest),
+        None => (negative, text.strip_prefix('+').unwrap_or(&text)),
+    };
+
+    let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+    if whole.is_empty() && fraction.is_empty() || fraction.len() > 2 {
+        return None;
+    }
+    if !whole
+        .chars()
+        .chain(fraction.chars())
+        .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let whole: isize = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().ok()?
+    };
+    let fraction: isize = format!("{:0<2}", fraction).parse().ok()?;
+    let cents = whole.checked_mul(100)?.checked_add(fraction)?;
+
+    Some(if negative { -cents } else { cents })
+}
+
+///Splits a CSV row on commas, allowing fields to be wrapped in double quotes that can hold commas
+///and escaped quotes ("").
+fn split_csv_row(row: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = row.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields
+}
+
+fn invalid(line_number: usize, message: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("statement line {}: {}", line_number, message),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TransactionKind;
+
+    const DAY: u64 = SECONDS_PER_DAY;
+    //2026-10-19 00:00:00 UTC
+    const OCT_19: u64 = 1_792_368_000;
+
+    fn transaction(
+        sequence: u64,
+        kind: TransactionKind,
+        amount: isize,
+        timestamp: u64,
+    ) -> Transaction {
+        Transaction {
+            sequence,
+            kind,
+            amount,
+            timestamp,
+            review: None,
+        }
+    }
+
+    fn line(
+        line_number: usize,
+        date: u64,
+        amount: isize,
+        reference: Option<&str>,
+    ) -> StatementLine {
+        StatementLine {
+            line_number,
+            date,
+            amount,
+            reference: reference.map(String::from),
+            description: String::new(),
+        }
+    }
+
+    fn matched_sequences(report: &Report) -> Vec<(usize, u64)> {
+        report
+            .matched
+            .iter()
+            .map(|(line, transaction)| (line.line_number, transaction.sequence))
+            .collect()
+    }
+
+    #[test]
+    fn parses_csv_with_columns_in_any_order() {
+        let text = "Description,Amount,Date,Reference\n\"Coffee, large\",-4.50,2026-10-19,7\nPay,\"1,200\",2026-10-20,\n";
+        let statement = parse_csv(text).unwrap();
+
+        assert_eq!(statement.len(), 2);
+        assert_eq!(statement[0].line_number, 2);
+        assert_eq!(statement[0].date, OCT_19);
+        assert_eq!(statement[0].amount, -450);
+        assert_eq!(statement[0].reference.as_deref(), Some("7"));
+        assert_eq!(statement[0].description, "Coffee, large");
+        assert_eq!(statement[1].amount, 120_000);
+        assert_eq!(statement[1].reference, None);
+    }
+
+    #[test]
+    fn csv_errors_name_the_line() {
+        let error = parse_csv("date,amount\n2026-10-19,1.00\n2026-13-01,1.00").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "statement line 3: '2026-13-01' is not a YYYY-MM-DD date"
+        );
+        //years too large for the calendar arithmetic are refused rather than overflowing
+        for date in ["300000000000-01-01", "9000000000000000000-01-01"] {
+            let error = parse_csv(&format!("date,amount\n{},1.00", date)).unwrap_err();
+            assert!(
+                error.to_string().starts_with("statement line 2:"),
+                "{}",
+                error
+            );
+        }
+
+        let error = parse_csv("when,amount\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "statement line 1: header has no 'date' column"
+        );
+    }
+
+    #[test]
+    fn parses_sgml_and_xml_ofx() {
+        let sgml = "<STMTTRN>\n<TRNTYPE>DEBIT\n<DTPOSTED>20261019120000[-5:EST]\n<TRNAMT>-12.34\n<FITID>42\n<NAME>Shop\n</STMTTRN>";
+        let xml =
+            "<STMTTRN><DTPOSTED>20261019</DTPOSTED><TRNAMT>5</TRNAMT><MEMO>Refund</MEMO></STMTTRN>";
+
+        let statement = parse_ofx(sgml).unwrap();
+        assert_eq!(statement.len(), 1);
+        assert_eq!((statement[0].date, statement[0].amount), (OCT_19, -1234));
+        assert_eq!(statement[0].reference.as_deref(), Some("42"));
+        assert_eq!(statement[0].description, "Shop");
+
+        let statement = parse_ofx(xml).unwrap();
+        assert_eq!(
+            (statement[0].amount, statement[0].description.as_str()),
+            (500, "Refund")
+        );
+
+        assert!(parse_ofx("<STMTTRN><TRNAMT>1").is_err());
+    }
+
+    #[test]
+    fn parses_amounts() {
+        assert_eq!(parse_amount("12"), Some(1200));
+        assert_eq!(parse_amount("-12.3"), Some(-1230));
+        assert_eq!(parse_amount("(1,234.56)"), Some(-123_456));
+        assert_eq!(parse_amount("$.5"), Some(50));
+        assert_eq!(parse_amount("1.234"), None);
+        assert_eq!(parse_amount("abc"), None);
+        assert_eq!(parse_amount(&format!("{}", u64::MAX)), None);
+    }
+
+    #[test]
+    fn each_line_takes_the_closest_unmatched_transaction() {
+        let transactions = [
+            transaction(1, TransactionKind::Debit, 500, OCT_19 + 100),
+            transaction(2, TransactionKind::Debit, 500, OCT_19 + 200),
+        ];
+        //the reference on line 3 looks like transaction 1's sequence number, but references aren't matched on
+        let statement = vec![
+            line(2, OCT_19, -500, None),
+            line(3, OCT_19 + DAY, -500, Some("1")),
+        ];
+
+        let report = reconcile(&transactions, statement, 3);
+
+        assert_eq!(matched_sequences(&report), vec![(2, 1), (3, 2)]);
+        assert!(report.missing.is_empty());
+        assert!(report.unexpected.is_empty());
+    }
+
+    #[test]
+    fn matches_within_the_window_only() {
+        let transactions = [
+            transaction(1, TransactionKind::Credit, 1000, OCT_19),
+            transaction(2, TransactionKind::Credit, 300, OCT_19),
+        ];
+        let statement = vec![
+            line(2, OCT_19 + 2 * DAY, 1000, None),
+            line(3, OCT_19 + 5 * DAY, 300, None),
+            line(4, OCT_19, -300, None),
+        ];
+
+        let report = reconcile(&transactions, statement, 3);
+
+        assert_eq!(matched_sequences(&report), vec![(2, 1)]);
+        assert_eq!(report.missing.len(), 1);
+        assert_eq!(report.missing[0].sequence, 2);
+        let unexpected: Vec<usize> = report
+            .unexpected
+            .iter()
+            .map(|line| line.line_number)
+            .collect();
+        assert_eq!(unexpected, vec![3, 4]);
+    }
+
+    #[test]
+    fn a_huge_window_does_not_overflow() {
+        let transactions = [transaction(1, TransactionKind::Credit, 1000, OCT_19)];
+        let statement = vec![line(2, 0, 1000, None)];
+
+        let report = reconcile(&transactions, statement, u64::MAX);
+
+        assert_eq!(matched_sequences(&report), vec![(2, 1)]);
+    }
+}
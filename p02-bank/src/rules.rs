@@ -0,0 +1,307 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+
+use super::dates::{SECONDS_PER_DAY, SECONDS_PER_MINUTE};
+use super::{Account, TransactionKind};
+
+///The result of checking a transaction against the rules.
+///Flagged transactions are still applied, but are kept aside so someone can review them afterwards.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    Allow,
+    Flag(String),
+    Deny(String),
+}
+
+impl Decision {
+    ///Keeps whichever of the two decisions is more severe, Deny > Flag > Allow, preferring self on a tie.
+    fn or(self, other: Decision) -> Decision {
+        match (&self, &other) {
+            (Decision::Deny(_), _) => self,
+            (_, Decision::Deny(_)) => other,
+            (Decision::Flag(_), _) => self,
+            (_, Decision::Flag(_)) => other,
+            _ => Decision::Allow,
+        }
+    }
+}
+
+///What a rule does when it is broken, written after the value in the config file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Action {
+    Deny,
+    Flag,
+}
+
+#[derive(Debug)]
+enum Rule {
+    MaxTransaction { limit: isize, action: Action },
+    DailyWithdrawalLimit { limit: isize, action: Action },
+    VelocityPerMinute { count: usize, action: Action },
+    BlockedHolder { holder: String, action: Action },
+}
+
+///A list of rules that every credit, debit and transfer is checked against before it is applied.
+///An empty RuleSet allows everything.
+#[derive(Debug, Default)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    ///Reads and parses a rules config file, see `RuleSet::parse` for the format.
+    pub fn from_file(path: &str) -> Result<RuleSet, Error> {
+        let text = fs::read_to_string(path)?;
+        RuleSet::parse(&text)
+    }
+
+    ///Parses rules written one per line as `<rule> = <value> [deny|flag]`, where the action defaults to deny.
+    ///Blank lines and lines starting with '#' are ignored.  Amounts are in cents like Account balances.
+    ///
+    ///Known rules are `max_transaction`, `daily_withdrawal_limit`, `velocity_per_minute` and `blocked_holder`.
+    pub fn parse(text: &str) -> Result<RuleSet, Error> {
+        let mut rules = vec![];
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (name, rest) = line
+                .split_once('=')
+                .ok_or_else(|| invalid(line_number, "expected '<rule> = <value>'"))?;
+
+            //the action is optional, so only split it off when the last word is actually an action
+            let rest = rest.trim();
+            let (value, action) = match rest.rsplit_once(char::is_whitespace) {
+                Some((value, "deny")) => (value.trim(), Action::Deny),
+                Some((value, "flag")) => (value.trim(), Action::Flag),
+                _ => (rest, Action::Deny),
+            };
+
+            let rule = match name.trim() {
+                "max_transaction" => Rule::MaxTransaction {
+                    limit: parse_number(value, line_number)?,
+                    action,
+                },
+                "daily_withdrawal_limit" => Rule::DailyWithdrawalLimit {
+                    limit: parse_number(value, line_number)?,
+                    action,
+                },
+                "velocity_per_minute" => Rule::VelocityPerMinute {
+                    count: parse_number(value, line_number)?,
+                    action,
+                },
+                "blocked_holder" if !value.is_empty() => Rule::BlockedHolder {
+                    holder: value.to_string(),
+                    action,
+                },
+                "blocked_holder" => {
+                    return Err(invalid(line_number, "blocked_holder needs a name"))
+                }
+                other => return Err(invalid(line_number, &format!("unknown rule '{}'", other))),
+            };
+
+            rules.push(rule);
+        }
+
+        Ok(RuleSet { rules })
+    }
+
+    ///Checks a transaction of the given kind and amount against every rule, using the account's
+    ///past transactions for the daily and velocity rules.  Returns the most severe outcome.
+    pub fn check(
+        &self,
+        account: &Account,
+        kind: TransactionKind,
+        amount: isize,
+        now: u64,
+    ) -> Decision {
+        self.rules
+            .iter()
+            .map(|rule| rule.check(account, kind, amount, now))
+            .fold(Decision::Allow, Decision::or)
+    }
+}
+
+impl Rule {
+    fn check(&self, account: &Account, kind: TransactionKind, amount: isize, now: u64) -> Decision {
+        let (broken, action, reason) = match self {
+            Rule::MaxTransaction { limit, action } => (
+                amount > *limit,
+                action,
+                format!(
+                    "amount {} is over the single transaction limit of {}",
+                    amount, limit
+                ),
+            ),
+            Rule::DailyWithdrawalLimit { limit, action } => {
+                let today = now / SECONDS_PER_DAY;
+                let withdrawn_today: isize = account
+                    .transactions
+                    .iter()
+                    .filter(|transaction| transaction.kind.is_withdrawal())
+                    .filter(|transaction| transaction.timestamp / SECONDS_PER_DAY == today)
+                    .map(|transaction| transaction.amount)
+                    .fold(0, isize::saturating_add);
+
+                (
+                    kind.is_withdrawal() && withdrawn_today.saturating_add(amount) > *limit,
+                    action,
+                    format!(
+                        "withdrawing {} would go over the daily limit of {} ({} already withdrawn today)",
+                        amount, limit, withdrawn_today
+                    ),
+                )
+            }
+            Rule::VelocityPerMinute { count, action } => {
+                let recent = account
+                    .transactions
+                    .iter()
+                    .filter(|transaction| transaction.timestamp + SECONDS_PER_MINUTE > now)
+                    .count();
+
+                (
+                    recent >= *count,
+                    action,
+                    format!("more than {} transactions in one minute", count),
+                )
+            }
+            Rule::BlockedHolder { holder, action } => (
+                account.holder == *holder,
+                action,
+                format!("holder {} is blocked", holder),
+            ),
+        };
+
+        match (broken, action) {
+            (false, _) => Decision::Allow,
+            (true, Action::Deny) => Decision::Deny(reason),
+            (true, Action::Flag) => Decision::Flag(reason),
+        }
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, line_number: usize) -> Result<T, Error> {
+    value
+        .parse()
+        .map_err(|_| invalid(line_number, &format!("'{}' is not a valid number", value)))
+}
+
+fn invalid(line_number: usize, message: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("rules line {}: {}", line_number, message),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Transaction;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn account_with(transactions: &[(TransactionKind, isize, u64)]) -> Account {
+        Account {
+            id: 1,
+            balance: 1_000_000,
+            holder: String::from("TestName"),
+            transactions: transactions
+                .iter()
+                .map(|&(kind, amount, timestamp)| Transaction {
+                    sequence: 0,
+                    kind,
+                    amount,
+                    timestamp,
+                    review: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn daily_limit_counts_todays_withdrawals() {
+        let rules = RuleSet::parse("daily_withdrawal_limit = 1000").unwrap();
+        let account = account_with(&[
+            (TransactionKind::Debit, 600, NOW - 60),
+            (TransactionKind::TransferOut, 300, NOW - 120),
+            //deposits and yesterday's withdrawals don't count
+            (TransactionKind::Credit, 5000, NOW - 60),
+            (TransactionKind::Debit, 5000, NOW - SECONDS_PER_DAY),
+        ]);
+
+        assert_eq!(
+            rules.check(&account, TransactionKind::Debit, 100, NOW),
+            Decision::Allow
+        );
+        assert!(matches!(
+            rules.check(&account, TransactionKind::Debit, 101, NOW),
+            Decision::Deny(_)
+        ));
+        assert!(matches!(
+            rules.check(&account, TransactionKind::TransferOut, 101, NOW),
+            Decision::Deny(_)
+        ));
+        assert_eq!(
+            rules.check(&account, TransactionKind::Credit, 5000, NOW),
+            Decision::Allow
+        );
+    }
+
+    #[test]
+    fn daily_limit_does_not_overflow() {
+        let rules = RuleSet::parse("daily_withdrawal_limit = 1000 flag").unwrap();
+        let account = account_with(&[(TransactionKind::Debit, isize::MAX, NOW)]);
+
+        assert!(matches!(
+            rules.check(&account, TransactionKind::Debit, isize::MAX, NOW),
+            Decision::Flag(_)
+        ));
+    }
+
+    #[test]
+    fn velocity_counts_the_last_minute() {
+        let rules = RuleSet::parse("velocity_per_minute = 2 flag").unwrap();
+        let one_recent = account_with(&[
+            (TransactionKind::Credit, 1, NOW - 10),
+            (TransactionKind::Credit, 1, NOW - SECONDS_PER_MINUTE),
+        ]);
+        let two_recent = account_with(&[
+            (TransactionKind::Credit, 1, NOW - 10),
+            (TransactionKind::Debit, 1, NOW - 59),
+        ]);
+
+        assert_eq!(
+            rules.check(&one_recent, TransactionKind::Credit, 1, NOW),
+            Decision::Allow
+        );
+        assert!(matches!(
+            rules.check(&two_recent, TransactionKind::Credit, 1, NOW),
+            Decision::Flag(_)
+        ));
+    }
+
+    #[test]
+    fn deny_wins_over_flag() {
+        let rules = RuleSet::parse("max_transaction = 10 flag\nblocked_holder = TestName").unwrap();
+        let account = account_with(&[]);
+
+        assert!(matches!(
+            rules.check(&account, TransactionKind::Credit, 50, NOW),
+            Decision::Deny(_)
+        ));
+    }
+
+    #[test]
+    fn parse_reports_the_line() {
+        let error = RuleSet::parse("# comment\n\nvelocity_per_minute = lots").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "rules line 3: 'lots' is not a valid number"
+        );
+    }
+}
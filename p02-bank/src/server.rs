@@ -0,0 +1,540 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::{Account, Bank, Decision, TransactionError};
+
+//requests bigger than this are refused rather than read into memory
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+//the same goes for the request line and headers, which are read one line at a time
+const MAX_LINE_SIZE: usize = 8 * 1024;
+const MAX_HEADERS: usize = 100;
+
+///A parsed HTTP request, only as much of one as the bank's routes need.
+struct Request {
+    method: String,
+    path: String,
+    body: String,
+}
+
+///A JSON response and the HTTP status code to send it with.
+struct Response {
+    status: u16,
+    body: serde_json::Value,
+}
+
+impl Response {
+    fn ok(body: serde_json::Value) -> Self {
+        Response { status: 200, body }
+    }
+
+    fn created(body: serde_json::Value) -> Self {
+        Response { status: 201, body }
+    }
+}
+
+///Everything that can go wrong with a request, each one is sent back as a JSON body
+///with a machine readable `error` code and a human readable `message`.
+enum ApiError {
+    BadRequest(String),
+    NotFound(String),
+    MethodNotAllowed,
+    PayloadTooLarge,
+    HeadersTooLarge,
+    Transaction(TransactionError),
+}
+
+impl From<TransactionError> for ApiError {
+    fn from(error: TransactionError) -> Self {
+        ApiError::Transaction(error)
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: &'static str,
+    message: String,
+}
+
+impl ApiError {
+    fn into_response(self) -> Response {
+        let (status, error, message) = match self {
+            ApiError::BadRequest(message) => (400, "bad_request", message),
+            ApiError::NotFound(message) => (404, "not_found", message),
+            ApiError::MethodNotAllowed => (
+                405,
+                "method_not_allowed",
+                String::from("method not allowed"),
+            ),
+            ApiError::PayloadTooLarge => (
+                413,
+                "payload_too_large",
+                String::from("request body is too large"),
+            ),
+            ApiError::HeadersTooLarge => (
+                431,
+                "headers_too_large",
+                String::from("request line or headers are too large"),
+            ),
+            ApiError::Transaction(error) => {
+                let (status, code) = match &error {
+                    TransactionError::AccountNotFound(_) => (404, "account_not_found"),
+                    TransactionError::AccountExists(_) => (409, "account_exists"),
+                    TransactionError::InvalidAmount(_) => (400, "invalid_amount"),
+                    TransactionError::InsufficientFunds => (422, "insufficient_funds"),
+                    TransactionError::BalanceTooLarge => (422, "balance_too_large"),
+                    TransactionError::Denied(_) => (403, "denied"),
+                    TransactionError::Storage(_) => (500, "storage"),
+                };
+                (status, code, error.to_string())
+            }
+        };
+
+        Response {
+            status,
+            body: json!(ErrorBody { error, message }),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct OpenAccount {
+    id: u32,
+    holder: String,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum PostTransaction {
+    Credit { id: u32, amount: isize },
+    Debit { id: u32, amount: isize },
+    Transfer { from: u32, to: u32, amount: isize },
+}
+
+///Serves the bank's JSON API on localhost only, handling one request at a time until the program is stopped.
+///Use port 0 to let the operating system pick a free port.
+pub fn serve(bank: &mut Bank, port: u16) -> Result<(), io::Error> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+    println!("Bank API listening on http://{}", listener.local_addr()?);
+    serve_on(bank, listener)
+}
+
+///Answers the connections made to a listener that is already bound.
+fn serve_on(bank: &mut Bank, listener: TcpListener) -> Result<(), io::Error> {
+    for stream in listener.incoming() {
+        //one bad connection shouldn't stop the server, so errors are only printed
+        match stream {
+            Ok(stream) => {
+                if let Err(error) = handle_connection(bank, stream) {
+                    println!("Connection error: {}", error);
+                }
+            }
+            Err(error) => println!("Connection error: {}", error),
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_connection(bank: &mut Bank, mut stream: TcpStream) -> Result<(), io::Error> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+    let response = match read_request(&mut stream)? {
+        Ok(request) => route(bank, &request).unwrap_or_else(ApiError::into_response),
+        Err(error) => error.into_response(),
+    };
+
+    let body = response.body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason_phrase(response.status),
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+///Reads the request line, the headers and the body.  The outer Result is for the connection failing,
+///the inner one is for a request that arrived but can't be understood.
+fn read_request(stream: &mut TcpStream) -> Result<Result<Request, ApiError>, io::Error> {
+    let mut reader = BufReader::new(stream);
+
+    let Some(request_line) = read_line(&mut reader)? else {
+        return Ok(Err(ApiError::HeadersTooLarge));
+    };
+
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+        _ => {
+            return Ok(Err(ApiError::BadRequest(String::from(
+                "malformed request line",
+            ))))
+        }
+    };
+
+    let mut content_length = 0;
+    for count in 0.. {
+        let Some(header) = read_line(&mut reader)? else {
+            return Ok(Err(ApiError::HeadersTooLarge));
+        };
+        if header.trim().is_empty() {
+            break;
+        }
+        if count == MAX_HEADERS {
+            return Ok(Err(ApiError::HeadersTooLarge));
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = match value.trim().parse() {
+                    Ok(length) => length,
+                    Err(_) => {
+                        return Ok(Err(ApiError::BadRequest(String::from(
+                            "invalid Content-Length",
+                        ))))
+                    }
+                };
+            }
+        }
+    }
+
+    if content_length > MAX_BODY_SIZE {
+        return Ok(Err(ApiError::PayloadTooLarge));
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    match String::from_utf8(body) {
+        Ok(body) => Ok(Ok(Request { method, path, body })),
+        Err(_) => Ok(Err(ApiError::BadRequest(String::from(
+            "body is not valid UTF-8",
+        )))),
+    }
+}
+
+///Reads one line of the request line or headers, or None if it goes on for longer than MAX_LINE_SIZE.
+///An empty string means the connection was closed.
+fn read_line(reader: &mut impl BufRead) -> Result<Option<String>, io::Error> {
+    let mut line = String::new();
+    reader
+        .by_ref()
+        .take(MAX_LINE_SIZE as u64 + 1)
+        .read_line(&mut line)?;
+
+    if line.len() > MAX_LINE_SIZE {
+        Ok(None)
+    } else {
+        Ok(Some(line))
+    }
+}
+
+fn route(bank: &mut Bank, request: &Request) -> Result<Response, ApiError> {
+    //the query string isn't used by any route, so it is ignored rather than treated as part of the path
+    let path = request.path.split('?').next().unwrap_or("");
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["accounts"]) => {
+            let body: OpenAccount = parse_body(&request.body)?;
+            bank.open_account(body.id, body.holder)?;
+            Ok(Response::created(account_json(bank.find_account(body.id)?)))
+        }
+        ("GET", ["accounts"]) => {
+            let accounts: Vec<serde_json::Value> = bank.accounts.iter().map(account_json).collect();
+            Ok(Response::ok(json!(accounts)))
+        }
+        ("GET", ["accounts", id, "balance"]) => {
+            let account = bank.find_account(parse_id(id)?)?;
+            Ok(Response::ok(account_json(account)))
+        }
+        ("GET", ["accounts", id, "statement"]) => {
+            let id = parse_id(id)?;
+            let transactions = bank.history(id)?;
+            let account = bank.find_account(id)?;
+            Ok(Response::ok(json!({
+                "id": account.id,
+                "holder": account.holder,
+                "balance": account.balance,
+                "transactions": transactions,
+            })))
+        }
+        ("POST", ["transactions"]) => {
+            let decision = match parse_body(&request.body)? {
+                PostTransaction::Credit { id, amount } => bank.credit(id, amount)?,
+                PostTransaction::Debit { id, amount } => bank.debit(id, amount)?,
+                PostTransaction::Transfer { from, to, amount } => {
+                    bank.transfer(from, to, amount)?
+                }
+            };
+
+            let body = match decision {
+                Decision::Flag(reason) => json!({ "decision": "flag", "reason": reason }),
+                _ => json!({ "decision": "allow" }),
+            };
+            Ok(Response::created(body))
+        }
+        (_, ["accounts"])
+        | (_, ["accounts", _, "balance"])
+        | (_, ["accounts", _, "statement"])
+        | (_, ["transactions"]) => Err(ApiError::MethodNotAllowed),
+        _ => Err(ApiError::NotFound(format!(
+            "no route for {} {}",
+            request.method, path
+        ))),
+    }
+}
+
+fn account_json(account: &Account) -> serde_json::Value {
+    json!({ "id": account.id, "holder": account.holder, "balance": account.balance })
+}
+
+fn parse_body<'a, T: Deserialize<'a>>(body: &'a str) -> Result<T, ApiError> {
+    serde_json::from_str(body)
+        .map_err(|error| ApiError::BadRequest(format!("invalid JSON body: {}", error)))
+}
+
+fn parse_id(id: &str) -> Result<u32, ApiError> {
+    id.parse()
+        .map_err(|_| ApiError::BadRequest(format!("'{}' is not a valid account ID", id)))
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        422 => "Unprocessable Entity",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use std::thread;
+
+    //starts a server for an empty in-memory bank on a port picked by the operating system
+    fn start_server() -> SocketAddr {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let mut bank = Bank::new();
+            serve_on(&mut bank, listener)
+        });
+        address
+    }
+
+    //sends a raw request and returns the status code and the JSON body of the response
+    fn send_raw(address: SocketAddr, request: &[u8]) -> (u16, serde_json::Value) {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(request).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    fn send(address: SocketAddr, method: &str, path: &str, body: &str) -> (u16, serde_json::Value) {
+        let request = format!(
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        );
+        send_raw(address, request.as_bytes())
+    }
+
+    fn balance(address: SocketAddr, id: u32) -> serde_json::Value {
+        let (status, body) = send(address, "GET", &format!("/accounts/{}/balance", id), "");
+        assert_eq!(status, 200);
+        body["balance"].clone()
+    }
+
+    #[test]
+    fn deposit_withdraw_and_transfer() {
+        let address = start_server();
+
+        let (status, body) = send(
+            address,
+            "POST",
+            "/accounts",
+            r#"{"id": 1, "holder": "TestName"}"#,
+        );
+        assert_eq!(status, 201);
+        assert_eq!(body, json!({ "id": 1, "holder": "TestName", "balance": 0 }));
+        let (status, _) = send(
+            address,
+            "POST",
+            "/accounts",
+            r#"{"id": 2, "holder": "Jeffy"}"#,
+        );
+        assert_eq!(status, 201);
+
+        let (status, body) = send(
+            address,
+            "POST",
+            "/transactions",
+            r#"{"type": "credit", "id": 1, "amount": 1000}"#,
+        );
+        assert_eq!(status, 201);
+        assert_eq!(body, json!({ "decision": "allow" }));
+        assert_eq!(balance(address, 1), 1000);
+
+        let (status, _) = send(
+            address,
+            "POST",
+            "/transactions",
+            r#"{"type": "debit", "id": 1, "amount": 300}"#,
+        );
+        assert_eq!(status, 201);
+        assert_eq!(balance(address, 1), 700);
+
+        let (status, _) = send(
+            address,
+            "POST",
+            "/transactions",
+            r#"{"type": "transfer", "from": 1, "to": 2, "amount": 200}"#,
+        );
+        assert_eq!(status, 201);
+        assert_eq!(balance(address, 1), 500);
+        assert_eq!(balance(address, 2), 200);
+
+        let (status, body) = send(address, "GET", "/accounts/1/statement", "");
+        assert_eq!(status, 200);
+        assert_eq!(body["transactions"].as_array().unwrap().len(), 3);
+
+        let (status, body) = send(address, "GET", "/accounts", "");
+        assert_eq!(status, 200);
+        assert_eq!(body.as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn errors_have_matching_statuses() {
+        let address = start_server();
+        send(
+            address,
+            "POST",
+            "/accounts",
+            r#"{"id": 1, "holder": "TestName"}"#,
+        );
+
+        let cases = [
+            (
+                "POST",
+                "/accounts",
+                r#"{"id": 1, "holder": "Again"}"#,
+                409,
+                "account_exists",
+            ),
+            (
+                "POST",
+                "/transactions",
+                r#"{"type": "debit", "id": 1, "amount": 5}"#,
+                422,
+                "insufficient_funds",
+            ),
+            (
+                "POST",
+                "/transactions",
+                r#"{"type": "credit", "id": 9, "amount": 5}"#,
+                404,
+                "account_not_found",
+            ),
+            (
+                "POST",
+                "/transactions",
+                r#"{"type": "credit", "id": 1, "amount": -5}"#,
+                400,
+                "invalid_amount",
+            ),
+            ("POST", "/transactions", "not json", 400, "bad_request"),
+            ("GET", "/accounts/abc/balance", "", 400, "bad_request"),
+            ("DELETE", "/accounts", "", 405, "method_not_allowed"),
+            ("GET", "/nowhere", "", 404, "not_found"),
+        ];
+
+        for (method, path, body, expected_status, expected_error) in cases {
+            let (status, response) = send(address, method, path, body);
+            assert_eq!(
+                (status, response["error"].as_str().unwrap()),
+                (expected_status, expected_error),
+                "{} {}",
+                method,
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn overflowing_amounts_are_refused_and_the_server_keeps_going() {
+        let address = start_server();
+        send(
+            address,
+            "POST",
+            "/accounts",
+            r#"{"id": 1, "holder": "TestName"}"#,
+        );
+        let credit = |amount: &str| {
+            let body = format!(r#"{{"type": "credit", "id": 1, "amount": {}}}"#, amount);
+            send(address, "POST", "/transactions", &body)
+        };
+
+        assert_eq!(credit("10").0, 201);
+        let (status, body) = credit(&isize::MAX.to_string());
+        assert_eq!(
+            (status, body["error"].as_str()),
+            (422, Some("balance_too_large"))
+        );
+        //too large to be an amount at all
+        let (status, body) = credit("9223372036854775808");
+        assert_eq!((status, body["error"].as_str()), (400, Some("bad_request")));
+
+        assert_eq!(balance(address, 1), 10);
+    }
+
+    #[test]
+    fn oversized_requests_are_refused() {
+        let address = start_server();
+
+        let request = format!(
+            "POST /accounts HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_SIZE + 1
+        );
+        assert_eq!(send_raw(address, request.as_bytes()).0, 413);
+
+        let request = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE_SIZE));
+        assert_eq!(send_raw(address, request.as_bytes()).0, 431);
+
+        let request = format!(
+            "GET /accounts HTTP/1.1\r\nX-Long: {}\r\n\r\n",
+            "a".repeat(MAX_LINE_SIZE)
+        );
+        assert_eq!(send_raw(address, request.as_bytes()).0, 431);
+
+        let request = format!(
+            "GET /accounts HTTP/1.1\r\n{}\r\n",
+            "X-Header: 1\r\n".repeat(MAX_HEADERS + 1)
+        );
+        assert_eq!(send_raw(address, request.as_bytes()).0, 431);
+    }
+}
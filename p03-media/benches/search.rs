@@ -0,0 +1,160 @@
+use std::time::{Duration, Instant};
+
+use p03_media::content::catalog::{Catalog, MediaId};
+use p03_media::content::index::tokenize;
+use p03_media::content::media::{Media, Metadata};
+
+const ITEM_COUNT: u32 = 100_000;
+const RUNS: u32 = 20;
+
+//a small vocabulary so that words are shared between many items, like real titles are
+const WORDS: [&str; 16] = [
+    "lord", "ring", "night", "river", "silver", "garden", "winter", "shadow", "storm", "dragon",
+    "empire", "ocean", "secret", "city", "forest", "crown",
+];
+const NAMES: [&str; 8] = [
+    "tolkien",
+    "austen",
+    "herbert",
+    "le guin",
+    "pratchett",
+    "nolan",
+    "kubrick",
+    "gerwig",
+];
+const TAGS: [&str; 6] = [
+    "classic",
+    "favourite",
+    "signed",
+    "borrowed",
+    "sequel",
+    "award",
+];
+
+fn main() {
+    let started = Instant::now();
+    let catalog = build_catalog();
+    println!(
+        "Built a catalog of {} items in {:?}",
+        catalog.len(),
+        started.elapsed()
+    );
+
+    for query in [
+        "dragon",
+        "silver riv",
+        "tolkien crown",
+        "classic shadow storm",
+        "nothing",
+    ] {
+        let (mut index_results, index_time) = time(|| {
+            catalog
+                .search_text(query)
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect::<Vec<MediaId>>()
+        });
+        let (scan_results, scan_time) = time(|| scan(&catalog, query));
+
+        //the index returns the best match first, the scan goes in ID order, so compare them sorted
+        index_results.sort();
+        assert_eq!(
+            index_results, scan_results,
+            "the index and the linear scan disagree on '{}'",
+            query
+        );
+
+        println!(
+            "{:<22} index: {:>6} results in {:>10.3?}   linear scan: {:>6} results in {:>10.3?}",
+            format!("'{}'", query),
+            index_results.len(),
+            index_time,
+            scan_results.len(),
+            scan_time
+        );
+    }
+}
+
+//items are made from a simple pseudo-random sequence so every run builds the same catalog
+fn build_catalog() -> Catalog {
+    let mut catalog = Catalog::new();
+    let mut seed: u64 = 42;
+    let mut next = |limit: usize| {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (seed >> 33) as usize % limit
+    };
+
+    for number in 0..ITEM_COUNT {
+        let title = format!(
+            "The {} of the {} {}",
+            WORDS[next(WORDS.len())],
+            WORDS[next(WORDS.len())],
+            number
+        );
+        let creator = NAMES[next(NAMES.len())].to_string();
+        let metadata = Metadata {
+            tags: vec![TAGS[next(TAGS.len())].to_string()],
+            ..Metadata::default()
+        };
+
+        let media = if number % 2 == 0 {
+            Media::Book {
+                title,
+                author: creator,
+                metadata,
+            }
+        } else {
+            Media::Movie {
+                title,
+                director: creator,
+                metadata,
+            }
+        };
+        catalog.add_media(media);
+    }
+
+    catalog
+}
+
+//the same search as the index, done by looking at every item: each query word has to be
+//the start of a word in the title, a creator or a tag
+fn scan(catalog: &Catalog, query: &str) -> Vec<MediaId> {
+    let query_words = tokenize(query);
+    if query_words.is_empty() {
+        return vec![];
+    }
+
+    let mut ids: Vec<MediaId> = catalog
+        .iter()
+        .filter(|(_, media)| {
+            let mut words = tokenize(media.title());
+            for creator in media.creators() {
+                words.extend(tokenize(creator));
+            }
+            for tag in &media.metadata().tags {
+                words.extend(tokenize(tag));
+            }
+
+            query_words.iter().all(|query_word| {
+                words
+                    .iter()
+                    .any(|word| word.starts_with(query_word.as_str()))
+            })
+        })
+        .map(|(id, _)| id)
+        .collect();
+    ids.sort();
+    ids
+}
+
+//runs the search several times and returns its result with the average time taken
+fn time<T>(search: impl Fn() -> T) -> (T, Duration) {
+    let mut results = search();
+    let started = Instant::now();
+    for _ in 1..RUNS {
+        results = search();
+    }
+    (results, started.elapsed() / (RUNS - 1))
+}
@@ -0,0 +1,450 @@
+//this was the program's main.rs before the catalog manager took its place, run it with `cargo run --example demo`
+//the learning notes below are kept the way they were written, so the lints they trip are allowed for this file
+#![allow(dead_code, unused_assignments, clippy::empty_line_after_doc_comments, clippy::ptr_arg)]
+use p03_media::content;
+
+use content::media::{Episode, Media, MediaKind, Metadata};
+use content::catalog::{Catalog, MediaId};
+use content::query::Filter;
+use content::dates::Day;
+use content::lending::Library;
+
+
+///Custom 'Option' Enum as demonstrated by guide
+///This one requires a lifetime annotation added to the syntax, 'a ,
+///but the guide doesn't explain it much yet,
+///as they say it will be covered in a later topic of the course
+
+//commenting this enum out and modifying 'get_by_index_custom_option_enum to no longer return 'mighthaveavalue' but return 'Option' instead
+
+// #[derive(Debug)]
+// enum MightHaveAValue<'a> {
+//     ThereIsAValue(&'a Media),
+//     NoValueAvailable,
+// }
+
+//I made this function first as my own guess at how to make a custom Vec::get, before watching the instructor build the function,
+//to see how close my guess would be to the real thing.  The instructor's version of this is done by adding a method onto the Catalog struct
+///Making a custom version of Vec::get to further understand the reason why Vec::get gives Option::Some(value) and Option::None returns
+fn get_by_index_version1(vector: &Vec<Media>, index: usize) -> &Media {
+    &vector[index]
+}
+
+/*
+
+///A custom made enum to demonstrate what is going on when Option::Some() and Option::None are being returned by methods in Rust
+enum CustomOptionVersion2 {
+
+    //This one is something I'm trying to make by guessing how it would be done before seeing how the guide does it
+    //My guess appeared to look about right, but it ran into a problem where the type specified for Some wasn't able to be Some(_)
+    //and using Some(Media) to make it expect the exact type for the practice experiment revealed other issues about it preferring something with a Copy trait
+    //while using Some(&Media) was causing errors talking about how the lifetime might not be appropriate somehow
+    //so it didn't become fully functional and I want to see what the guide does here
+    //UPDATE:  It seemed to have to do with this enum needing a "lifetime annotation" added to it in order to clear some errors talking about lifetime issues
+    Some(Media),
+    None,
+}
+
+fn get_by_index_version2(vector: &Vec<Media>, index: usize) -> CustomOptionVersion2 {
+    if vector.len() > index {
+        CustomOptionVersion2::Some(vector[index])
+    }
+    else {
+        CustomOptionVersion2::None
+    }
+}
+
+*/
+
+fn main() {
+    let audiobook = Media::Audiobook {
+        title: String::from("An Audiobook"),
+        metadata: Metadata::default(),
+    };
+    let good_movie = Media::Movie {
+        title: String::from("Good Movie"),
+        director: String::from("Good Director"),
+        metadata: Metadata {
+            year: Some(1999),
+            genres: vec![String::from("Drama")],
+            duration_minutes: Some(121),
+            language: Some(String::from("English")),
+            ..Metadata::default()
+        },
+    };
+    //'..Metadata::default()' fills in every field that isn't written out with its default value
+    let book = Media::Book {
+        title: String::from("A Book"),
+        author: String::from("Author Authorman"),
+        metadata: Metadata {
+            year: Some(2004),
+            isbn: Some(String::from("978-0-306-40615-7")),
+            tags: vec![String::from("signed copy")],
+            ..Metadata::default()
+        },
+    };
+    //this enum variant used to be created as Media::Podcast(1), in a similar syntax to how it was defined in the Enum block
+    let podcast = Media::Podcast {
+        show: String::from("A Podcast"),
+        episodes: vec![
+            Episode {
+                number: 1,
+                title: String::from("Pilot"),
+                duration_minutes: Some(42),
+            },
+            Episode {
+                number: 2,
+                title: String::from("The Second One"),
+                duration_minutes: None,
+            },
+        ],
+        metadata: Metadata::default(),
+    };
+    //this enum variant used to be created with no curly braces nor parentheses, as Media::Placeholder
+    let placeholder = Media::Placeholder {
+        title: String::from("Something on order"),
+        metadata: Metadata::default(),
+    };
+
+    let mut catalog = Catalog::new();
+
+    //add_media gives back the ID that each item can be found by later, even after other items are removed
+    catalog.add_media(audiobook);
+    let book_id = catalog.add_media(book);
+    let movie_id = catalog.add_media(good_movie);
+    catalog.add_media(podcast);
+    let placeholder_id = catalog.add_media(placeholder);
+
+    println!("Catalog object: {:#?}\n", catalog);
+
+    println!("Catalog item descriptions:");
+    //catalog.iter() gives references to the items along with their IDs, so nothing is moved out of the catalog
+    for (id, media) in catalog.iter() {
+        println!("{}: {}", id, media.description());
+    }
+
+    println!(
+        "\nUsing catalog.get() to show match checking the returned Some() and None Options:"
+    );
+    //Example of using Some() and None match checking when using catalog.get()
+    match catalog.get(book_id) {
+        Option::Some(value) => {
+            println!("Item found: {:#?}", value)
+        }
+        Option::None => {
+            println!("No item at that index")
+        }
+    }
+
+    //it is possible to omit the "Option::" part of the enum variant checking
+    match catalog.get(MediaId(8)) {
+        Some(value) => println!("Value found: {:#?}", value),
+        None => println!("No value found with that ID"),
+    }
+
+    //testing the function I made on my own before seeing the instructor's explanations
+
+    //these function calls return the value and print it nicely
+    // println!("Get_By_Index 1: {:#?}", get_by_index_version1(&catalog.items, 1));
+    // println!("Get_By_Index 1: {:#?}", get_by_index_version1(&catalog.items, 1));
+
+    //this function call causes a panic at runtime due to the index being out of bounds
+    // println!("Get_By_Index 1: {:#?}", get_by_index_version1(&catalog.items, 100));
+
+    //this demonstrates why having an Option return type can be helpful to avoid a panic at runtime
+
+    //using the instructor's example method calls:
+    // println!("Index 1: {:#?}", catalog.get_by_index(1));     //returns &Media
+    // println!("Index 4: {:#?}", catalog.get_by_index(4));     //returns &Media
+    // println!("Index 40: {:#?}", catalog.get_by_index(40));   //causes a panic due to out of bounds index
+
+    println!("\nUsing custom made 'Vec::get' type method with custom made 'Option' type enums:");
+    //using the intructor's example of a method call that returns a custom made enum called MightHaveAValue:
+    println!("Index 1: {:#?}", catalog.get_by_index_custom_option_enum(1)); //returns ThereIsAValue(&'a Media)
+    println!("Index 4: {:#?}", catalog.get_by_index_custom_option_enum(4)); //returns ThereIsAValue(&'a Media)
+    println!("Index 40: {:#?}", catalog.get_by_index_custom_option_enum(40)); //returns NoValueAvailable, doesn't panic
+
+
+    // old block of code that demonstrated the custom enum of MightHaveAValue before replacing it to use Option again
+    // println!("\nGet the actual item with match statement rather than getting the enum:");
+    // match catalog.get_by_index_custom_option_enum(1) {
+    //     MightHaveAValue::ThereIsAValue(value) => println!("Value found: {:#?}", value),
+    //     MightHaveAValue::NoValueAvailable => println!("No value was available at that index")
+    // }
+
+
+    // println!("\nGet the actual item using 'if let' pattern matching:");
+    // if let MightHaveAValue::ThereIsAValue(value) = catalog.get_by_index_custom_option_enum(1) {
+    //     println!("Value found: {:#?}", value)
+    // }
+    // else {
+    //     println!("No value found")
+    // }
+    
+
+    //new versions of the above block of code that use the Option type for its matching
+    println!("\nGet the actual item with match statement rather than getting the enum:");
+    match catalog.get_by_index_custom_option_enum(1) {
+        Some(value) => println!("Value found: {:#?}", value),
+        None => println!("No value was available at that index")
+    }
+
+
+    println!("\nGet the actual item using 'if let' pattern matching:");
+    if let Some(value) = catalog.get_by_index_custom_option_enum(1) {
+        println!("Value found: {:#?}", value)
+    }
+    else {
+        println!("No value found")
+    }
+
+
+
+
+    // ADDITIONAL WAYS TO CHECK OPTION TYPES: 
+    
+    //      .unwrap(), .expect(""), .unwrap_or(&placeholder)
+
+
+    //using .unwrap()
+    //this gets the value if it is a Some(), and panics if it is a None
+
+    
+    let mut item = catalog.get_by_index_custom_option_enum(1);
+    
+    //this prints the item
+    println!("Item at index 1 unwrapped: {:#?}", item.unwrap());
+    
+    item = catalog.get_by_index_custom_option_enum(10);
+    
+    //this panics
+    // println!("Item at index 10 unwrapped: {:#?}", item.unwrap());
+
+
+
+    //using .expect()
+    //this gets the value if there is Some, and if None, it prints a message and panics
+
+    item = catalog.get_by_index_custom_option_enum(1);
+
+    //this prints the item
+    println!("Item at 1 using .expect(): {:#?}", item.expect(".expect() error message: There was no value at the index selected"));
+
+    item = catalog.get_by_index_custom_option_enum(10);
+
+    //this prints a message and then panics
+    // println!("Item at 1 using .expect(): {:#?}", item.expect(".expect() error message: There was no value at the index selected"));
+
+
+    //using .unwrap_or()
+    //this gets the value in the Some() if there is one, or else gives another value if there is a None, and does not panic
+
+    item = catalog.get_by_index_custom_option_enum(1);
+
+    //a Placeholder now needs a title, so the default value is made ahead of time instead of inline as &Media::Placeholder
+    let nothing_found = Media::Placeholder {
+        title: String::from("Nothing found"),
+        metadata: Metadata::default(),
+    };
+
+    //this gives the value in the Some()
+    //this needed to be given a default value that was of type &Media, so I used a reference to a newly created placeholder variant 
+    println!("Index 1 using the .unwrap_or() method: {:#?}", item.unwrap_or(&nothing_found));
+    item = catalog.get_by_index_custom_option_enum(10);
+
+    //this has a None Option type so it gives 'Placeholder' in the output AND DOES NOT PANIC / CRASH THE PROGRAM
+    //this needed to be given a default value that was of type &Media, so I used a reference to a newly created placeholder variant 
+    println!("Index 10 using the .unwrap_or() method: {:#?}", item.unwrap_or(&nothing_found));
+
+
+    //SEARCHING THE CATALOG WITH FILTERS
+
+    println!("\nItems with 'book' in the title:");
+    for (id, media) in catalog.search(&Filter::title_contains("book")) {
+        println!("{}: {}", id, media.description());
+    }
+
+    println!("\nItems by a creator with 'director' in their name:");
+    for (id, media) in catalog.search(&Filter::creator_contains("director")) {
+        println!("{}: {}", id, media.description());
+    }
+
+    //filters can be combined, this finds Books and Movies whose title doesn't contain 'good'
+    let filter = Filter::kind(MediaKind::Book)
+        .or(Filter::kind(MediaKind::Movie))
+        .and(Filter::title_contains("good").not());
+
+    println!("\nBooks and Movies without 'good' in the title:");
+    for (id, media) in catalog.search(&filter) {
+        println!("{}: {}", id, media.description());
+    }
+
+
+    //FULL-TEXT SEARCH
+
+    //unlike the filters this uses the catalog's index, and the words can be the start of words in any order
+    println!("\nFull-text search for 'good dir':");
+    for (id, media) in catalog.search_text("good dir") {
+        println!("{}: {}", id, media.description());
+    }
+
+
+    //UPDATING AND REMOVING ITEMS BY ID
+
+    let old_movie = catalog.update(
+        movie_id,
+        Media::Movie {
+            title: String::from("Good Movie (Director's Cut)"),
+            director: String::from("Good Director"),
+            metadata: Metadata {
+                duration_minutes: Some(148),
+                tags: vec![String::from("extended")],
+                ..Metadata::default()
+            },
+        },
+    );
+    if let (Some(old_movie), Some(new_movie)) = (old_movie, catalog.get(movie_id)) {
+        println!("\nReplaced '{}' with '{}'", old_movie.description(), new_movie.description());
+    }
+
+    //removing an item doesn't change any other item's ID, so book_id still finds the book
+    if let Ok(removed) = catalog.remove(placeholder_id) {
+        println!("Removed '{}', {} items left", removed.description(), catalog.len());
+    }
+    println!("Book is still at ID {}: {:?}", book_id, catalog.get(book_id).map(Media::description));
+    println!("Removed ID {} now finds: {:?}", placeholder_id, catalog.get(placeholder_id));
+
+
+    //SAVING AND LOADING THE CATALOG
+
+    //the file extension picks the format, .csv for CSV (one section per kind of media) and JSON for anything else
+    for path in ["demo_catalog.json", "demo_catalog.csv"] {
+        if let Err(error) = catalog.save(path) {
+            println!("\nCould not save {}: {}", path, error);
+            continue;
+        }
+
+        //loading keeps the IDs from the file, so the same ID still finds the same item
+        match Catalog::load(path) {
+            Ok(loaded) => println!(
+                "\nSaved and loaded {}, book at ID {}: {:?}",
+                path,
+                book_id,
+                loaded.get(book_id).map(Media::description)
+            ),
+            Err(error) => println!("\nCould not load {}: {}", path, error),
+        }
+    }
+
+    //importing adds a file's items as new items with new IDs rather than replacing the catalog
+    match catalog.import_file("demo_catalog.csv") {
+        Ok(ids) => println!("Imported demo_catalog.csv again as new IDs {:?}, {} items now", ids, catalog.len()),
+        Err(error) => println!("Could not import demo_catalog.csv: {}", error),
+    }
+
+    //malformed rows give an error that says which line they are on
+    match Catalog::from_csv("kind,id,title,author,year\nbook,1,A Book,Someone,1999\nbook,2,Another Book,Someone,last year\n") {
+        Ok(_) => println!("Unexpectedly parsed the malformed CSV"),
+        Err(error) => println!("Malformed CSV: {}", error),
+    }
+
+
+    //LENDING ITEMS TO PATRONS
+
+    //the Library keeps track of loans and holds by ID, and is given the catalog to check what an ID is,
+    //and to mark what it lends so the catalog won't remove an item while someone has it
+    let mut library = Library::new();
+    let alice = library.add_patron("Alice");
+    let bob = library.add_patron("Bob");
+    let start = Day::from_date(2026, 10, 1).expect("a real date");
+
+    match library.check_out(&mut catalog, book_id, alice, start) {
+        Ok(loan) => println!("\nLent item {} to patron {} until {}", loan.item, loan.patron, loan.due),
+        Err(error) => println!("\nCould not lend item {}: {}", book_id, error),
+    }
+
+    //Bob can't take the book while Alice has it, but he can get in line for it
+    if let Err(error) = library.check_out(&mut catalog, book_id, bob, start) {
+        println!("Bob can't check out item {}: {}", book_id, error);
+    }
+    if let Ok(place) = library.place_hold(&catalog, book_id, bob) {
+        println!("Bob is number {} in line for item {}", place, book_id);
+    }
+
+    //placeholders stand in for items that aren't on the shelf, so they can't be lent
+    let on_order_id = catalog.add_media(Media::Placeholder {
+        title: String::from("Something else on order"),
+        metadata: Metadata::default(),
+    });
+    if let Err(error) = library.check_out(&mut catalog, on_order_id, bob, start) {
+        println!("Bob can't check out item {}: {}", on_order_id, error);
+    }
+
+    let later = start.plus_days(20).expect("a date that can be stored");
+    print!("{}", library.overdue_report(&catalog, later));
+
+    if let Ok(returned) = library.check_in(&mut catalog, book_id, later) {
+        println!("Item {} came back {} days late, now held for {:?}", returned.loan.item, returned.days_overdue, returned.hold_for);
+    }
+    println!("Item {} is on the shelf: {}", book_id, library.is_available(book_id));
+
+
+    /*
+
+    //--------------------experimenting with checking all values with a loop
+
+        //got unexpected results when using 'for i in [0..5]' vs 'for i in [0,1,2,3,4,5]'
+
+    //for i in [0..5] was intended to produce "value found:" 5 times and "No item.." one time,
+    //but instead it said "Value found" once and showed all items
+    //compiler sees this as Range<usize>
+    for i in [0..5] {
+        match catalog.items.get(i) {
+            Some(value) => println!("Value found: {:#?}", value),
+            None => println!("No item found at that index"),
+        }
+    }
+
+    //writing the for loop in this way did result in either "Value Found" or "No item found.." appearing a total of 6 times
+    //compiler sees this as usize
+    for i in [0,1,2,3,4,5] {
+        match catalog.items.get(i) {
+            Some(value) => println!("Value found: {:#?}", value),
+            None => println!("No item found at index of {}", i)
+        }
+    }
+
+    */
+
+    /*
+
+    //--------------------experimenting with Vec::get and index slicing
+
+    //using catalog.items.get(index) doesn't move the value, and it also wraps the result in 'Some()'
+    println!("Index 1: {:#?}", catalog.items.get(1));
+    println!("Index 1: {:#?}", catalog.items.get(1));
+
+    //using catalog.items.get(100) for an index that is not in the collection, it returns a None value
+    println!("Index 100: {:#?}", catalog.items.get(100));
+
+    //if you want to get all the items rather than one index or a subset, use ..
+    println!("All items: {:#?}", catalog.items.get(..));
+
+    //to get the 3rd item to the last item, use 2..
+    println!("Item index 2 to the end: {:#?}", catalog.items.get(2..));
+
+
+    //there isn't a 'stride' option like in python, but similar functionality would work like this, using iter().step_by()
+    //this example of step_by().collect()::<Vec<_>> no longer has the Option Some() or None appear with it as was seen when using Vec::get
+
+    println!("Every second item: {:#?}", catalog.items.iter().step_by(2).collect::<Vec<_>>());
+
+    //using either <Vec<_>> or <Vec<&Media>> both work, though I don't yet know what the meaning of the former is
+
+    //I checked and <_> means it's a type placeholder that allows the compiler to infer the type based on the context, which it could tell was &Media
+    //however, using just .collect() wasn't enough to be able to infer that it was needing <Vec<&Media>>, and it needed <Vec<_>> to help it infer
+
+    println!("Every second item: {:#?}", catalog.items.iter().step_by(2).collect::<Vec<&Media>>());
+
+    */
+}
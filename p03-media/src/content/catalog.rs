@@ -1,22 +1,313 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::collections::{CollectionEntry, CollectionError, Collections, OnRemove};
+use super::history::{Change, History};
+use super::index::SearchIndex;
 use super::media::Media;
+use super::query::Filter;
+use super::reviews::Reviews;
+
+///The ID that a Media item is given when it is added to a Catalog.
+///IDs are never reused, so an ID keeps pointing at the same item (or at nothing) no matter what else is added or removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MediaId(pub u32);
+
+impl fmt::Display for MediaId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
 
 #[derive(Debug)]
 pub struct Catalog {
-    pub items: Vec<Media>,
+    //a BTreeMap keeps the items in the order they were added, since IDs only ever go up
+    items: BTreeMap<MediaId, Media>,
+    next_id: u32,
+    //kept up to date by every method that adds, changes or removes an item
+    index: SearchIndex,
+    collections: Collections,
+    reviews: Reviews,
+    history: History,
+    //items a Library has lent out, which can't be removed or merged away until they are checked back in
+    lent: BTreeSet<MediaId>,
+}
+
+impl Default for Catalog {
+    fn default() -> Self {
+        Catalog::new()
+    }
 }
 
 impl Catalog {
     pub fn new() -> Self {
-        Catalog { items: vec![] }
+        Catalog {
+            items: BTreeMap::new(),
+            next_id: 1,
+            index: SearchIndex::new(),
+            collections: Collections::default(),
+            reviews: Reviews::default(),
+            history: History::default(),
+            lent: BTreeSet::new(),
+        }
+    }
+
+    ///Makes a Catalog holding items that already have IDs, such as ones loaded from a file.
+    ///New items will be given IDs after `next_id` or after the highest ID given, whichever is higher.
+    ///Returns the ID that is too large if there would be no ID left to give the next new item.
+    pub(super) fn from_items(
+        items: BTreeMap<MediaId, Media>,
+        next_id: u32,
+    ) -> Result<Self, MediaId> {
+        //next_id is handed out as an ID itself, so it needs an ID after it as well
+        let after_highest = match items.keys().next_back() {
+            Some(id) => match id.0.checked_add(1) {
+                Some(after) if after < u32::MAX => after,
+                _ => return Err(*id),
+            },
+            None => 1,
+        };
+        if next_id == u32::MAX {
+            return Err(MediaId(next_id));
+        }
+        let next_id = next_id.max(after_highest);
+
+        let mut index = SearchIndex::new();
+        for (id, media) in &items {
+            index.insert(*id, media);
+        }
+
+        Ok(Catalog {
+            items,
+            next_id,
+            index,
+            collections: Collections::default(),
+            reviews: Reviews::default(),
+            history: History::default(),
+            lent: BTreeSet::new(),
+        })
+    }
+
+    pub(super) fn next_id(&self) -> u32 {
+        self.next_id
+    }
+
+    ///Adds the item and returns the ID it can be looked up by from now on.
+    pub fn add_media(&mut self, media: Media) -> MediaId {
+        let id = MediaId(self.next_id);
+        self.next_id = self
+            .next_id
+            .checked_add(1)
+            .expect("a catalog can't hand out more than u32::MAX IDs");
+        self.history.record(
+            format!("add {}: {}", id, media.title()),
+            vec![Change::Added {
+                id,
+                media: media.clone(),
+            }],
+        );
+        self.index.insert(id, &media);
+        self.items.insert(id, media);
+        id
+    }
+
+    ///Puts back an item that was removed, with the ID it had, when undoing.  Nothing happens if the ID is in use,
+    ///or if it is too large to have been handed out, which can only happen with a history edited by hand.
+    pub(super) fn restore_item(&mut self, id: MediaId, media: Media) {
+        let Some(after) = id.0.checked_add(1) else {
+            return;
+        };
+        if after == u32::MAX || self.items.contains_key(&id) {
+            return;
+        }
+        self.next_id = self.next_id.max(after);
+        self.index.insert(id, &media);
+        self.items.insert(id, media);
+    }
+
+    pub fn get(&self, id: MediaId) -> Option<&Media> {
+        self.items.get(&id)
+    }
+
+    ///Whether a Library has the item lent out, which keeps it from being removed or merged away.
+    pub fn is_lent(&self, id: MediaId) -> bool {
+        self.lent.contains(&id)
+    }
+
+    //only the Library marks items as lent, when it checks them out and back in
+    pub(super) fn set_lent(&mut self, id: MediaId, lent: bool) {
+        if lent {
+            self.lent.insert(id);
+        } else {
+            self.lent.remove(&id);
+        }
+    }
+
+    ///Takes every item out of the catalog, in the order they were added.
+    pub(super) fn into_media(self) -> impl Iterator<Item = Media> {
+        self.items.into_values()
+    }
+
+    ///Replaces the item with the given ID and returns the old one, or returns None and changes nothing if there is no such item.
+    pub fn update(&mut self, id: MediaId, media: Media) -> Option<Media> {
+        let item = self.items.get_mut(&id)?;
+        self.history.record(
+            format!("update {}: {}", id, media.title()),
+            vec![Change::Updated {
+                id,
+                old: Box::new(item.clone()),
+                new: Box::new(media.clone()),
+            }],
+        );
+        self.index.remove(id, item);
+        self.index.insert(id, &media);
+        Some(std::mem::replace(item, media))
+    }
+
+    ///Removes the item with the given ID and returns it.  Other items keep their IDs and the item's reviews are removed with it.
+    ///An item that is still in a collection is not removed, use `remove_with` and `OnRemove::Cascade` to take it out of them too.
+    ///An item that is lent out is never removed, see `Library::remove_item`.
+    pub fn remove(&mut self, id: MediaId) -> Result<Media, CollectionError> {
+        self.remove_with(id, OnRemove::Refuse)
+    }
+
+    ///Removes the item with the given ID and returns it.  If the item is in any collections, `on_remove` decides
+    ///whether it is taken out of them too or whether nothing is removed and the collections are listed in the error.
+    pub fn remove_with(
+        &mut self,
+        id: MediaId,
+        on_remove: OnRemove,
+    ) -> Result<Media, CollectionError> {
+        if !self.items.contains_key(&id) {
+            return Err(CollectionError::ItemNotFound(id));
+        }
+        if self.is_lent(id) {
+            return Err(CollectionError::Lent(id));
+        }
+        self.check_unused(CollectionEntry::Item(id), on_remove)?;
+
+        //what is taken out of collections and reviews is recorded too, so undoing the removal puts it all back
+        let mut changes = self.collection_changes(CollectionEntry::Item(id));
+        self.collections.remove_entry(CollectionEntry::Item(id));
+        self.finish_collection_changes(&mut changes);
+
+        let reviews = self.reviews(id).to_vec();
+        if !reviews.is_empty() {
+            changes.push(Change::Reviews {
+                id,
+                before: reviews,
+                after: vec![],
+            });
+        }
+        self.reviews.remove_item(id);
+
+        let media = self.items.remove(&id).expect("the item was checked above");
+        self.index.remove(id, &media);
+        changes.push(Change::Removed {
+            id,
+            media: media.clone(),
+        });
+        self.history
+            .record(format!("remove {}: {}", id, media.title()), changes);
+        Ok(media)
+    }
+
+    ///A change for every collection the entry is in, holding the collection's entries before it is changed.
+    ///The entries after the change are filled in by `finish_collection_changes` once it has been made.
+    pub(super) fn collection_changes(&self, entry: CollectionEntry) -> Vec<Change> {
+        self.collections
+            .containing(entry)
+            .into_iter()
+            .filter_map(|id| {
+                let before = self.collections.get(id)?.entries.clone();
+                Some(Change::CollectionEntries {
+                    id,
+                    before,
+                    after: vec![],
+                })
+            })
+            .collect()
+    }
+
+    pub(super) fn finish_collection_changes(&self, changes: &mut [Change]) {
+        for change in changes {
+            if let Change::CollectionEntries { id, after, .. } = change {
+                *after = self
+                    .collections
+                    .get(*id)
+                    .map(|collection| collection.entries.clone())
+                    .unwrap_or_default();
+            }
+        }
+    }
+
+    pub fn collections(&self) -> &Collections {
+        &self.collections
+    }
+
+    pub(super) fn collections_mut(&mut self) -> &mut Collections {
+        &mut self.collections
     }
 
-    pub fn add_media(&mut self, media: Media) {
-        self.items.push(media);
+    ///Every review of every item.  Use `reviews` for the reviews of one item.
+    pub fn all_reviews(&self) -> &Reviews {
+        &self.reviews
     }
 
+    pub(super) fn reviews_mut(&mut self) -> &mut Reviews {
+        &mut self.reviews
+    }
+
+    pub(super) fn history_log(&self) -> &History {
+        &self.history
+    }
+
+    pub(super) fn history_log_mut(&mut self) -> &mut History {
+        &mut self.history
+    }
+
+    ///Iterates over every item and its ID, in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = (MediaId, &Media)> {
+        self.items.iter().map(|(id, media)| (*id, media))
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    ///Returns the items that match the filter, along with their IDs.  The items are checked lazily
+    ///as the iterator is used, so taking only the first few results doesn't scan the whole catalog.
+    pub fn search<'a>(
+        &'a self,
+        filter: &'a Filter,
+    ) -> impl Iterator<Item = (MediaId, &'a Media)> + 'a {
+        self.iter().filter(move |(_, media)| filter.matches(media))
+    }
+
+    ///Finds the items whose title, creator or tags contain every word of the query, best match first.
+    ///Words match whole words or the start of words, ignoring case, and a title match ranks above a creator or tag match.
+    ///This uses the catalog's search index, so it stays fast for large catalogs unlike `search`.
+    pub fn search_text(&self, query: &str) -> Vec<(MediaId, &Media)> {
+        self.index
+            .search(query)
+            .into_iter()
+            .filter_map(|(id, _)| self.items.get(&id).map(|media| (id, media)))
+            .collect()
+    }
+
+    //the index based methods below count positions in the order items were added,
+    //so an item's index changes when an earlier item is removed, unlike its ID
+
     //a method that has no error checking and can cause panic at runtime if given out of bounds index
     pub fn get_by_index(&self, index: usize) -> &Media {
-        &self.items[index]
+        self.items.values().nth(index).expect("index out of bounds")
     }
 
     //OLD WAY THAT RETURNED 'MIGHTHAVEAVALUE' AS EXAMPLE OF HOW OPTION WORKS
@@ -35,12 +326,87 @@ impl Catalog {
     pub fn get_by_index_custom_option_enum(&self, index: usize) -> Option<&Media> {
         if index < self.items.len() {
             //we have something to return
-            Some(&self.items[index])
+            self.items.values().nth(index)
         } else {
             //there is no item at this index
             None
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::media::Metadata;
+
+    fn movie(title: &str) -> Media {
+        Media::Movie {
+            title: title.to_string(),
+            director: String::from("Director"),
+            metadata: Metadata::default(),
+        }
+    }
 
+    #[test]
+    fn ids_keep_pointing_at_the_same_item() {
+        let mut catalog = Catalog::new();
+        let first = catalog.add_media(movie("First"));
+        let second = catalog.add_media(movie("Second"));
+        let third = catalog.add_media(movie("Third"));
+        assert_eq!((first, second, third), (MediaId(1), MediaId(2), MediaId(3)));
 
+        assert_eq!(catalog.remove(second).unwrap().title(), "Second");
+        assert!(catalog.get(second).is_none());
+        assert_eq!(catalog.get(third).map(Media::title), Some("Third"));
+        assert_eq!(catalog.len(), 2);
+
+        //the removed item's ID isn't handed out again
+        assert_eq!(catalog.add_media(movie("Fourth")), MediaId(4));
+        let titles: Vec<&str> = catalog.iter().map(|(_, media)| media.title()).collect();
+        assert_eq!(titles, vec!["First", "Third", "Fourth"]);
+        assert_eq!(catalog.get_by_index(1).title(), "Third");
+        assert!(catalog.get_by_index_custom_option_enum(3).is_none());
+    }
+
+    #[test]
+    fn update_replaces_the_item_and_the_indexed_words() {
+        let mut catalog = Catalog::new();
+        let id = catalog.add_media(movie("Alien"));
+
+        let old = catalog.update(id, movie("Heat"));
+        assert_eq!(old.as_ref().map(Media::title), Some("Alien"));
+        assert_eq!(catalog.get(id).map(Media::title), Some("Heat"));
+        //the old title's words are taken out of the search index
+        assert!(catalog.search_text("alien").is_empty());
+        assert_eq!(catalog.search_text("heat")[0].0, id);
+
+        assert!(catalog.update(MediaId(99), movie("Nothing")).is_none());
+        assert!(catalog.get(MediaId(99)).is_none());
+        assert!(matches!(
+            catalog.remove(MediaId(99)),
+            Err(CollectionError::ItemNotFound(MediaId(99)))
+        ));
+    }
+
+    #[test]
+    fn loaded_items_keep_their_ids() {
+        let items = BTreeMap::from([(MediaId(3), movie("Three")), (MediaId(7), movie("Seven"))]);
+        let mut catalog = Catalog::from_items(items, 5).unwrap();
+        assert_eq!(catalog.get(MediaId(7)).map(Media::title), Some("Seven"));
+        assert_eq!(catalog.search_text("three")[0].0, MediaId(3));
+        //new IDs come after the highest one given, even if the saved next ID is lower
+        assert_eq!(catalog.add_media(movie("Eight")), MediaId(8));
+
+        let catalog =
+            Catalog::from_items(BTreeMap::from([(MediaId(2), movie("Two"))]), 10).unwrap();
+        assert_eq!(catalog.next_id(), 10);
+
+        //there has to be an ID left for the next new item
+        let highest = BTreeMap::from([(MediaId(u32::MAX - 1), movie("Last"))]);
+        assert_eq!(
+            Catalog::from_items(highest, 1).map(|catalog| catalog.len()),
+            Err(MediaId(u32::MAX - 1))
+        );
+        assert!(Catalog::from_items(BTreeMap::new(), u32::MAX - 1).is_ok());
+    }
 }
@@ -0,0 +1,553 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::catalog::{Catalog, MediaId};
+
+///The ID a collection is given when it is created.  Like MediaIds, these are never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CollectionId(pub u32);
+
+impl fmt::Display for CollectionId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+///One thing in a collection, either a catalog item or another collection nested inside it.
+//saved as {"item": 3} or {"collection": 2}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollectionEntry {
+    Item(MediaId),
+    Collection(CollectionId),
+}
+
+impl fmt::Display for CollectionEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CollectionEntry::Item(id) => write!(f, "item {}", id),
+            CollectionEntry::Collection(id) => write!(f, "collection {}", id),
+        }
+    }
+}
+
+///A named, ordered list of catalog items and other collections, like a reading list or a watch queue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Collection {
+    pub name: String,
+    pub entries: Vec<CollectionEntry>,
+}
+
+///What to do when something being removed is still in a collection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnRemove {
+    //take it out of every collection it is in
+    Cascade,
+    //leave everything as it is and return CollectionError::InUse
+    Refuse,
+}
+
+///Why a change to the collections was refused.
+#[derive(Debug, PartialEq)]
+pub enum CollectionError {
+    NotFound(CollectionId),
+    ItemNotFound(MediaId),
+    NameTaken(String),
+    //nesting the collection would make it end up inside itself
+    Cycle {
+        parent: CollectionId,
+        child: CollectionId,
+    },
+    PositionOutOfRange {
+        collection: CollectionId,
+        position: usize,
+    },
+    //the item or collection is in these collections, and OnRemove::Refuse was asked for
+    InUse {
+        entry: CollectionEntry,
+        used_by: Vec<CollectionId>,
+    },
+    //the item is lent out by a Library, and removing it would leave the loan pointing at nothing
+    Lent(MediaId),
+}
+
+impl fmt::Display for CollectionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CollectionError::NotFound(id) => write!(f, "there is no collection with ID {}", id),
+            CollectionError::ItemNotFound(id) => write!(f, "there is no item with ID {}", id),
+            CollectionError::NameTaken(name) => {
+                write!(f, "there is already a collection named '{}'", name)
+            }
+            CollectionError::Cycle { parent, child } => {
+                write!(
+                    f,
+                    "collection {} can't go in collection {} because it contains it",
+                    child, parent
+                )
+            }
+            CollectionError::PositionOutOfRange {
+                collection,
+                position,
+            } => {
+                write!(
+                    f,
+                    "collection {} has no entry at position {}",
+                    collection, position
+                )
+            }
+            CollectionError::InUse { entry, used_by } => {
+                let used_by: Vec<String> = used_by.iter().map(|id| id.to_string()).collect();
+                write!(f, "{} is still in collection {}", entry, used_by.join(", "))
+            }
+            CollectionError::Lent(id) => {
+                write!(f, "item {} is lent out and has to be checked in first", id)
+            }
+        }
+    }
+}
+
+///Every collection in a Catalog.  The Catalog makes sure the entries only ever point at items and collections that exist.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Collections {
+    next_id: u32,
+    collections: BTreeMap<CollectionId, Collection>,
+}
+
+impl Default for Collections {
+    fn default() -> Self {
+        Collections {
+            next_id: 1,
+            collections: BTreeMap::new(),
+        }
+    }
+}
+
+impl Collections {
+    pub fn is_empty(&self) -> bool {
+        self.collections.is_empty()
+    }
+
+    pub fn get(&self, id: CollectionId) -> Option<&Collection> {
+        self.collections.get(&id)
+    }
+
+    ///Iterates over every collection and its ID, in the order they were created.
+    pub fn iter(&self) -> impl Iterator<Item = (CollectionId, &Collection)> {
+        self.collections
+            .iter()
+            .map(|(id, collection)| (*id, collection))
+    }
+
+    ///Finds a collection by name, ignoring case.
+    pub fn find(&self, name: &str) -> Option<CollectionId> {
+        self.iter()
+            .find(|(_, collection)| collection.name.eq_ignore_ascii_case(name.trim()))
+            .map(|(id, _)| id)
+    }
+
+    ///The collections that directly contain the entry.
+    pub fn containing(&self, entry: CollectionEntry) -> Vec<CollectionId> {
+        self.iter()
+            .filter(|(_, collection)| collection.entries.contains(&entry))
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    ///Adds a collection with the given ID as it is, returning false if the ID is already used.
+    ///The entries aren't checked, so this is only for building up saved collections to pass to `restore_collections`.
+    pub(super) fn insert(&mut self, id: CollectionId, collection: Collection) -> bool {
+        if self.collections.contains_key(&id) {
+            return false;
+        }
+        self.collections.insert(id, collection);
+        true
+    }
+
+    ///Takes the entry out of every collection.
+    pub(super) fn remove_entry(&mut self, entry: CollectionEntry) {
+        for collection in self.collections.values_mut() {
+            collection.entries.retain(|existing| *existing != entry);
+        }
+    }
+
+    ///Points every reference to one entry at another instead, like when two items are merged.
+    pub(super) fn replace_entry(&mut self, old: CollectionEntry, new: CollectionEntry) {
+        for collection in self.collections.values_mut() {
+            for existing in collection.entries.iter_mut() {
+                if *existing == old {
+                    *existing = new;
+                }
+            }
+            //if the collection already had the new entry, only its first place is kept
+            let mut seen = false;
+            collection.entries.retain(|existing| {
+                let duplicate = *existing == new && seen;
+                seen |= *existing == new;
+                !duplicate
+            });
+        }
+    }
+
+    ///Replaces the entries of a collection as they are, for undoing changes that were checked when they were made.
+    pub(super) fn set_entries(&mut self, id: CollectionId, entries: Vec<CollectionEntry>) {
+        if let Some(collection) = self.collections.get_mut(&id) {
+            collection.entries = entries;
+        }
+    }
+
+    ///Returns whether `target` is `from` or is somewhere inside it, however deeply nested.
+    fn reaches(&self, from: CollectionId, target: CollectionId) -> bool {
+        if from == target {
+            return true;
+        }
+        self.get(from)
+            .map(|collection| {
+                collection.entries.iter().any(|entry| match entry {
+                    CollectionEntry::Collection(child) => self.reaches(*child, target),
+                    CollectionEntry::Item(_) => false,
+                })
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl Catalog {
+    ///Makes a new empty collection.  Names have to be unique, ignoring case.
+    pub fn create_collection(&mut self, name: &str) -> Result<CollectionId, CollectionError> {
+        let name = name.trim();
+        if self.collections().find(name).is_some() {
+            return Err(CollectionError::NameTaken(name.to_string()));
+        }
+
+        let collections = self.collections_mut();
+        let id = CollectionId(collections.next_id);
+        collections.next_id += 1;
+        collections.collections.insert(
+            id,
+            Collection {
+                name: name.to_string(),
+                entries: vec![],
+            },
+        );
+        Ok(id)
+    }
+
+    ///Adds an item or collection to the end of a collection.
+    pub fn add_to_collection(
+        &mut self,
+        collection: CollectionId,
+        entry: CollectionEntry,
+    ) -> Result<(), CollectionError> {
+        let length = self.collection_entries(collection)?.len();
+        self.insert_into_collection(collection, length, entry)
+    }
+
+    ///Puts an item or collection at a position in a collection, moving everything after it along.
+    ///A collection can't be put inside itself or inside anything nested in it.
+    pub fn insert_into_collection(
+        &mut self,
+        collection: CollectionId,
+        position: usize,
+        entry: CollectionEntry,
+    ) -> Result<(), CollectionError> {
+        let length = self.collection_entries(collection)?.len();
+        if position > length {
+            return Err(CollectionError::PositionOutOfRange {
+                collection,
+                position,
+            });
+        }
+
+        match entry {
+            CollectionEntry::Item(id) if self.get(id).is_none() => {
+                return Err(CollectionError::ItemNotFound(id))
+            }
+            CollectionEntry::Collection(child) => {
+                if self.collections().get(child).is_none() {
+                    return Err(CollectionError::NotFound(child));
+                }
+                if self.collections().reaches(child, collection) {
+                    return Err(CollectionError::Cycle {
+                        parent: collection,
+                        child,
+                    });
+                }
+            }
+            CollectionEntry::Item(_) => {}
+        }
+
+        self.entries_mut(collection)?.insert(position, entry);
+        Ok(())
+    }
+
+    ///Moves the entry at one position of a collection to another, keeping the order of everything else.
+    pub fn move_in_collection(
+        &mut self,
+        collection: CollectionId,
+        from: usize,
+        to: usize,
+    ) -> Result<(), CollectionError> {
+        let entries = self.entries_mut(collection)?;
+        for position in [from, to] {
+            if position >= entries.len() {
+                return Err(CollectionError::PositionOutOfRange {
+                    collection,
+                    position,
+                });
+            }
+        }
+
+        let entry = entries.remove(from);
+        entries.insert(to, entry);
+        Ok(())
+    }
+
+    ///Takes the entry at a position out of a collection and returns it.  The item or collection itself isn't removed.
+    pub fn remove_from_collection(
+        &mut self,
+        collection: CollectionId,
+        position: usize,
+    ) -> Result<CollectionEntry, CollectionError> {
+        let entries = self.entries_mut(collection)?;
+        if position >= entries.len() {
+            return Err(CollectionError::PositionOutOfRange {
+                collection,
+                position,
+            });
+        }
+        Ok(entries.remove(position))
+    }
+
+    ///Deletes a collection.  If it is nested in other collections, `on_remove` decides whether it is taken out of them
+    ///or whether nothing is deleted.  Collections nested inside it are not deleted, only their place in it.
+    pub fn remove_collection(
+        &mut self,
+        id: CollectionId,
+        on_remove: OnRemove,
+    ) -> Result<Collection, CollectionError> {
+        if self.collections().get(id).is_none() {
+            return Err(CollectionError::NotFound(id));
+        }
+        self.check_unused(CollectionEntry::Collection(id), on_remove)?;
+
+        let collections = self.collections_mut();
+        collections.remove_entry(CollectionEntry::Collection(id));
+        Ok(collections
+            .collections
+            .remove(&id)
+            .expect("the collection was checked above"))
+    }
+
+    ///Every item in a collection in order, with the items of nested collections in the place the collection is.
+    ///An item that is in the collection more than once is listed each time.
+    pub fn collection_items(&self, id: CollectionId) -> Result<Vec<MediaId>, CollectionError> {
+        let mut items = vec![];
+
+        for entry in self.collection_entries(id)? {
+            match entry {
+                CollectionEntry::Item(item) => items.push(*item),
+                //collections can't contain themselves, so this always ends
+                CollectionEntry::Collection(child) => items.extend(self.collection_items(*child)?),
+            }
+        }
+
+        Ok(items)
+    }
+
+    ///Puts back collections that were saved along with the catalog, checking that every entry points at an item
+    ///or collection that exists and that no collection ends up inside itself.
+    pub(super) fn restore_collections(
+        &mut self,
+        saved: Collections,
+    ) -> Result<(), CollectionError> {
+        let mut entries = vec![];
+
+        //every collection is made first, so entries can refer to collections that come later in the file
+        for (id, collection) in saved.collections {
+            if self
+                .collections()
+                .iter()
+                .any(|(_, existing)| existing.name.eq_ignore_ascii_case(&collection.name))
+            {
+                return Err(CollectionError::NameTaken(collection.name));
+            }
+            entries.push((id, collection.entries));
+            self.collections_mut().collections.insert(
+                id,
+                Collection {
+                    name: collection.name,
+                    entries: vec![],
+                },
+            );
+        }
+
+        for (id, collection_entries) in entries {
+            for entry in collection_entries {
+                self.add_to_collection(id, entry)?;
+            }
+        }
+
+        let after_highest = self
+            .collections()
+            .collections
+            .keys()
+            .next_back()
+            .map(|id| id.0 + 1)
+            .unwrap_or(1);
+        self.collections_mut().next_id = saved.next_id.max(after_highest);
+        Ok(())
+    }
+
+    ///Returns an error listing the collections the entry is in if there are any and `on_remove` is Refuse.
+    pub(super) fn check_unused(
+        &self,
+        entry: CollectionEntry,
+        on_remove: OnRemove,
+    ) -> Result<(), CollectionError> {
+        let used_by = self.collections().containing(entry);
+        if on_remove == OnRemove::Refuse && !used_by.is_empty() {
+            return Err(CollectionError::InUse { entry, used_by });
+        }
+        Ok(())
+    }
+
+    fn collection_entries(
+        &self,
+        id: CollectionId,
+    ) -> Result<&Vec<CollectionEntry>, CollectionError> {
+        self.collections()
+            .get(id)
+            .map(|collection| &collection.entries)
+            .ok_or(CollectionError::NotFound(id))
+    }
+
+    fn entries_mut(
+        &mut self,
+        id: CollectionId,
+    ) -> Result<&mut Vec<CollectionEntry>, CollectionError> {
+        self.collections_mut()
+            .collections
+            .get_mut(&id)
+            .map(|collection| &mut collection.entries)
+            .ok_or(CollectionError::NotFound(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::media::{Media, Metadata};
+
+    fn book(title: &str) -> Media {
+        Media::Book {
+            title: title.to_string(),
+            author: String::from("Author"),
+            metadata: Metadata::default(),
+        }
+    }
+
+    //a catalog with two books, the first of them in a "Reading" list nested inside a "Shelf" collection
+    fn nested_catalog() -> (Catalog, MediaId, CollectionId, CollectionId) {
+        let mut catalog = Catalog::new();
+        let item = catalog.add_media(book("One"));
+        let other = catalog.add_media(book("Two"));
+        let reading = catalog.create_collection("Reading").unwrap();
+        let shelf = catalog.create_collection("Shelf").unwrap();
+        catalog
+            .add_to_collection(reading, CollectionEntry::Item(item))
+            .unwrap();
+        catalog
+            .add_to_collection(shelf, CollectionEntry::Item(other))
+            .unwrap();
+        catalog
+            .add_to_collection(shelf, CollectionEntry::Collection(reading))
+            .unwrap();
+        (catalog, item, reading, shelf)
+    }
+
+    #[test]
+    fn remove_refuses_an_item_in_a_collection() {
+        let (mut catalog, item, reading, _) = nested_catalog();
+
+        assert_eq!(
+            catalog.remove(item),
+            Err(CollectionError::InUse {
+                entry: CollectionEntry::Item(item),
+                used_by: vec![reading],
+            })
+        );
+        assert!(catalog.get(item).is_some());
+        assert_eq!(catalog.collection_entries(reading).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn undoing_a_cascading_remove_puts_the_item_back_in_its_collections() {
+        let (mut catalog, item, reading, shelf) = nested_catalog();
+        let before = catalog.collection_items(shelf).unwrap();
+
+        catalog.remove_with(item, OnRemove::Cascade).unwrap();
+        assert!(catalog.collection_entries(reading).unwrap().is_empty());
+
+        catalog.undo().unwrap();
+        assert_eq!(catalog.collection_items(shelf).unwrap(), before);
+
+        catalog.redo().unwrap();
+        assert!(catalog.get(item).is_none());
+        assert!(catalog.collection_entries(reading).unwrap().is_empty());
+    }
+
+    #[test]
+    fn nested_collections_list_their_items_in_place() {
+        let (catalog, item, _, shelf) = nested_catalog();
+        assert_eq!(
+            catalog.collection_items(shelf).unwrap(),
+            vec![MediaId(2), item]
+        );
+    }
+
+    #[test]
+    fn a_collection_cannot_end_up_inside_itself() {
+        let (mut catalog, _, reading, shelf) = nested_catalog();
+        assert_eq!(
+            catalog.add_to_collection(reading, CollectionEntry::Collection(shelf)),
+            Err(CollectionError::Cycle {
+                parent: reading,
+                child: shelf,
+            })
+        );
+        assert_eq!(
+            catalog.add_to_collection(shelf, CollectionEntry::Collection(shelf)),
+            Err(CollectionError::Cycle {
+                parent: shelf,
+                child: shelf,
+            })
+        );
+    }
+
+    #[test]
+    fn removing_a_nested_collection_follows_on_remove() {
+        let (mut catalog, _, reading, shelf) = nested_catalog();
+
+        assert!(matches!(
+            catalog.remove_collection(reading, OnRemove::Refuse),
+            Err(CollectionError::InUse { .. })
+        ));
+        catalog
+            .remove_collection(reading, OnRemove::Cascade)
+            .unwrap();
+        assert_eq!(catalog.collection_items(shelf).unwrap(), vec![MediaId(2)]);
+    }
+
+    #[test]
+    fn names_are_unique_ignoring_case() {
+        let (mut catalog, ..) = nested_catalog();
+        assert_eq!(
+            catalog.create_collection("reading"),
+            Err(CollectionError::NameTaken(String::from("reading")))
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+//a Day is shown with a four digit year, and larger years would overflow days_from_civil
+const MAX_YEAR: i64 = 9999;
+
+///A calendar date, stored as the number of days since 1970-01-01 so that dates can be compared and
+///have days added to them without needing a date library.  It is shown as YYYY-MM-DD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Day(pub u32);
+
+impl Day {
+    ///Today's date in UTC.
+    pub fn today() -> Day {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        Day((seconds / SECONDS_PER_DAY) as u32)
+    }
+
+    ///Makes a Day from a year, month and day, or returns None if the date doesn't exist or isn't between 1970 and 9999.
+    pub fn from_date(year: i64, month: u32, day: u32) -> Option<Day> {
+        if !(1970..=MAX_YEAR).contains(&year)
+            || !(1..=12).contains(&month)
+            || day == 0
+            || day > days_in_month(year, month)
+        {
+            return None;
+        }
+
+        u32::try_from(days_from_civil(year, month, day))
+            .ok()
+            .map(Day)
+    }
+
+    ///Parses a date written as YYYY-MM-DD.
+    pub fn parse(text: &str) -> Option<Day> {
+        let mut parts = text.trim().splitn(3, '-');
+        let year = parts.next()?.parse().ok()?;
+        let month = parts.next()?.parse().ok()?;
+        let day = parts.next()?.parse().ok()?;
+
+        Day::from_date(year, month, day)
+    }
+
+    ///The day `days` after this one, or None if that is too far away to be stored.
+    pub fn plus_days(self, days: u32) -> Option<Day> {
+        self.0.checked_add(days).map(Day)
+    }
+
+    ///How many days after `earlier` this is, or 0 if it isn't after it.
+    pub fn days_since(self, earlier: Day) -> u32 {
+        self.0.saturating_sub(earlier.0)
+    }
+}
+
+impl fmt::Display for Day {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (year, month, day) = civil_from_days(self.0 as i64);
+        write!(f, "{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+//days_from_civil, civil_from_days and days_in_month are copied from p02-bank/src/dates.rs,
+//keep the two copies the same when changing either of them
+
+///Returns the number of days from 1970-01-01 to the given date, using the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    //shifting the year to start in March puts the leap day at the very end of the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+///Returns the (year, month, day) that the given number of days since 1970-01-01 falls on.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_shows_dates() {
+        for text in ["1970-01-01", "2000-02-29", "2024-12-31", "2100-03-01"] {
+            let day = Day::parse(text).unwrap();
+            assert_eq!(day.to_string(), text);
+        }
+        assert_eq!(Day::parse(" 1970-01-02 "), Some(Day(1)));
+    }
+
+    #[test]
+    fn rejects_dates_that_do_not_exist() {
+        for text in [
+            "2023-02-29",
+            "2100-02-29",
+            "2024-04-31",
+            "2024-13-01",
+            "2024-00-10",
+            "1969-12-31",
+            "10000-01-01",
+            "9000000000000000000-01-01",
+            "2024-1",
+            "today",
+        ] {
+            assert_eq!(Day::parse(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn counts_days_between_dates() {
+        let start = Day::parse("2024-02-27").unwrap();
+        let later = start.plus_days(3).unwrap();
+        assert_eq!(later.to_string(), "2024-03-01");
+        assert_eq!(later.days_since(start), 3);
+        assert_eq!(start.days_since(later), 0);
+        assert_eq!(start.plus_days(u32::MAX), None);
+    }
+}
@@ -0,0 +1,558 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use super::catalog::{Catalog, MediaId};
+use super::collections::CollectionEntry;
+use super::history::Change;
+use super::index::tokenize;
+use super::media::{Media, MediaKind, Metadata};
+use super::sort::strip_article;
+
+//how alike two normalized titles have to be to count as the same, from 0.0 (nothing in common) to 1.0 (identical)
+const TITLE_SIMILARITY: f64 = 0.85;
+//creators are names, which are usually written more consistently than titles, so they have to be closer
+const CREATOR_SIMILARITY: f64 = 0.9;
+
+//how many characters at the start and at the end of a title are used to put items into blocks, see `Block`
+const BLOCK_LENGTH: usize = 3;
+
+///A group of items that might be duplicates of each other.  Only items that share a block are compared, instead of
+///every item with every other one.  Titles that are close enough to count as the same almost never differ at both
+///their start and their end, so each item goes in a block for each, along with blocks for its ISBN and ISAN.
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum Block {
+    TitleStart(MediaKind, String),
+    TitleEnd(MediaKind, String),
+    Isbn(String),
+    Isan(String),
+}
+
+///Two items that look like the same thing, and which one a merge should keep.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    //the item with the richer metadata, which keeps its ID when merged
+    pub keep: MediaId,
+    pub duplicate: MediaId,
+    pub similarity: f64,
+    pub reason: String,
+}
+
+///Why two items couldn't be merged.
+#[derive(Debug, PartialEq)]
+pub enum MergeError {
+    NotFound(MediaId),
+    SameItem(MediaId),
+    DifferentKinds(MediaId, MediaId),
+    //the duplicate is lent out, so it can't be merged away until it is checked back in
+    Lent(MediaId),
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MergeError::NotFound(id) => write!(f, "there is no item with ID {}", id),
+            MergeError::SameItem(id) => write!(f, "can't merge item {} with itself", id),
+            MergeError::DifferentKinds(first, second) => {
+                write!(
+                    f,
+                    "items {} and {} are different kinds of media",
+                    first, second
+                )
+            }
+            MergeError::Lent(id) => {
+                write!(f, "item {} is lent out and has to be checked in first", id)
+            }
+        }
+    }
+}
+
+impl Catalog {
+    ///Finds pairs of items that are probably the same thing entered twice: the same kind of media with
+    ///nearly the same title and creator, or the same ISBN or ISAN.  The most similar pairs come first.
+    pub fn find_duplicates(&self) -> Vec<Suggestion> {
+        //normalizing once up front instead of for every pair, since an item can be compared with many others
+        let items: Vec<(MediaId, &Media, String, Option<String>)> = self
+            .iter()
+            .map(|(id, media)| {
+                (
+                    id,
+                    media,
+                    normalize(media.title()),
+                    media.creator().map(normalize),
+                )
+            })
+            .collect();
+
+        let mut blocks: HashMap<Block, Vec<usize>> = HashMap::new();
+        for (position, (_, media, title, _)) in items.iter().enumerate() {
+            for block in blocks_for(media, title) {
+                blocks.entry(block).or_default().push(position);
+            }
+        }
+
+        let mut suggestions = vec![];
+        //two items can share more than one block but are only compared once
+        let mut compared: HashSet<(usize, usize)> = HashSet::new();
+
+        for positions in blocks.values() {
+            for (index, first_position) in positions.iter().enumerate() {
+                for second_position in &positions[index + 1..] {
+                    if !compared.insert((*first_position, *second_position)) {
+                        continue;
+                    }
+                    let (first_id, first, first_title, first_creator) = &items[*first_position];
+                    let (second_id, second, second_title, second_creator) =
+                        &items[*second_position];
+                    if first.kind() != second.kind() {
+                        continue;
+                    }
+
+                    let Some((similarity, reason)) = compare(
+                        first,
+                        second,
+                        (first_title, first_creator),
+                        (second_title, second_creator),
+                    ) else {
+                        continue;
+                    };
+
+                    let (keep, duplicate) = if richness(second) > richness(first) {
+                        (*second_id, *first_id)
+                    } else {
+                        (*first_id, *second_id)
+                    };
+                    suggestions.push(Suggestion {
+                        keep,
+                        duplicate,
+                        similarity,
+                        reason,
+                    });
+                }
+            }
+        }
+
+        suggestions.sort_by(|first, second| {
+            second
+                .similarity
+                .total_cmp(&first.similarity)
+                .then(first.keep.cmp(&second.keep))
+                .then(first.duplicate.cmp(&second.duplicate))
+        });
+        suggestions
+    }
+
+    ///Merges the duplicate into the kept item and removes the duplicate.  The kept item keeps its ID, takes
+    ///the duplicate's place in any collections and gets its reviews.
+    ///Whichever of the two has the richer metadata provides the title, creator and any field both have filled in,
+    ///fields only one of them has are kept, and lists like genres, tags and podcast episodes are combined.
+    ///A duplicate that is lent out isn't merged, since that would remove it.
+    pub fn merge(&mut self, keep: MediaId, duplicate: MediaId) -> Result<&Media, MergeError> {
+        if keep == duplicate {
+            return Err(MergeError::SameItem(keep));
+        }
+        let kept = self.get(keep).ok_or(MergeError::NotFound(keep))?;
+        let other = self.get(duplicate).ok_or(MergeError::NotFound(duplicate))?;
+        if kept.kind() != other.kind() {
+            return Err(MergeError::DifferentKinds(keep, duplicate));
+        }
+        if self.is_lent(duplicate) {
+            return Err(MergeError::Lent(duplicate));
+        }
+
+        let (richer, poorer) = if richness(other) > richness(kept) {
+            (other, kept)
+        } else {
+            (kept, other)
+        };
+        let merged = merge_media(richer, poorer);
+
+        //everything the merge does is undone together
+        self.batch(&format!("merge {} into {}", duplicate, keep), |catalog| {
+            //collections that had the duplicate get the kept item in its place instead of losing it
+            let mut changes = catalog.collection_changes(CollectionEntry::Item(duplicate));
+            catalog.collections_mut().replace_entry(
+                CollectionEntry::Item(duplicate),
+                CollectionEntry::Item(keep),
+            );
+            catalog.finish_collection_changes(&mut changes);
+
+            let reviews_before = [
+                (keep, catalog.reviews(keep).to_vec()),
+                (duplicate, catalog.reviews(duplicate).to_vec()),
+            ];
+            catalog.reviews_mut().move_reviews(duplicate, keep);
+            for (id, before) in reviews_before {
+                let after = catalog.reviews(id).to_vec();
+                if before != after {
+                    changes.push(Change::Reviews { id, before, after });
+                }
+            }
+
+            catalog.history_log_mut().record(String::new(), changes);
+            catalog.update(keep, merged);
+            catalog
+                .remove(duplicate)
+                .expect("the duplicate's collection entries were just replaced");
+        });
+        Ok(self.get(keep).expect("the kept item was just updated"))
+    }
+}
+
+///The blocks an item goes in, from its normalized title and its standard numbers.
+fn blocks_for(media: &Media, title: &str) -> Vec<Block> {
+    let kind = media.kind();
+    let start: String = strip_article(title).chars().take(BLOCK_LENGTH).collect();
+    let end: String = {
+        let reversed: Vec<char> = title.chars().rev().take(BLOCK_LENGTH).collect();
+        reversed.into_iter().rev().collect()
+    };
+
+    let mut blocks = vec![Block::TitleStart(kind, start), Block::TitleEnd(kind, end)];
+    let metadata = media.metadata();
+    if let Some(isbn) = identifier(&metadata.isbn) {
+        blocks.push(Block::Isbn(isbn));
+    }
+    if let Some(isan) = identifier(&metadata.isan) {
+        blocks.push(Block::Isan(isan));
+    }
+    blocks
+}
+
+///Checks whether two items of the same kind look like duplicates, returning how alike they are and why.
+fn compare(
+    first: &Media,
+    second: &Media,
+    (first_title, first_creator): (&str, &Option<String>),
+    (second_title, second_creator): (&str, &Option<String>),
+) -> Option<(f64, String)> {
+    let (first_metadata, second_metadata) = (first.metadata(), second.metadata());
+
+    //the same standard number is the same work no matter how the title was typed
+    if same_identifier(&first_metadata.isbn, &second_metadata.isbn) {
+        return Some((1.0, String::from("same ISBN")));
+    }
+    if same_identifier(&first_metadata.isan, &second_metadata.isan) {
+        return Some((1.0, String::from("same ISAN")));
+    }
+
+    //the edit distance is at least the difference in length, which is much quicker to check first
+    let (first_length, second_length) = (first_title.chars().count(), second_title.chars().count());
+    let longest = first_length.max(second_length) as f64;
+    if longest > 0.0
+        && 1.0 - first_length.abs_diff(second_length) as f64 / longest < TITLE_SIMILARITY
+    {
+        return None;
+    }
+
+    let title_similarity = similarity(first_title, second_title);
+    if title_similarity < TITLE_SIMILARITY {
+        return None;
+    }
+
+    //items without a creator, like podcasts and audiobooks, are compared on their title alone
+    match (first_creator, second_creator) {
+        (Some(first_creator), Some(second_creator)) => {
+            let creator_similarity = similarity(first_creator, second_creator);
+            if creator_similarity < CREATOR_SIMILARITY {
+                return None;
+            }
+            Some((
+                title_similarity.min(creator_similarity),
+                format!(
+                    "similar title ({:.0}%) and creator",
+                    title_similarity * 100.0
+                ),
+            ))
+        }
+        _ => Some((
+            title_similarity,
+            format!("similar title ({:.0}%)", title_similarity * 100.0),
+        )),
+    }
+}
+
+fn same_identifier(first: &Option<String>, second: &Option<String>) -> bool {
+    match (identifier(first), identifier(second)) {
+        (Some(first), Some(second)) => first == second,
+        _ => false,
+    }
+}
+
+//ISBNs and ISANs are often written with or without dashes and spaces, so only the digits and letters are compared
+fn identifier(text: &Option<String>) -> Option<String> {
+    let digits: String = text
+        .as_deref()?
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_ascii_uppercase();
+    (!digits.is_empty()).then_some(digits)
+}
+
+///Lowercases text and reduces it to its words separated by single spaces, so "The Hobbit: or, There and Back Again"
+///and "the hobbit or there and back again" are the same.
+pub fn normalize(text: &str) -> String {
+    tokenize(text).join(" ")
+}
+
+///How alike two strings are, 1.0 minus the edit distance between them divided by the length of the longer one.
+pub fn similarity(first: &str, second: &str) -> f64 {
+    let longest = first.chars().count().max(second.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+
+    1.0 - edit_distance(first, second) as f64 / longest as f64
+}
+
+///The Levenshtein distance: the fewest single character insertions, deletions and substitutions that turn one string into the other.
+pub fn edit_distance(first: &str, second: &str) -> usize {
+    let second: Vec<char> = second.chars().collect();
+
+    //only the previous row of the table is needed to work out the next one
+    let mut previous: Vec<usize> = (0..=second.len()).collect();
+    let mut current = vec![0; second.len() + 1];
+
+    for (row, first_char) in first.chars().enumerate() {
+        current[0] = row + 1;
+        for (column, second_char) in second.iter().enumerate() {
+            let substitution = previous[column] + usize::from(first_char != *second_char);
+            current[column + 1] = substitution
+                .min(previous[column + 1] + 1)
+                .min(current[column] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[second.len()]
+}
+
+//a rough measure of how much is known about an item, used to decide which of two duplicates to keep
+fn richness(media: &Media) -> usize {
+    let metadata = media.metadata();
+    let filled = [
+        metadata.year.is_some(),
+        metadata.duration_minutes.is_some(),
+        metadata.isbn.is_some(),
+        metadata.isan.is_some(),
+        metadata.language.is_some(),
+        metadata.publisher.is_some(),
+    ]
+    .iter()
+    .filter(|filled| **filled)
+    .count();
+
+    let episodes = match media {
+        Media::Podcast { episodes, .. } => episodes.len(),
+        _ => 0,
+    };
+
+    filled + metadata.genres.len() + metadata.tags.len() + episodes
+}
+
+fn merge_media(richer: &Media, poorer: &Media) -> Media {
+    let mut merged = richer.clone();
+    merge_metadata(merged.metadata_mut(), poorer.metadata());
+
+    if let (
+        Media::Podcast { episodes, .. },
+        Media::Podcast {
+            episodes: other_episodes,
+            ..
+        },
+    ) = (&mut merged, poorer)
+    {
+        for episode in other_episodes {
+            if !episodes
+                .iter()
+                .any(|existing| existing.number == episode.number)
+            {
+                episodes.push(episode.clone());
+            }
+        }
+        episodes.sort_by_key(|episode| episode.number);
+    }
+
+    merged
+}
+
+fn merge_metadata(metadata: &mut Metadata, other: &Metadata) {
+    metadata.year = metadata.year.or(other.year);
+    metadata.duration_minutes = metadata.duration_minutes.or(other.duration_minutes);
+    metadata.isbn = metadata.isbn.take().or_else(|| other.isbn.clone());
+    metadata.isan = metadata.isan.take().or_else(|| other.isan.clone());
+    metadata.language = metadata.language.take().or_else(|| other.language.clone());
+    metadata.publisher = metadata
+        .publisher
+        .take()
+        .or_else(|| other.publisher.clone());
+
+    for (list, other_list) in [
+        (&mut metadata.genres, &other.genres),
+        (&mut metadata.tags, &other.tags),
+    ] {
+        for value in other_list {
+            if !list
+                .iter()
+                .any(|existing| existing.eq_ignore_ascii_case(value))
+            {
+                list.push(value.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(title: &str, author: &str, metadata: Metadata) -> Media {
+        Media::Book {
+            title: title.to_string(),
+            author: author.to_string(),
+            metadata,
+        }
+    }
+
+    fn pairs(catalog: &Catalog) -> Vec<(MediaId, MediaId)> {
+        catalog
+            .find_duplicates()
+            .iter()
+            .map(|suggestion| (suggestion.keep, suggestion.duplicate))
+            .collect()
+    }
+
+    #[test]
+    fn edit_distance_counts_single_character_changes() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("hobbit", "hobit"), 1);
+        assert_eq!(edit_distance("", "dune"), 4);
+        assert_eq!(edit_distance("émile", "emile"), 1);
+        assert_eq!(similarity("dune", "dune"), 1.0);
+    }
+
+    #[test]
+    fn normalize_keeps_only_words() {
+        assert_eq!(
+            normalize("The Hobbit: or, There and  Back Again!"),
+            "the hobbit or there and back again"
+        );
+    }
+
+    #[test]
+    fn finds_typos_at_either_end_of_a_title() {
+        let mut catalog = Catalog::new();
+        let original = catalog.add_media(book(
+            "The Left Hand of Darkness",
+            "Ursula K. Le Guin",
+            Metadata::default(),
+        ));
+        let typo_at_start = catalog.add_media(book(
+            "Teh Left Hand of Darkness",
+            "Ursula K Le Guin",
+            Metadata::default(),
+        ));
+        let typo_at_end = catalog.add_media(book(
+            "The Left Hand of Darknes",
+            "Ursula K. Le Guin",
+            Metadata::default(),
+        ));
+        catalog.add_media(book(
+            "The Right Hand of Light",
+            "Ursula K. Le Guin",
+            Metadata::default(),
+        ));
+
+        //the two typos differ at both ends of the title so they share no block and aren't compared, but both are
+        //still found as duplicates of the original
+        let mut found = pairs(&catalog);
+        found.sort();
+        assert_eq!(
+            found,
+            vec![(original, typo_at_start), (original, typo_at_end)]
+        );
+    }
+
+    #[test]
+    fn the_same_isbn_matches_whatever_the_title() {
+        let mut catalog = Catalog::new();
+        let poorer = catalog.add_media(book(
+            "Dune",
+            "Frank Herbert",
+            Metadata {
+                isbn: Some(String::from("978-0-441-17271-9")),
+                ..Metadata::default()
+            },
+        ));
+        let richer = catalog.add_media(book(
+            "Dune (Dune Chronicles, Book 1)",
+            "Herbert, Frank",
+            Metadata {
+                isbn: Some(String::from("9780441172719")),
+                year: Some(1965),
+                ..Metadata::default()
+            },
+        ));
+
+        let suggestions = catalog.find_duplicates();
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(
+            (suggestions[0].keep, suggestions[0].duplicate),
+            (richer, poorer)
+        );
+        assert_eq!(suggestions[0].reason, "same ISBN");
+    }
+
+    #[test]
+    fn different_kinds_and_creators_are_not_duplicates() {
+        let mut catalog = Catalog::new();
+        catalog.add_media(book("Dune", "Frank Herbert", Metadata::default()));
+        catalog.add_media(book("Dune", "Brian Herbert Jr", Metadata::default()));
+        catalog.add_media(Media::Movie {
+            title: String::from("Dune"),
+            director: String::from("Frank Herbert"),
+            metadata: Metadata::default(),
+        });
+
+        assert!(catalog.find_duplicates().is_empty());
+    }
+
+    #[test]
+    fn merge_combines_metadata_and_undoes_in_one_step() {
+        let mut catalog = Catalog::new();
+        let keep = catalog.add_media(book(
+            "Dune",
+            "Frank Herbert",
+            Metadata {
+                year: Some(1965),
+                genres: vec![String::from("Sci-Fi")],
+                ..Metadata::default()
+            },
+        ));
+        let duplicate = catalog.add_media(book(
+            "Dune!",
+            "Frank Herbert",
+            Metadata {
+                language: Some(String::from("en")),
+                genres: vec![String::from("Classic")],
+                ..Metadata::default()
+            },
+        ));
+        let before: Vec<Media> = catalog.iter().map(|(_, media)| media.clone()).collect();
+
+        let merged = catalog.merge(keep, duplicate).unwrap().metadata().clone();
+        assert_eq!(merged.year, Some(1965));
+        assert_eq!(merged.language.as_deref(), Some("en"));
+        assert_eq!(merged.genres, vec!["Sci-Fi", "Classic"]);
+        assert!(catalog.get(duplicate).is_none());
+
+        catalog.undo().unwrap();
+        let after: Vec<Media> = catalog.iter().map(|(_, media)| media.clone()).collect();
+        assert_eq!(after, before);
+        assert_eq!(
+            catalog.merge(keep, keep).map(|_| ()),
+            Err(MergeError::SameItem(keep))
+        );
+    }
+}
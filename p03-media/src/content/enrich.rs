@@ -0,0 +1,457 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+
+use super::catalog::{Catalog, MediaId};
+use super::isbn::{Isbn, IsbnError};
+use super::media::Media;
+use super::storage::{malformed, read_csv_records, ImportError};
+
+//authors in a CSV dump are written in one cell separated by this, like genres and tags in catalog CSV files
+const AUTHOR_SEPARATOR: char = ';';
+
+///What a metadata dump knows about one book.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BookRecord {
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    pub year: Option<u16>,
+    pub publisher: Option<String>,
+}
+
+///Book details by ISBN, read from a local file such as an Open Library data dump, for filling in what books in a
+///Catalog are missing.  Nothing is ever fetched over the network.
+#[derive(Debug, Default)]
+pub struct MetadataDump {
+    records: HashMap<Isbn, BookRecord>,
+    //records without any valid ISBN can't be looked up, so they are left out and only counted
+    skipped: usize,
+}
+
+///What `Catalog::enrich` did.
+#[derive(Debug, Default)]
+pub struct Enrichment {
+    //the books that were changed, with the names of the fields that were filled in
+    pub filled: Vec<(MediaId, Vec<&'static str>)>,
+    //books with a valid ISBN that the dump has nothing new about
+    pub unchanged: Vec<MediaId>,
+    pub not_found: Vec<MediaId>,
+    pub invalid: Vec<(MediaId, IsbnError)>,
+}
+
+impl MetadataDump {
+    ///Reads a dump from a file, as CSV if the path ends in .csv and as JSON otherwise.
+    pub fn load(path: &str) -> Result<MetadataDump, ImportError> {
+        let text = fs::read_to_string(path)?;
+        let is_csv = Path::new(path)
+            .extension()
+            .map(|extension| extension.eq_ignore_ascii_case("csv"))
+            .unwrap_or(false);
+
+        if is_csv {
+            MetadataDump::from_csv(&text)
+        } else {
+            MetadataDump::from_json(&text)
+        }
+    }
+
+    ///Reads either a JSON array of records or one record per line.  A line can also be an Open Library dump line,
+    ///with tab separated columns and the record as JSON in the last one.
+    ///A record is an object with Open Library's field names, like "isbn_13", "isbn_10", "title", "authors",
+    ///"publishers" and "publish_date", or the simpler "isbn", "author", "publisher" and "year".
+    pub fn from_json(text: &str) -> Result<MetadataDump, ImportError> {
+        let mut dump = MetadataDump::default();
+
+        if text.trim_start().starts_with('[') {
+            let records: Vec<Value> = serde_json::from_str(text)?;
+            for record in &records {
+                dump.add(json_isbns(record), json_record(record));
+            }
+            return Ok(dump);
+        }
+
+        for (index, line) in text.lines().enumerate() {
+            let json = line.rsplit('\t').next().unwrap_or(line).trim();
+            if json.is_empty() {
+                continue;
+            }
+
+            let record: Value = serde_json::from_str(json)
+                .map_err(|error| malformed(index + 1, error.to_string()))?;
+            if !record.is_object() {
+                return Err(malformed(
+                    index + 1,
+                    String::from("a record has to be a JSON object"),
+                ));
+            }
+            dump.add(json_isbns(&record), json_record(&record));
+        }
+
+        Ok(dump)
+    }
+
+    ///Reads CSV with a header row naming its columns: "isbn" (or "isbn_13" and "isbn_10"), and any of "title",
+    ///"author" (several separated by ';'), "year" and "publisher".
+    pub fn from_csv(text: &str) -> Result<MetadataDump, ImportError> {
+        let mut records = read_csv_records(text)?.into_iter();
+        let Some((_, header)) = records.next() else {
+            return Ok(MetadataDump::default());
+        };
+        let header: Vec<String> = header
+            .iter()
+            .map(|column| column.trim().to_lowercase())
+            .collect();
+        if !header.iter().any(|column| column.starts_with("isbn")) {
+            return Err(malformed(
+                1,
+                String::from("the header row has no isbn column"),
+            ));
+        }
+
+        let mut dump = MetadataDump::default();
+        for (line, record) in records {
+            if record.iter().all(|field| field.trim().is_empty()) {
+                continue;
+            }
+
+            let cell = |column: &str| -> Option<String> {
+                let index = header.iter().position(|name| name == column)?;
+                Some(record.get(index)?.trim().to_string()).filter(|value| !value.is_empty())
+            };
+
+            let year = match cell("year") {
+                Some(year) => Some(
+                    year.parse()
+                        .map_err(|_| malformed(line, format!("'{}' is not a valid year", year)))?,
+                ),
+                None => None,
+            };
+            let authors = cell("author")
+                .or_else(|| cell("authors"))
+                .map(|authors| {
+                    authors
+                        .split(AUTHOR_SEPARATOR)
+                        .map(|author| author.trim().to_string())
+                        .filter(|author| !author.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            let isbns = ["isbn", "isbn_13", "isbn_10"]
+                .into_iter()
+                .filter_map(cell)
+                .collect();
+            let record = BookRecord {
+                title: cell("title"),
+                authors,
+                year,
+                publisher: cell("publisher"),
+            };
+            dump.add(isbns, record);
+        }
+
+        Ok(dump)
+    }
+
+    pub fn get(&self, isbn: &Isbn) -> Option<&BookRecord> {
+        self.records.get(isbn)
+    }
+
+    ///The number of ISBNs that can be looked up.  A record with both an ISBN-10 and an ISBN-13 counts once.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    ///The number of records that were left out because none of their ISBNs were valid.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    //dumps often list the same edition more than once, the first record for an ISBN is the one that is kept
+    fn add(&mut self, isbns: Vec<String>, record: BookRecord) {
+        let isbns: Vec<Isbn> = isbns
+            .iter()
+            .filter_map(|isbn| Isbn::parse(isbn).ok())
+            .collect();
+        if isbns.is_empty() {
+            self.skipped += 1;
+            return;
+        }
+
+        for isbn in isbns {
+            self.records.entry(isbn).or_insert_with(|| record.clone());
+        }
+    }
+}
+
+impl Catalog {
+    ///Fills in the author, year and publisher of every Book that has an ISBN but is missing some of them, from the
+    ///dump's record for that ISBN.  Fields that are already filled in are never changed.  The ISBN can be in either
+    ///its 10 or 13 digit form in the catalog and in the dump, and books whose ISBN is invalid are listed in the result.
+    pub fn enrich(&mut self, dump: &MetadataDump) -> Enrichment {
+        self.batch("enrich from a metadata dump", |catalog| {
+            catalog.enrich_books(dump)
+        })
+    }
+
+    fn enrich_books(&mut self, dump: &MetadataDump) -> Enrichment {
+        let mut enrichment = Enrichment::default();
+        let books: Vec<(MediaId, String)> = self
+            .iter()
+            .filter(|(_, media)| matches!(media, Media::Book { .. }))
+            .filter_map(|(id, media)| media.metadata().isbn.clone().map(|isbn| (id, isbn)))
+            .collect();
+
+        for (id, isbn) in books {
+            let isbn = match Isbn::parse(&isbn) {
+                Ok(isbn) => isbn,
+                Err(error) => {
+                    enrichment.invalid.push((id, error));
+                    continue;
+                }
+            };
+            let Some(record) = dump.get(&isbn) else {
+                enrichment.not_found.push(id);
+                continue;
+            };
+
+            let mut book = self
+                .get(id)
+                .expect("the ID was just taken from the catalog")
+                .clone();
+            let filled = fill_in(&mut book, record);
+            if filled.is_empty() {
+                enrichment.unchanged.push(id);
+            } else {
+                self.update(id, book);
+                enrichment.filled.push((id, filled));
+            }
+        }
+
+        enrichment
+    }
+}
+
+//fills in the fields the book is missing and returns their names
+fn fill_in(book: &mut Media, record: &BookRecord) -> Vec<&'static str> {
+    let mut filled = vec![];
+
+    if let Media::Book { author, .. } = book {
+        if author.trim().is_empty() && !record.authors.is_empty() {
+            *author = record.authors.join(", ");
+            filled.push("author");
+        }
+    }
+
+    let metadata = book.metadata_mut();
+    if metadata.year.is_none() && record.year.is_some() {
+        metadata.year = record.year;
+        filled.push("year");
+    }
+    if metadata.publisher.is_none() && record.publisher.is_some() {
+        metadata.publisher = record.publisher.clone();
+        filled.push("publisher");
+    }
+
+    filled
+}
+
+//every ISBN a JSON record lists, each field being either one ISBN or a list of them
+fn json_isbns(record: &Value) -> Vec<String> {
+    ["isbn_13", "isbn_10", "isbn"]
+        .into_iter()
+        .flat_map(|field| json_strings(record.get(field)))
+        .collect()
+}
+
+fn json_record(record: &Value) -> BookRecord {
+    //Open Library editions list authors as objects, which only have a name once the author records are joined in
+    let authors = match record.get("authors") {
+        Some(Value::Array(authors)) => authors
+            .iter()
+            .filter_map(|author| match author {
+                Value::String(name) => Some(name.clone()),
+                _ => author.get("name").and_then(Value::as_str).map(String::from),
+            })
+            .collect(),
+        _ => json_strings(record.get("author_name").or_else(|| record.get("author"))),
+    };
+
+    let year = match record
+        .get("year")
+        .or_else(|| record.get("first_publish_year"))
+    {
+        Some(Value::Number(year)) => year.as_u64().and_then(|year| u16::try_from(year).ok()),
+        Some(Value::String(year)) => year_in(year),
+        _ => record
+            .get("publish_date")
+            .and_then(Value::as_str)
+            .and_then(year_in),
+    };
+
+    let publisher = json_strings(record.get("publishers").or_else(|| record.get("publisher")))
+        .into_iter()
+        .next();
+
+    BookRecord {
+        title: record
+            .get("title")
+            .and_then(Value::as_str)
+            .map(String::from),
+        authors: authors
+            .into_iter()
+            .map(|author| author.trim().to_string())
+            .filter(|author| !author.is_empty())
+            .collect(),
+        year,
+        publisher,
+    }
+}
+
+fn json_strings(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::String(text)) => vec![text.clone()],
+        Some(Value::Array(values)) => values
+            .iter()
+            .filter_map(Value::as_str)
+            .map(String::from)
+            .collect(),
+        _ => vec![],
+    }
+}
+
+//publish dates are free text like "1965", "June 1965" or "1965-08-01", so the first four digit number is taken as the year
+fn year_in(date: &str) -> Option<u16> {
+    date.split(|c: char| !c.is_ascii_digit())
+        .find(|part| part.len() == 4)
+        .and_then(|year| year.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::media::Metadata;
+
+    fn book(title: &str, author: &str, isbn: &str) -> Media {
+        Media::Book {
+            title: title.to_string(),
+            author: author.to_string(),
+            metadata: Metadata {
+                isbn: Some(isbn.to_string()),
+                ..Metadata::default()
+            },
+        }
+    }
+
+    fn record(dump: &MetadataDump, isbn: &str) -> BookRecord {
+        dump.get(&Isbn::parse(isbn).unwrap()).cloned().unwrap()
+    }
+
+    #[test]
+    fn reads_json_arrays_lines_and_open_library_dumps() {
+        let array = r#"[{"isbn": "0-306-40615-2", "title": "Measuring", "author": "Ann Smith", "year": 1993,
+            "publisher": "Plenum"}, {"title": "no isbn"}]"#;
+        let dump = MetadataDump::from_json(array).unwrap();
+        assert_eq!((dump.len(), dump.skipped()), (1, 1));
+        assert_eq!(
+            record(&dump, "9780306406157"),
+            BookRecord {
+                title: Some("Measuring".to_string()),
+                authors: vec!["Ann Smith".to_string()],
+                year: Some(1993),
+                publisher: Some("Plenum".to_string()),
+            }
+        );
+
+        let lines = "/type/edition\t/books/OL1M\t3\t2010-01-01\t{\"isbn_13\": [\"9780804429573\"], \
+            \"isbn_10\": [\"080442957X\"], \"authors\": [{\"name\": \"Jo Doe\"}, \"Sam Roe\"], \
+            \"publishers\": [\"Ungar\", \"Other\"], \"publish_date\": \"June 1977\"}\n\n\
+            {\"isbn\": \"979-10-90636-07-1\", \"author_name\": [\"Lee\"], \"first_publish_year\": \"2001\"}\n";
+        let dump = MetadataDump::from_json(lines).unwrap();
+        //both ISBNs of the first record are the same book
+        assert_eq!(dump.len(), 2);
+        let first = record(&dump, "080442957X");
+        assert_eq!(first.authors, vec!["Jo Doe", "Sam Roe"]);
+        assert_eq!(
+            (first.year, first.publisher.as_deref()),
+            (Some(1977), Some("Ungar"))
+        );
+        assert_eq!(record(&dump, "9791090636071").year, Some(2001));
+
+        let error = MetadataDump::from_json("{\"isbn\": \"0306406152\"}\n[1]\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 2: a record has to be a JSON object"
+        );
+        assert!(MetadataDump::from_json("{oops")
+            .unwrap_err()
+            .to_string()
+            .starts_with("line 1: "));
+    }
+
+    #[test]
+    fn reads_csv_with_named_columns() {
+        let csv = "Title,ISBN,Author,Year,Publisher\n\
+            Measuring,0-306-40615-2,Ann Smith; Bo Lin,1993,\n\
+            ,,,,\n\
+            Bad,12345,,,\n";
+        let dump = MetadataDump::from_csv(csv).unwrap();
+        assert_eq!((dump.len(), dump.skipped()), (1, 1));
+        let measuring = record(&dump, "0306406152");
+        assert_eq!(measuring.authors, vec!["Ann Smith", "Bo Lin"]);
+        assert_eq!((measuring.year, measuring.publisher), (Some(1993), None));
+
+        let error = MetadataDump::from_csv("isbn,year\n0306406152,soon\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 2: 'soon' is not a valid year");
+        let error = MetadataDump::from_csv("title,year\nDune,1965\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 1: the header row has no isbn column"
+        );
+        assert!(MetadataDump::from_csv("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn fills_in_only_what_books_are_missing() {
+        let dump = MetadataDump::from_json(
+            r#"[{"isbn": "9780306406157", "author": "Ann Smith", "year": 1993, "publisher": "Plenum"},
+                {"isbn": "9780804429573", "author": "Jo Doe", "year": 1977}]"#,
+        )
+        .unwrap();
+
+        let mut catalog = Catalog::new();
+        let missing = catalog.add_media(book("Measuring", "", "0-306-40615-2"));
+        let mut tales = book("Tales", "Someone Else", "978-0-8044-2957-3");
+        tales.metadata_mut().year = Some(1980);
+        let complete = catalog.add_media(tales);
+        let unknown = catalog.add_media(book("Unknown", "", "979-10-90636-07-1"));
+        let invalid = catalog.add_media(book("Typo", "", "0-306-40615-3"));
+
+        let enrichment = catalog.enrich(&dump);
+        assert_eq!(
+            enrichment.filled,
+            vec![(missing, vec!["author", "year", "publisher"])]
+        );
+        assert_eq!(enrichment.unchanged, vec![complete]);
+        assert_eq!(enrichment.not_found, vec![unknown]);
+        assert_eq!(
+            enrichment.invalid,
+            vec![(invalid, IsbnError::WrongCheckDigit)]
+        );
+
+        let measuring = catalog.get(missing).unwrap();
+        assert_eq!(measuring.creator(), Some("Ann Smith"));
+        assert_eq!(measuring.metadata().publisher.as_deref(), Some("Plenum"));
+        assert_eq!(catalog.get(complete).unwrap().metadata().year, Some(1980));
+
+        //the whole enrichment is undone in one step
+        assert!(catalog.undo().is_some());
+        assert_eq!(catalog.get(missing).unwrap().creator(), Some(""));
+    }
+}
@@ -0,0 +1,379 @@
+use serde::{Deserialize, Serialize};
+
+use super::catalog::{Catalog, MediaId};
+use super::collections::{CollectionEntry, CollectionId, OnRemove};
+use super::media::Media;
+use super::reviews::Review;
+
+//only this many of the latest changes can be undone, so the history doesn't grow forever in a catalog that is used for years
+const MAX_HISTORY: usize = 100;
+//each change holds whole copies of items, so the older ChangeSets are also dropped once there are more than this
+//many changes in all, except for the latest one so that even a very large import can still be undone
+const MAX_HISTORY_CHANGES: usize = 10_000;
+
+///One thing that was changed in a Catalog, with what is needed to change it back and to do it again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    Added {
+        id: MediaId,
+        media: Media,
+    },
+    //boxed so that every Change isn't the size of two Media
+    Updated {
+        id: MediaId,
+        old: Box<Media>,
+        new: Box<Media>,
+    },
+    Removed {
+        id: MediaId,
+        media: Media,
+    },
+    //what removing or merging items did to the collections they were in and to their reviews
+    CollectionEntries {
+        id: CollectionId,
+        before: Vec<CollectionEntry>,
+        after: Vec<CollectionEntry>,
+    },
+    Reviews {
+        id: MediaId,
+        before: Vec<Review>,
+        after: Vec<Review>,
+    },
+}
+
+///Changes that are undone and redone together: a single add, update or removal, or everything done by one import.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangeSet {
+    pub description: String,
+    pub changes: Vec<Change>,
+}
+
+///The changes made to a Catalog that can be undone, and the undone ones that can be redone.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct History {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    done: Vec<ChangeSet>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    undone: Vec<ChangeSet>,
+    //the changes made so far inside `Catalog::batch`, which are recorded as one ChangeSet when it ends
+    #[serde(skip)]
+    batch: Option<ChangeSet>,
+    //set while undoing and redoing, whose changes to the catalog are not new changes
+    #[serde(skip)]
+    paused: bool,
+}
+
+impl History {
+    pub fn is_empty(&self) -> bool {
+        self.done.is_empty() && self.undone.is_empty()
+    }
+
+    ///Records changes as one ChangeSet, or as part of the batch if one is running.  Any undone changes can no
+    ///longer be redone, since they might not make sense after the new ones.
+    pub(super) fn record(&mut self, description: String, changes: Vec<Change>) {
+        if self.paused || changes.is_empty() {
+            return;
+        }
+
+        match &mut self.batch {
+            Some(batch) => batch.changes.extend(changes),
+            None => self.push(ChangeSet {
+                description,
+                changes,
+            }),
+        }
+    }
+
+    ///Every item kept in the changes, so that loading can read the ones of kinds from outside this crate.
+    pub(super) fn media_mut(&mut self) -> impl Iterator<Item = &mut Media> {
+        self.done
+            .iter_mut()
+            .chain(self.undone.iter_mut())
+            .flat_map(|changes| changes.changes.iter_mut())
+            .flat_map(|change| match change {
+                Change::Added { media, .. } | Change::Removed { media, .. } => vec![media],
+                Change::Updated { old, new, .. } => vec![old.as_mut(), new.as_mut()],
+                Change::CollectionEntries { .. } | Change::Reviews { .. } => vec![],
+            })
+    }
+
+    ///A history read back from a saved file, trimmed like one that was recorded.
+    pub(super) fn from_saved(done: Vec<ChangeSet>, undone: Vec<ChangeSet>) -> History {
+        let mut history = History {
+            done,
+            undone,
+            ..History::default()
+        };
+        history.trim();
+        history
+    }
+
+    fn push(&mut self, changes: ChangeSet) {
+        self.undone.clear();
+        self.done.push(changes);
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        let mut total: usize = self.done.iter().map(|set| set.changes.len()).sum();
+        let mut oldest_kept = 0;
+        while self.done.len() - oldest_kept > 1
+            && (self.done.len() - oldest_kept > MAX_HISTORY || total > MAX_HISTORY_CHANGES)
+        {
+            total -= self.done[oldest_kept].changes.len();
+            oldest_kept += 1;
+        }
+        self.done.drain(..oldest_kept);
+    }
+}
+
+impl Catalog {
+    ///The changes that can be undone, oldest first.
+    pub fn history(&self) -> &[ChangeSet] {
+        &self.history_log().done
+    }
+
+    ///The changes that have been undone and can be redone, with the next one to redo last.
+    pub fn undone(&self) -> &[ChangeSet] {
+        &self.history_log().undone
+    }
+
+    ///Runs `change` with everything it adds, updates and removes recorded as a single ChangeSet, so that a bulk
+    ///import can be undone all at once.  A batch inside another batch becomes part of the outer one.
+    pub fn batch<T>(&mut self, description: &str, change: impl FnOnce(&mut Catalog) -> T) -> T {
+        if self.history_log().batch.is_some() {
+            return change(self);
+        }
+
+        self.history_log_mut().batch = Some(ChangeSet {
+            description: description.to_string(),
+            changes: vec![],
+        });
+        let result = change(self);
+
+        let history = self.history_log_mut();
+        if let Some(batch) = history
+            .batch
+            .take()
+            .filter(|batch| !batch.changes.is_empty())
+        {
+            history.push(batch);
+        }
+        result
+    }
+
+    ///Reverts the latest ChangeSet and returns it, or returns None if there is nothing to undo.
+    pub fn undo(&mut self) -> Option<&ChangeSet> {
+        let changes = self.history_log_mut().done.pop()?;
+        let changes = self.replay(changes, true);
+        self.history_log_mut().undone.push(changes);
+        self.history_log().undone.last()
+    }
+
+    ///Does the latest undone ChangeSet again and returns it, or returns None if there is nothing to redo.
+    pub fn redo(&mut self) -> Option<&ChangeSet> {
+        let changes = self.history_log_mut().undone.pop()?;
+        let changes = self.replay(changes, false);
+        self.history_log_mut().done.push(changes);
+        self.history_log().done.last()
+    }
+
+    //undoing goes through the changes backwards, putting back what was there before each one
+    fn replay(&mut self, mut changes: ChangeSet, undo: bool) -> ChangeSet {
+        self.history_log_mut().paused = true;
+
+        let mut replayed = vec![];
+        let ordered: Box<dyn Iterator<Item = Change>> = if undo {
+            Box::new(changes.changes.into_iter().rev())
+        } else {
+            Box::new(changes.changes.into_iter())
+        };
+        for change in ordered {
+            let mut also_undone = vec![];
+
+            match (&change, undo) {
+                (Change::Added { id, media }, false) | (Change::Removed { id, media }, true) => {
+                    self.restore_item(*id, media.clone());
+                }
+                //an item added since it was put in collections or reviewed, so that is taken away with it, and put
+                //back by redo after the item is
+                (Change::Added { id, .. }, true) => also_undone = self.take_out(*id),
+                (Change::Removed { id, .. }, false) => {
+                    self.remove_with(*id, OnRemove::Cascade).ok();
+                }
+                (Change::Updated { id, old, .. }, true) => {
+                    self.update(*id, old.as_ref().clone());
+                }
+                (Change::Updated { id, new, .. }, false) => {
+                    self.update(*id, new.as_ref().clone());
+                }
+                (Change::CollectionEntries { id, before, after }, undo) => {
+                    self.restore_collection_entries(*id, if undo { before } else { after });
+                }
+                (Change::Reviews { id, before, after }, undo) => {
+                    if self.get(*id).is_some() {
+                        self.reviews_mut()
+                            .set(*id, if undo { before.clone() } else { after.clone() });
+                    }
+                }
+            }
+
+            //changes are kept in the order they were made, whichever way they were replayed
+            replayed.extend(also_undone.into_iter().rev());
+            replayed.push(change);
+        }
+
+        if undo {
+            replayed.reverse();
+        }
+        changes.changes = replayed;
+        self.history_log_mut().paused = false;
+        changes
+    }
+
+    //removes an added item while undoing, returning changes that put back the collection entries and reviews it had.
+    //An item that has since been lent out is left where it is, like any other removal of it would be refused
+    fn take_out(&mut self, id: MediaId) -> Vec<Change> {
+        if self.is_lent(id) {
+            return vec![];
+        }
+        let mut changes = self.collection_changes(CollectionEntry::Item(id));
+        let reviews = self.reviews(id).to_vec();
+        self.remove_with(id, OnRemove::Cascade).ok();
+        self.finish_collection_changes(&mut changes);
+
+        if !reviews.is_empty() {
+            changes.push(Change::Reviews {
+                id,
+                before: vec![],
+                after: reviews,
+            });
+        }
+
+        //recorded the other way around, since these happen after the item is added back
+        for change in changes.iter_mut() {
+            if let Change::CollectionEntries { before, after, .. } = change {
+                std::mem::swap(before, after);
+            }
+        }
+        changes
+    }
+
+    //the entries are added back one at a time so that any that no longer make sense, like an item that has since
+    //been removed or a collection that would now end up inside itself, are left out instead of breaking the collection
+    fn restore_collection_entries(&mut self, id: CollectionId, entries: &[CollectionEntry]) {
+        if self.collections().get(id).is_none() {
+            return;
+        }
+
+        self.collections_mut().set_entries(id, vec![]);
+        for entry in entries {
+            self.add_to_collection(id, *entry).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::media::Metadata;
+
+    fn book(title: &str) -> Media {
+        Media::Book {
+            title: title.to_string(),
+            author: String::from("Author"),
+            metadata: Metadata::default(),
+        }
+    }
+
+    fn titles(catalog: &Catalog) -> Vec<String> {
+        catalog
+            .iter()
+            .map(|(_, media)| media.title().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn undo_and_redo_an_update() {
+        let mut catalog = Catalog::new();
+        let id = catalog.add_media(book("Dune"));
+        catalog.update(id, book("Dune Messiah"));
+
+        assert_eq!(
+            catalog.undo().unwrap().description,
+            "update 1: Dune Messiah"
+        );
+        assert_eq!(titles(&catalog), vec!["Dune"]);
+        catalog.redo().unwrap();
+        assert_eq!(titles(&catalog), vec!["Dune Messiah"]);
+
+        catalog.undo();
+        catalog.add_media(book("Emma"));
+        assert!(
+            catalog.redo().is_none(),
+            "a new change drops what was undone"
+        );
+    }
+
+    #[test]
+    fn an_import_is_undone_after_saving_and_loading() {
+        let dir = tempfile::tempdir().unwrap();
+        let import_path = dir.path().join("import.json");
+        let mut imported = Catalog::new();
+        imported.add_media(book("Dune"));
+        imported.add_media(book("Emma"));
+        imported.save(import_path.to_str().unwrap()).unwrap();
+
+        for file_name in ["catalog.csv", "catalog.json"] {
+            let path = dir.path().join(file_name);
+            let path = path.to_str().unwrap();
+            let mut catalog = Catalog::new();
+            catalog.add_media(book("Kept"));
+            catalog.import_file(import_path.to_str().unwrap()).unwrap();
+            catalog.save(path).unwrap();
+
+            let mut loaded = Catalog::load(path).unwrap();
+            assert_eq!(loaded.history(), catalog.history(), "{}", file_name);
+            assert!(loaded.undo().unwrap().description.starts_with("import "));
+            assert_eq!(titles(&loaded), vec!["Kept"], "{}", file_name);
+
+            //what was undone can be redone after another save and load
+            loaded.save(path).unwrap();
+            let mut loaded = Catalog::load(path).unwrap();
+            loaded.redo().unwrap();
+            assert_eq!(
+                titles(&loaded),
+                vec!["Kept", "Dune", "Emma"],
+                "{}",
+                file_name
+            );
+        }
+    }
+
+    #[test]
+    fn only_the_latest_change_sets_are_kept() {
+        let mut catalog = Catalog::new();
+        for number in 0..MAX_HISTORY + 5 {
+            catalog.add_media(book(&number.to_string()));
+        }
+
+        assert_eq!(catalog.history().len(), MAX_HISTORY);
+        assert_eq!(catalog.history()[0].description, "add 6: 5");
+    }
+
+    #[test]
+    fn a_large_change_set_pushes_out_older_ones_but_is_kept_itself() {
+        let mut catalog = Catalog::new();
+        catalog.add_media(book("Before"));
+        catalog.batch("import", |catalog| {
+            for number in 0..MAX_HISTORY_CHANGES {
+                catalog.add_media(book(&number.to_string()));
+            }
+        });
+
+        assert_eq!(catalog.history().len(), 1);
+        catalog.undo().unwrap();
+        assert_eq!(titles(&catalog), vec!["Before"]);
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use super::catalog::MediaId;
+use super::media::Media;
+
+//how much a word counts towards an item's score depending on where it was found
+const TITLE_WEIGHT: u32 = 3;
+const CREATOR_WEIGHT: u32 = 2;
+const TAG_WEIGHT: u32 = 1;
+
+//a query word that is a whole word in an item scores this many times more than one that is only the start of a word
+const EXACT_MATCH_BONUS: u32 = 2;
+
+///An inverted index from each word in the catalog's titles, creators and tags to the items containing it,
+///so a text search only has to look at the items that contain the words instead of scanning every item.
+#[derive(Default)]
+pub struct SearchIndex {
+    //a BTreeMap keeps the words sorted, so every word starting with a prefix sits together in one range
+    words: BTreeMap<String, HashMap<MediaId, u32>>,
+}
+
+impl SearchIndex {
+    pub fn new() -> Self {
+        SearchIndex::default()
+    }
+
+    ///Adds the words of an item to the index.
+    pub fn insert(&mut self, id: MediaId, media: &Media) {
+        for (word, weight) in weighted_words(media) {
+            let entry = self.words.entry(word).or_default().entry(id).or_insert(0);
+            //a word in both the title and the tags counts once, at its most important place
+            *entry = (*entry).max(weight);
+        }
+    }
+
+    ///Takes the words of an item back out of the index.  `media` must be the item as it was when it was inserted.
+    pub fn remove(&mut self, id: MediaId, media: &Media) {
+        for (word, _) in weighted_words(media) {
+            if let Some(items) = self.words.get_mut(&word) {
+                items.remove(&id);
+                if items.is_empty() {
+                    self.words.remove(&word);
+                }
+            }
+        }
+    }
+
+    ///Returns the IDs of the items that contain every word of the query, best match first.
+    ///Each query word matches whole words or the start of words, ignoring case, so "lord ri" finds "The Lord of the Rings".
+    pub fn search(&self, query: &str) -> Vec<(MediaId, u32)> {
+        let query_words = tokenize(query);
+        if query_words.is_empty() {
+            return vec![];
+        }
+
+        let mut scores: Option<HashMap<MediaId, u32>> = None;
+
+        for query_word in &query_words {
+            let matches = self.word_matches(query_word);
+
+            //only keep items that matched every query word so far
+            scores = Some(match scores {
+                None => matches,
+                Some(scores) => scores
+                    .into_iter()
+                    .filter_map(|(id, score)| matches.get(&id).map(|extra| (id, score + extra)))
+                    .collect(),
+            });
+        }
+
+        let mut results: Vec<(MediaId, u32)> = scores.unwrap_or_default().into_iter().collect();
+        results.sort_by(|(first_id, first_score), (second_id, second_score)| {
+            second_score.cmp(first_score).then(first_id.cmp(second_id))
+        });
+        results
+    }
+
+    ///Scores every item that has a word starting with the query word, using the item's best matching word.
+    fn word_matches(&self, query_word: &str) -> HashMap<MediaId, u32> {
+        let mut matches: HashMap<MediaId, u32> = HashMap::new();
+
+        let words_with_prefix = self
+            .words
+            .range(query_word.to_string()..)
+            .take_while(|(word, _)| word.starts_with(query_word));
+
+        for (word, items) in words_with_prefix {
+            let bonus = if word == query_word {
+                EXACT_MATCH_BONUS
+            } else {
+                1
+            };
+
+            for (id, weight) in items {
+                let score = matches.entry(*id).or_insert(0);
+                *score = (*score).max(weight * bonus);
+            }
+        }
+
+        matches
+    }
+}
+
+//printing every word would bury the rest of a printed Catalog, so only the number of words is shown
+impl fmt::Debug for SearchIndex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SearchIndex {{ {} words }}", self.words.len())
+    }
+}
+
+///Splits text into lowercase words, treating anything that isn't a letter or a digit as a separator.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+fn weighted_words(media: &Media) -> Vec<(String, u32)> {
+    let mut words: Vec<(String, u32)> = tokenize(media.title())
+        .into_iter()
+        .map(|word| (word, TITLE_WEIGHT))
+        .collect();
+
+    for creator in media.creators() {
+        words.extend(
+            tokenize(creator)
+                .into_iter()
+                .map(|word| (word, CREATOR_WEIGHT)),
+        );
+    }
+
+    for tag in &media.metadata().tags {
+        words.extend(tokenize(tag).into_iter().map(|word| (word, TAG_WEIGHT)));
+    }
+
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::media::Metadata;
+
+    fn book(title: &str, author: &str, tags: &[&str]) -> Media {
+        Media::Book {
+            title: title.to_string(),
+            author: author.to_string(),
+            metadata: Metadata {
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                ..Metadata::default()
+            },
+        }
+    }
+
+    fn ids(index: &SearchIndex, query: &str) -> Vec<u32> {
+        index
+            .search(query)
+            .into_iter()
+            .map(|(id, _)| id.0)
+            .collect()
+    }
+
+    #[test]
+    fn splits_text_into_lowercase_words() {
+        assert_eq!(
+            tokenize("The Lord of the Rings: Part-1, Ænima"),
+            vec!["the", "lord", "of", "the", "rings", "part", "1", "ænima"]
+        );
+        assert!(tokenize(" -- ").is_empty());
+    }
+
+    #[test]
+    fn finds_items_with_every_word_or_the_start_of_it() {
+        let mut index = SearchIndex::new();
+        index.insert(MediaId(1), &book("The Lord of the Rings", "Tolkien", &[]));
+        index.insert(MediaId(2), &book("The Hobbit", "Tolkien", &["rings"]));
+        index.insert(MediaId(3), &book("Lord of Light", "Zelazny", &[]));
+
+        assert_eq!(ids(&index, "lord ri"), vec![1]);
+        assert_eq!(ids(&index, "LORD"), vec![1, 3]);
+        assert_eq!(ids(&index, "tolk"), vec![1, 2]);
+        assert!(ids(&index, "lord hobbit").is_empty());
+        assert!(ids(&index, "").is_empty());
+        assert!(ids(&index, "ring lordship").is_empty());
+    }
+
+    #[test]
+    fn ranks_title_matches_and_whole_words_first() {
+        let mut index = SearchIndex::new();
+        index.insert(MediaId(1), &book("Notes", "Anne Rice", &["dune"]));
+        index.insert(MediaId(2), &book("Dune", "Frank Herbert", &[]));
+        index.insert(MediaId(3), &book("Dunes of Arrakis", "Someone", &[]));
+        index.insert(MediaId(4), &book("Essays", "Dune Writer", &[]));
+
+        //whole title word, then whole creator word, then the start of a title word, then a tag
+        assert_eq!(ids(&index, "dune"), vec![2, 4, 3, 1]);
+        let scores: Vec<u32> = index
+            .search("dune")
+            .into_iter()
+            .map(|(_, score)| score)
+            .collect();
+        assert_eq!(
+            scores,
+            vec![
+                TITLE_WEIGHT * EXACT_MATCH_BONUS,
+                CREATOR_WEIGHT * EXACT_MATCH_BONUS,
+                TITLE_WEIGHT,
+                TAG_WEIGHT * EXACT_MATCH_BONUS
+            ]
+        );
+    }
+
+    #[test]
+    fn removing_an_item_takes_out_only_its_words() {
+        let mut index = SearchIndex::new();
+        let first = book("Emma", "Jane Austen", &["classic"]);
+        index.insert(MediaId(1), &first);
+        index.insert(MediaId(2), &book("Persuasion", "Jane Austen", &[]));
+
+        index.remove(MediaId(1), &first);
+        assert!(ids(&index, "emma").is_empty());
+        assert!(ids(&index, "classic").is_empty());
+        assert_eq!(ids(&index, "austen"), vec![2]);
+        assert_eq!(format!("{:?}", index), "SearchIndex { 3 words }");
+    }
+}
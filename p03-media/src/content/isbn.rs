@@ -0,0 +1,192 @@
+use std::fmt;
+
+///An International Standard Book Number whose check digit has been verified.  It is kept in its 13 digit form,
+///so the 10 and 13 digit forms of the same book are equal.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Isbn {
+    digits: [u8; 13],
+}
+
+///Why text isn't a valid ISBN.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IsbnError {
+    //the number of digits, not counting dashes and spaces
+    WrongLength(usize),
+    InvalidCharacter(char),
+    WrongCheckDigit,
+    //13 digit numbers are only ISBNs if they start with 978 or 979, the rest are other kinds of barcodes
+    NotABook,
+}
+
+impl fmt::Display for IsbnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IsbnError::WrongLength(length) => {
+                write!(f, "an ISBN has 10 or 13 digits, not {}", length)
+            }
+            IsbnError::InvalidCharacter(c) => write!(f, "'{}' can't be in an ISBN", c),
+            IsbnError::WrongCheckDigit => {
+                write!(f, "the check digit is wrong, so the ISBN has a typo in it")
+            }
+            IsbnError::NotABook => write!(f, "a 13 digit ISBN starts with 978 or 979"),
+        }
+    }
+}
+
+impl Isbn {
+    ///Reads an ISBN-10 or ISBN-13, ignoring dashes, spaces and a leading "ISBN", and checks its check digit.
+    pub fn parse(text: &str) -> Result<Isbn, IsbnError> {
+        let text = text.trim();
+        let text = match text.get(..4) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("isbn") => {
+                text[4..].trim_start_matches([':', ' '])
+            }
+            _ => text,
+        };
+
+        let mut digits = vec![];
+        for c in text.chars().filter(|c| *c != '-' && !c.is_whitespace()) {
+            match c {
+                '0'..='9' => digits.push(c as u8 - b'0'),
+                //X stands for 10, and only ever as the check digit of an ISBN-10
+                'X' | 'x' => digits.push(10),
+                _ => return Err(IsbnError::InvalidCharacter(c)),
+            }
+        }
+
+        match digits.len() {
+            10 => from_isbn10(&digits),
+            13 => from_isbn13(&digits),
+            length => Err(IsbnError::WrongLength(length)),
+        }
+    }
+
+    ///The 13 digit form, without dashes.
+    pub fn isbn13(&self) -> String {
+        self.digits
+            .iter()
+            .map(|digit| char::from(b'0' + digit))
+            .collect()
+    }
+
+    ///The 10 digit form, without dashes, or None for ISBNs starting with 979, which have no 10 digit form.
+    pub fn isbn10(&self) -> Option<String> {
+        if self.digits[..3] != [9, 7, 8] {
+            return None;
+        }
+
+        let body = &self.digits[3..12];
+        let check = isbn10_check_digit(body);
+        let mut isbn10: String = body.iter().map(|digit| char::from(b'0' + digit)).collect();
+        isbn10.push(if check == 10 {
+            'X'
+        } else {
+            char::from(b'0' + check)
+        });
+        Some(isbn10)
+    }
+}
+
+//shown in its 13 digit form, which is what has been printed on books since 2007
+impl fmt::Display for Isbn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.isbn13())
+    }
+}
+
+fn from_isbn10(digits: &[u8]) -> Result<Isbn, IsbnError> {
+    if digits[..9].contains(&10) {
+        return Err(IsbnError::InvalidCharacter('X'));
+    }
+    if isbn10_check_digit(&digits[..9]) != digits[9] {
+        return Err(IsbnError::WrongCheckDigit);
+    }
+
+    //an ISBN-10 becomes an ISBN-13 by putting 978 in front and working out the check digit again
+    let mut isbn13 = [9, 7, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    isbn13[3..12].copy_from_slice(&digits[..9]);
+    isbn13[12] = isbn13_check_digit(&isbn13[..12]);
+    Ok(Isbn { digits: isbn13 })
+}
+
+fn from_isbn13(digits: &[u8]) -> Result<Isbn, IsbnError> {
+    //X is only allowed at the end of an ISBN-10
+    if digits.contains(&10) {
+        return Err(IsbnError::InvalidCharacter('X'));
+    }
+    if digits[..3] != [9, 7, 8] && digits[..3] != [9, 7, 9] {
+        return Err(IsbnError::NotABook);
+    }
+    if isbn13_check_digit(&digits[..12]) != digits[12] {
+        return Err(IsbnError::WrongCheckDigit);
+    }
+
+    let mut isbn13 = [0; 13];
+    isbn13.copy_from_slice(digits);
+    Ok(Isbn { digits: isbn13 })
+}
+
+//the digits are weighted 10, 9, ... 2, and the check digit makes the total a multiple of 11, where 10 is written X
+fn isbn10_check_digit(body: &[u8]) -> u8 {
+    let sum: u32 = body
+        .iter()
+        .zip((2..=10).rev())
+        .map(|(digit, weight)| *digit as u32 * weight)
+        .sum();
+    ((11 - sum % 11) % 11) as u8
+}
+
+//the digits are weighted 1, 3, 1, 3 ... and the check digit makes the total a multiple of 10
+fn isbn13_check_digit(body: &[u8]) -> u8 {
+    let sum: u32 = body
+        .iter()
+        .zip([1, 3].iter().cycle())
+        .map(|(digit, weight)| *digit as u32 * weight)
+        .sum();
+    ((10 - sum % 10) % 10) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_both_forms_as_the_same_isbn() {
+        let isbn10 = Isbn::parse("0-306-40615-2").unwrap();
+        let isbn13 = Isbn::parse("ISBN: 978-0-306-40615-7").unwrap();
+        assert_eq!(isbn10, isbn13);
+        assert_eq!(isbn10.isbn13(), "9780306406157");
+        assert_eq!(isbn13.isbn10().as_deref(), Some("0306406152"));
+        assert_eq!(isbn13.to_string(), "9780306406157");
+
+        //an X check digit stands for 10
+        let with_x = Isbn::parse("isbn 0 8044 2957 x").unwrap();
+        assert_eq!(with_x.isbn13(), "9780804429573");
+        assert_eq!(with_x.isbn10().as_deref(), Some("080442957X"));
+
+        //979 ISBNs have no 10 digit form
+        let isbn979 = Isbn::parse("979-10-90636-07-1").unwrap();
+        assert_eq!(isbn979.isbn10(), None);
+    }
+
+    #[test]
+    fn refuses_anything_that_is_not_a_valid_isbn() {
+        let cases = [
+            ("0-306-40615-3", IsbnError::WrongCheckDigit),
+            ("978-0-306-40615-8", IsbnError::WrongCheckDigit),
+            ("12345", IsbnError::WrongLength(5)),
+            ("", IsbnError::WrongLength(0)),
+            ("0-306-4061A-2", IsbnError::InvalidCharacter('A')),
+            ("X-306-40615-2", IsbnError::InvalidCharacter('X')),
+            ("978-0-306-4061X-7", IsbnError::InvalidCharacter('X')),
+            ("4006381333931", IsbnError::NotABook),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(Isbn::parse(text), Err(expected), "{}", text);
+        }
+        assert_eq!(
+            IsbnError::WrongLength(5).to_string(),
+            "an ISBN has 10 or 13 digits, not 5"
+        );
+    }
+}
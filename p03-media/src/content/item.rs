@@ -0,0 +1,481 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::Mutex;
+
+use serde::de::DeserializeOwned;
+use serde::ser;
+use serde::{Deserialize, Serialize, Serializer};
+
+use super::media::{Media, MediaKind, Metadata};
+
+///What every kind of media has, so kinds from outside this crate can be kept in a Catalog next to the built-in ones.
+///A kind is made by implementing this for a type, registering it in a `KindRegistry` that catalogs are loaded with
+///and wrapping items in `Media::Custom`.  For example, a board game kind:
+///
+///```
+///use p03_media::content::catalog::Catalog;
+///use p03_media::content::item::{CustomMedia, KindRegistry, MediaItem};
+///use p03_media::content::media::{Media, Metadata};
+///use serde::{Deserialize, Serialize};
+///
+///#[derive(Debug, Clone, Serialize, Deserialize)]
+///struct BoardGame {
+///    name: String,
+///    designers: Vec<String>,
+///    #[serde(default)]
+///    metadata: Metadata,
+///}
+///
+///impl MediaItem for BoardGame {
+///    fn kind_name(&self) -> &'static str { "board_game" }
+///    fn title(&self) -> &str { &self.name }
+///    fn creators(&self) -> Vec<&str> { self.designers.iter().map(String::as_str).collect() }
+///    fn metadata(&self) -> &Metadata { &self.metadata }
+///    fn metadata_mut(&mut self) -> &mut Metadata { &mut self.metadata }
+///    fn clone_item(&self) -> Box<dyn MediaItem> { Box::new(self.clone()) }
+///    fn to_json(&self) -> serde_json::Value { serde_json::to_value(self).unwrap() }
+///}
+///
+///let mut kinds = KindRegistry::new();
+///kinds.register::<BoardGame>("board_game");
+///
+///let mut catalog = Catalog::new();
+///let catan = BoardGame {
+///    name: String::from("Catan"),
+///    designers: vec![String::from("Klaus Teuber")],
+///    metadata: Metadata::default(),
+///};
+///let id = catalog.add_media(Media::Custom(CustomMedia::new(catan)));
+///
+///let loaded = Catalog::from_json_with_kinds(&catalog.to_json(), &kinds).unwrap();
+///assert_eq!(loaded.get(id).unwrap().description(), "board_game: Catan by Klaus Teuber");
+///```
+pub trait MediaItem: fmt::Debug + Send + Sync {
+    ///The name of the kind, like "board_game", which is what it is saved as.  It has to be the name it was registered with.
+    fn kind_name(&self) -> &'static str;
+
+    fn title(&self) -> &str;
+
+    ///The people who made it, most important first.  The first one is used when sorting and comparing by creator.
+    fn creators(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    ///A one line description, without the metadata since `Media::description` adds that after it.
+    fn description(&self) -> String {
+        let creators = self.creators();
+        if creators.is_empty() {
+            format!("{}: {}", self.kind_name(), self.title())
+        } else {
+            format!(
+                "{}: {} by {}",
+                self.kind_name(),
+                self.title(),
+                creators.join(", ")
+            )
+        }
+    }
+
+    fn metadata(&self) -> &Metadata;
+
+    fn metadata_mut(&mut self) -> &mut Metadata;
+
+    //Clone can't be a supertrait of a trait used as `dyn MediaItem`, so each kind clones itself into a new box
+    fn clone_item(&self) -> Box<dyn MediaItem>;
+
+    ///The item's fields as a JSON object, which is saved along with the kind name and read back with the kind's
+    ///Deserialize implementation.
+    fn to_json(&self) -> serde_json::Value;
+}
+
+//the built-in kinds are media items too, so code that only needs what every kind has can take any of them
+impl MediaItem for Media {
+    fn kind_name(&self) -> &'static str {
+        self.kind().name()
+    }
+
+    fn title(&self) -> &str {
+        Media::title(self)
+    }
+
+    fn creators(&self) -> Vec<&str> {
+        Media::creators(self)
+    }
+
+    fn description(&self) -> String {
+        Media::description(self)
+    }
+
+    fn metadata(&self) -> &Metadata {
+        Media::metadata(self)
+    }
+
+    fn metadata_mut(&mut self) -> &mut Metadata {
+        Media::metadata_mut(self)
+    }
+
+    fn clone_item(&self) -> Box<dyn MediaItem> {
+        Box::new(self.clone())
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("media should always serialize to JSON")
+    }
+}
+
+//how each registered kind is read back from its saved fields
+type FromJson = fn(serde_json::Value) -> Result<Box<dyn MediaItem>, serde_json::Error>;
+
+///The kinds from outside this crate that a program knows how to read, which it passes to
+///`Catalog::load_with_kinds` and the other loading functions.  Items of kinds that aren't in it are still loaded,
+///and kept as the fields they were saved with so that saving the catalog again writes them back unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct KindRegistry {
+    kinds: BTreeMap<&'static str, FromJson>,
+}
+
+impl KindRegistry {
+    pub fn new() -> Self {
+        KindRegistry::default()
+    }
+
+    ///Lets items of a kind from outside this crate be read as that kind when loading with this registry.
+    ///Returns false without changing anything if the name is already a built-in kind or was registered before.
+    pub fn register<T>(&mut self, name: &'static str) -> bool
+    where
+        T: MediaItem + DeserializeOwned + 'static,
+    {
+        if self.kind(name).is_some() {
+            return false;
+        }
+        self.kinds.insert(name, from_json::<T>);
+        true
+    }
+
+    ///Finds the built-in or registered kind with the given name, ignoring case.
+    pub fn kind(&self, name: &str) -> Option<MediaKind> {
+        MediaKind::from_name(name).or_else(|| {
+            self.kinds
+                .keys()
+                .find(|registered| registered.eq_ignore_ascii_case(name.trim()))
+                .copied()
+                .map(MediaKind::Custom)
+        })
+    }
+
+    ///Reads a loaded item of a kind from outside this crate as the registered kind of that name, if there is one.
+    pub(super) fn read(&self, media: &mut Media) -> Result<(), String> {
+        let Media::Custom(custom) = media else {
+            return Ok(());
+        };
+        let kind = custom.item().kind_name();
+        let Some((name, from_json)) = self
+            .kinds
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(kind))
+        else {
+            return Ok(());
+        };
+
+        let item =
+            from_json(custom.item().to_json()).map_err(|error| format!("{}: {}", name, error))?;
+        *custom = CustomMedia(item);
+        Ok(())
+    }
+}
+
+fn from_json<T: MediaItem + DeserializeOwned + 'static>(
+    fields: serde_json::Value,
+) -> Result<Box<dyn MediaItem>, serde_json::Error> {
+    Ok(Box::new(serde_json::from_value::<T>(fields)?))
+}
+
+//an item of a kind that the registry it was loaded with doesn't have.  Its title is whichever of a "title" or
+//"name" field it has, and its metadata is read from a "metadata" field if there is one, so it can still be
+//searched, sorted and have its metadata filled in
+#[derive(Debug, Clone)]
+struct UnregisteredItem {
+    kind: &'static str,
+    fields: serde_json::Map<String, serde_json::Value>,
+    metadata: Metadata,
+    //whether the metadata was taken out of the fields, and so has to be put back when saving
+    saved_metadata: bool,
+}
+
+impl MediaItem for UnregisteredItem {
+    fn kind_name(&self) -> &'static str {
+        self.kind
+    }
+
+    fn title(&self) -> &str {
+        ["title", "name"]
+            .iter()
+            .find_map(|field| self.fields.get(*field).and_then(serde_json::Value::as_str))
+            .unwrap_or("")
+    }
+
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+
+    fn clone_item(&self) -> Box<dyn MediaItem> {
+        Box::new(self.clone())
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let mut fields = self.fields.clone();
+        if self.saved_metadata || self.metadata != Metadata::default() {
+            fields.insert(
+                String::from("metadata"),
+                serde_json::to_value(&self.metadata)
+                    .expect("metadata should always serialize to JSON"),
+            );
+        }
+        serde_json::Value::Object(fields)
+    }
+}
+
+//items need their kind's name for as long as the program runs, which registered kinds are given as, so the name of
+//each unregistered kind read from a file is kept once for the whole program instead of once for every item
+fn lasting_name(name: &str) -> &'static str {
+    static NAMES: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+    let mut names = NAMES
+        .lock()
+        .expect("the kind names should never be poisoned");
+    match names.get(name) {
+        Some(lasting) => lasting,
+        None => {
+            let lasting: &'static str = Box::leak(name.to_string().into_boxed_str());
+            names.insert(lasting);
+            lasting
+        }
+    }
+}
+
+///An item of a kind from outside this crate, kept in `Media::Custom`.
+pub struct CustomMedia(Box<dyn MediaItem>);
+
+impl CustomMedia {
+    pub fn new(item: impl MediaItem + 'static) -> Self {
+        CustomMedia(Box::new(item))
+    }
+
+    ///An item of a kind that isn't built in, as it was read from a file before any registry has read it.
+    pub(super) fn unregistered(
+        kind: &str,
+        mut fields: serde_json::Map<String, serde_json::Value>,
+    ) -> CustomMedia {
+        //metadata that isn't what this crate's Metadata looks like is left in the fields, like any other field
+        let metadata = fields
+            .get("metadata")
+            .and_then(|metadata| Metadata::deserialize(metadata).ok());
+        let saved_metadata = metadata.is_some();
+        if saved_metadata {
+            fields.remove("metadata");
+        }
+
+        CustomMedia(Box::new(UnregisteredItem {
+            kind: lasting_name(kind),
+            fields,
+            metadata: metadata.unwrap_or_default(),
+            saved_metadata,
+        }))
+    }
+
+    pub fn item(&self) -> &dyn MediaItem {
+        self.0.as_ref()
+    }
+
+    pub fn item_mut(&mut self) -> &mut dyn MediaItem {
+        self.0.as_mut()
+    }
+}
+
+impl Clone for CustomMedia {
+    fn clone(&self) -> Self {
+        CustomMedia(self.0.clone_item())
+    }
+}
+
+//two items are the same if they are the same kind and would be saved the same way
+impl PartialEq for CustomMedia {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.kind_name() == other.0.kind_name() && self.0.to_json() == other.0.to_json()
+    }
+}
+
+impl fmt::Debug for CustomMedia {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+//saved like the built-in kinds, as an object with a "kind" field next to the item's own fields
+impl Serialize for CustomMedia {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let serde_json::Value::Object(mut fields) = self.0.to_json() else {
+            return Err(ser::Error::custom(format!(
+                "a {} should be saved as a JSON object",
+                self.0.kind_name()
+            )));
+        };
+        fields.insert(
+            String::from("kind"),
+            serde_json::Value::from(self.0.kind_name()),
+        );
+        fields.serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::catalog::Catalog;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct BoardGame {
+        name: String,
+        designers: Vec<String>,
+        players: u32,
+        #[serde(default)]
+        metadata: Metadata,
+    }
+
+    impl MediaItem for BoardGame {
+        fn kind_name(&self) -> &'static str {
+            "board_game"
+        }
+
+        fn title(&self) -> &str {
+            &self.name
+        }
+
+        fn creators(&self) -> Vec<&str> {
+            self.designers.iter().map(String::as_str).collect()
+        }
+
+        fn metadata(&self) -> &Metadata {
+            &self.metadata
+        }
+
+        fn metadata_mut(&mut self) -> &mut Metadata {
+            &mut self.metadata
+        }
+
+        fn clone_item(&self) -> Box<dyn MediaItem> {
+            Box::new(self.clone())
+        }
+
+        fn to_json(&self) -> serde_json::Value {
+            serde_json::to_value(self).unwrap()
+        }
+    }
+
+    fn kinds() -> KindRegistry {
+        let mut kinds = KindRegistry::new();
+        assert!(kinds.register::<BoardGame>("board_game"));
+        kinds
+    }
+
+    fn catalog_with_game() -> Catalog {
+        let mut catalog = Catalog::new();
+        catalog.add_media(Media::Custom(CustomMedia::new(BoardGame {
+            name: String::from("Catan, Seafarers"),
+            designers: vec![String::from("Klaus Teuber")],
+            players: 4,
+            metadata: Metadata {
+                tags: vec![String::from("family; strategy")],
+                ..Metadata::default()
+            },
+        })));
+        catalog.add_media(Media::Movie {
+            title: String::from("Jumanji"),
+            director: String::from("Joe Johnston"),
+            metadata: Metadata::default(),
+        });
+        catalog
+    }
+
+    #[test]
+    fn a_registered_kind_round_trips_through_json_and_csv() {
+        let kinds = kinds();
+        let catalog = catalog_with_game();
+
+        for loaded in [
+            Catalog::from_json_with_kinds(&catalog.to_json(), &kinds).unwrap(),
+            Catalog::from_csv_with_kinds(&catalog.to_csv(), &kinds).unwrap(),
+        ] {
+            assert_eq!(
+                loaded.iter().collect::<Vec<_>>(),
+                catalog.iter().collect::<Vec<_>>()
+            );
+            let game = loaded.iter().next().unwrap().1;
+            assert_eq!(game.kind(), MediaKind::Custom("board_game"));
+            assert_eq!(game.creators(), vec!["Klaus Teuber"]);
+            assert_eq!(loaded.history(), catalog.history());
+        }
+        assert_eq!(
+            kinds.kind("Board_Game"),
+            Some(MediaKind::Custom("board_game"))
+        );
+        assert_eq!(kinds.kind("movie"), Some(MediaKind::Movie));
+    }
+
+    #[test]
+    fn built_in_and_repeated_names_are_not_registered() {
+        let mut kinds = kinds();
+        assert!(!kinds.register::<BoardGame>("book"));
+        assert!(!kinds.register::<BoardGame>("BOARD_GAME"));
+        assert_eq!(KindRegistry::new().kind("board_game"), None);
+    }
+
+    #[test]
+    fn an_unregistered_kind_is_kept_as_it_was_saved() {
+        let catalog = catalog_with_game();
+
+        for loaded in [
+            Catalog::from_json(&catalog.to_json()).unwrap(),
+            Catalog::from_csv(&catalog.to_csv()).unwrap(),
+        ] {
+            let game = loaded.iter().next().unwrap().1;
+            assert_eq!(game.kind(), MediaKind::Custom("board_game"));
+            assert_eq!(game.title(), "Catan, Seafarers");
+            assert_eq!(game.metadata().tags, vec!["family; strategy"]);
+            assert_eq!(loaded.to_json(), catalog.to_json());
+            assert_eq!(loaded.to_csv(), catalog.to_csv());
+
+            //and a program that does register the kind reads it back as one
+            let reloaded = Catalog::from_json_with_kinds(&loaded.to_json(), &kinds()).unwrap();
+            assert_eq!(
+                reloaded.iter().collect::<Vec<_>>(),
+                catalog.iter().collect::<Vec<_>>()
+            );
+            assert_eq!(game.creators(), Vec::<&str>::new());
+            assert_eq!(
+                reloaded.iter().next().unwrap().1.creators(),
+                vec!["Klaus Teuber"]
+            );
+        }
+    }
+
+    #[test]
+    fn a_registered_kind_that_cant_read_its_fields_is_an_error() {
+        let json = r#"{"items": [{"id": 1, "kind": "board_game", "name": "Catan"}]}"#;
+        let error = Catalog::from_json_with_kinds(json, &kinds())
+            .unwrap_err()
+            .to_string();
+        assert_eq!(error, "board_game: missing field `designers`");
+
+        let csv = "kind,id,data\nboard_game,1,\"{\"\"name\"\":\"\"Catan\"\"}\"\n";
+        let error = Catalog::from_csv_with_kinds(csv, &kinds())
+            .unwrap_err()
+            .to_string();
+        assert_eq!(error, "line 2: board_game: missing field `designers`");
+    }
+}
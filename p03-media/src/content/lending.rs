@@ -0,0 +1,614 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use super::catalog::{Catalog, MediaId};
+use super::collections::{CollectionError, OnRemove};
+use super::dates::Day;
+use super::media::Media;
+
+//how long an item can be kept if the Library isn't told otherwise
+const DEFAULT_LOAN_DAYS: u32 = 14;
+
+///The ID a patron is given when they are added to a Library.  Like MediaIds, these are never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PatronId(pub u32);
+
+impl fmt::Display for PatronId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+///Someone who can borrow items from the Library.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Patron {
+    pub id: PatronId,
+    pub name: String,
+}
+
+///An item that is lent out, who has it and when it has to come back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Loan {
+    pub item: MediaId,
+    pub patron: PatronId,
+    pub checked_out: Day,
+    pub due: Day,
+}
+
+///What happened when an item was checked back in.
+#[derive(Debug)]
+pub struct Return {
+    pub loan: Loan,
+    pub days_overdue: u32,
+    //the patron at the front of the holds queue, who the item should now be kept for
+    pub hold_for: Option<PatronId>,
+}
+
+///Why a lending operation was refused.
+#[derive(Debug, PartialEq)]
+pub enum LendingError {
+    ItemNotFound(MediaId),
+    PatronNotFound(PatronId),
+    //Placeholders stand in for items that aren't on the shelf, so there is nothing to lend
+    NotLendable(MediaId),
+    AlreadyLent { item: MediaId, patron: PatronId },
+    NotLent(MediaId),
+    //someone else is ahead in the holds queue for the item
+    OnHold { item: MediaId, patron: PatronId },
+    AlreadyHolding { item: MediaId, patron: PatronId },
+    HasLoans(PatronId),
+    //an item that is lent out can't be removed from the catalog until it is checked back in
+    StillLent { item: MediaId, patron: PatronId },
+    //the item is still in a collection, see Catalog::remove_with
+    InCollection(CollectionError),
+    //the due date would be too far away to be stored as a Day
+    LoanTooLong(u32),
+}
+
+impl fmt::Display for LendingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LendingError::ItemNotFound(item) => write!(f, "there is no item with ID {}", item),
+            LendingError::PatronNotFound(patron) => {
+                write!(f, "there is no patron with ID {}", patron)
+            }
+            LendingError::NotLendable(item) => {
+                write!(f, "item {} is a placeholder and can't be lent", item)
+            }
+            LendingError::AlreadyLent { item, patron } => {
+                write!(f, "item {} is already lent to patron {}", item, patron)
+            }
+            LendingError::NotLent(item) => write!(f, "item {} isn't lent out", item),
+            LendingError::OnHold { item, patron } => {
+                write!(f, "item {} is being held for patron {}", item, patron)
+            }
+            LendingError::AlreadyHolding { item, patron } => {
+                write!(
+                    f,
+                    "patron {} already has item {} checked out or on hold",
+                    patron, item
+                )
+            }
+            LendingError::HasLoans(patron) => {
+                write!(f, "patron {} still has items checked out", patron)
+            }
+            LendingError::StillLent { item, patron } => {
+                write!(
+                    f,
+                    "item {} is lent to patron {} and has to be checked in before it is removed",
+                    item, patron
+                )
+            }
+            LendingError::InCollection(error) => write!(f, "{}", error),
+            LendingError::LoanTooLong(days) => {
+                write!(
+                    f,
+                    "a loan of {} days would be due too far in the future",
+                    days
+                )
+            }
+        }
+    }
+}
+
+///Keeps track of which items of a Catalog are lent out, to whom, and who is waiting for them.
+///The Library only stores MediaIds, so it is used alongside a Catalog rather than owning one.  The items it lends
+///are marked as lent in the Catalog too, which keeps the Catalog from removing or merging them away.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Library {
+    patrons: BTreeMap<PatronId, Patron>,
+    next_patron_id: u32,
+    loans: BTreeMap<MediaId, Loan>,
+    //patrons waiting for each item, first in line at the front
+    holds: BTreeMap<MediaId, VecDeque<PatronId>>,
+    loan_days: u32,
+}
+
+impl Default for Library {
+    fn default() -> Self {
+        Library::new()
+    }
+}
+
+impl Library {
+    pub fn new() -> Self {
+        Library::with_loan_days(DEFAULT_LOAN_DAYS)
+    }
+
+    ///Makes a Library that lends items for the given number of days.
+    pub fn with_loan_days(loan_days: u32) -> Self {
+        Library {
+            patrons: BTreeMap::new(),
+            next_patron_id: 1,
+            loans: BTreeMap::new(),
+            holds: BTreeMap::new(),
+            loan_days,
+        }
+    }
+
+    pub fn add_patron(&mut self, name: &str) -> PatronId {
+        let id = PatronId(self.next_patron_id);
+        self.next_patron_id += 1;
+        self.patrons.insert(
+            id,
+            Patron {
+                id,
+                name: name.to_string(),
+            },
+        );
+        id
+    }
+
+    pub fn patron(&self, id: PatronId) -> Option<&Patron> {
+        self.patrons.get(&id)
+    }
+
+    ///Removes a patron and takes them out of every holds queue.  A patron who still has items can't be removed.
+    pub fn remove_patron(&mut self, id: PatronId) -> Result<Patron, LendingError> {
+        if self.loans.values().any(|loan| loan.patron == id) {
+            return Err(LendingError::HasLoans(id));
+        }
+        let patron = self
+            .patrons
+            .remove(&id)
+            .ok_or(LendingError::PatronNotFound(id))?;
+
+        for queue in self.holds.values_mut() {
+            queue.retain(|waiting| *waiting != id);
+        }
+        self.holds.retain(|_, queue| !queue.is_empty());
+
+        Ok(patron)
+    }
+
+    ///Returns whether the item is on the shelf, meaning it isn't lent out.
+    ///An item on the shelf can still be held for someone, see `holds`.
+    pub fn is_available(&self, item: MediaId) -> bool {
+        !self.loans.contains_key(&item)
+    }
+
+    pub fn loan(&self, item: MediaId) -> Option<&Loan> {
+        self.loans.get(&item)
+    }
+
+    ///Every item the patron has checked out.
+    pub fn loans_for(&self, patron: PatronId) -> impl Iterator<Item = &Loan> {
+        self.loans
+            .values()
+            .filter(move |loan| loan.patron == patron)
+    }
+
+    ///The patrons waiting for an item, first in line first.
+    pub fn holds(&self, item: MediaId) -> impl Iterator<Item = PatronId> + '_ {
+        self.holds.get(&item).into_iter().flatten().copied()
+    }
+
+    ///Lends an item to a patron until `loan_days` after `today`.  If anyone has placed a hold on the item,
+    ///only the patron first in line can check it out, and doing so takes them out of the queue.
+    pub fn check_out(
+        &mut self,
+        catalog: &mut Catalog,
+        item: MediaId,
+        patron: PatronId,
+        today: Day,
+    ) -> Result<&Loan, LendingError> {
+        self.check_lendable(catalog, item)?;
+        self.check_patron(patron)?;
+
+        if let Some(loan) = self.loans.get(&item) {
+            return Err(LendingError::AlreadyLent {
+                item,
+                patron: loan.patron,
+            });
+        }
+        let due = today
+            .plus_days(self.loan_days)
+            .ok_or(LendingError::LoanTooLong(self.loan_days))?;
+
+        if let Some(queue) = self.holds.get_mut(&item) {
+            match queue.front() {
+                Some(first) if *first != patron => {
+                    return Err(LendingError::OnHold {
+                        item,
+                        patron: *first,
+                    })
+                }
+                _ => {
+                    queue.pop_front();
+                    if queue.is_empty() {
+                        self.holds.remove(&item);
+                    }
+                }
+            }
+        }
+
+        let loan = Loan {
+            item,
+            patron,
+            checked_out: today,
+            due,
+        };
+        catalog.set_lent(item, true);
+        Ok(self.loans.entry(item).or_insert(loan))
+    }
+
+    ///Takes an item back, saying how late it was and who it should now be kept for, if anyone.
+    pub fn check_in(
+        &mut self,
+        catalog: &mut Catalog,
+        item: MediaId,
+        today: Day,
+    ) -> Result<Return, LendingError> {
+        let loan = self
+            .loans
+            .remove(&item)
+            .ok_or(LendingError::NotLent(item))?;
+        catalog.set_lent(item, false);
+
+        Ok(Return {
+            days_overdue: today.days_since(loan.due),
+            hold_for: self.holds(item).next(),
+            loan,
+        })
+    }
+
+    ///Puts a patron at the back of the queue for an item.  Items that are on the shelf can be held too,
+    ///which keeps them for the patron until they come to check them out.
+    pub fn place_hold(
+        &mut self,
+        catalog: &Catalog,
+        item: MediaId,
+        patron: PatronId,
+    ) -> Result<usize, LendingError> {
+        self.check_lendable(catalog, item)?;
+        self.check_patron(patron)?;
+
+        let already_has_it = self.loans.get(&item).map(|loan| loan.patron) == Some(patron);
+        if already_has_it || self.holds(item).any(|waiting| waiting == patron) {
+            return Err(LendingError::AlreadyHolding { item, patron });
+        }
+
+        let queue = self.holds.entry(item).or_default();
+        queue.push_back(patron);
+        //the patron's place in line, starting from 1
+        Ok(queue.len())
+    }
+
+    ///Takes a patron out of the queue for an item, returning whether they were in it.
+    pub fn cancel_hold(&mut self, item: MediaId, patron: PatronId) -> bool {
+        let Some(queue) = self.holds.get_mut(&item) else {
+            return false;
+        };
+        let length = queue.len();
+        queue.retain(|waiting| *waiting != patron);
+        let removed = queue.len() != length;
+
+        if queue.is_empty() {
+            self.holds.remove(&item);
+        }
+        removed
+    }
+
+    ///Removes an item from the catalog and takes it out of the holds queues.  An item that is lent out is not
+    ///removed, so no loan is left pointing at nothing.  `on_remove` is passed on to `Catalog::remove_with`.
+    pub fn remove_item(
+        &mut self,
+        catalog: &mut Catalog,
+        item: MediaId,
+        on_remove: OnRemove,
+    ) -> Result<Media, LendingError> {
+        if let Some(loan) = self.loans.get(&item) {
+            return Err(LendingError::StillLent {
+                item,
+                patron: loan.patron,
+            });
+        }
+
+        let media = catalog
+            .remove_with(item, on_remove)
+            .map_err(|error| match error {
+                CollectionError::ItemNotFound(item) => LendingError::ItemNotFound(item),
+                error => LendingError::InCollection(error),
+            })?;
+        self.holds.remove(&item);
+        Ok(media)
+    }
+
+    ///Every loan that was due before `today` along with how many days late it is, the latest first.
+    pub fn overdue(&self, today: Day) -> Vec<(&Loan, u32)> {
+        let mut overdue: Vec<(&Loan, u32)> = self
+            .loans
+            .values()
+            .filter(|loan| loan.due < today)
+            .map(|loan| (loan, today.days_since(loan.due)))
+            .collect();
+        overdue.sort_by(|(first, first_days), (second, second_days)| {
+            second_days
+                .cmp(first_days)
+                .then(first.item.cmp(&second.item))
+        });
+        overdue
+    }
+
+    ///Formats the overdue loans as text, one line per loan with the item and patron names from the catalog.
+    pub fn overdue_report(&self, catalog: &Catalog, today: Day) -> String {
+        let overdue = self.overdue(today);
+        let mut report = format!("Overdue on {} ({}):\n", today, overdue.len());
+
+        for (loan, days) in overdue {
+            let title = catalog
+                .get(loan.item)
+                .map(|media| media.title())
+                .unwrap_or("(removed from catalog)");
+            let name = self
+                .patron(loan.patron)
+                .map(|patron| patron.name.as_str())
+                .unwrap_or("(unknown patron)");
+            report += &format!(
+                "  {} '{}' lent to {} {}, due {}, {} days late\n",
+                loan.item, title, loan.patron, name, loan.due, days
+            );
+        }
+
+        report
+    }
+
+    ///Writes the patrons, loans and holds to a JSON file.
+    pub fn save(&self, path: &str) -> Result<(), io::Error> {
+        fs::write(
+            path,
+            serde_json::to_string_pretty(self).expect("library should always serialize to JSON"),
+        )
+    }
+
+    ///Reads a Library saved by `save`, marking its loans as lent in the catalog it is used with.
+    pub fn load(path: &str, catalog: &mut Catalog) -> Result<Library, io::Error> {
+        let text = fs::read_to_string(path)?;
+        let library: Library = serde_json::from_str(&text)?;
+        for item in library.loans.keys() {
+            catalog.set_lent(*item, true);
+        }
+        Ok(library)
+    }
+
+    fn check_lendable(&self, catalog: &Catalog, item: MediaId) -> Result<(), LendingError> {
+        match catalog.get(item) {
+            None => Err(LendingError::ItemNotFound(item)),
+            Some(Media::Placeholder { .. }) => Err(LendingError::NotLendable(item)),
+            Some(_) => Ok(()),
+        }
+    }
+
+    fn check_patron(&self, patron: PatronId) -> Result<(), LendingError> {
+        if self.patrons.contains_key(&patron) {
+            Ok(())
+        } else {
+            Err(LendingError::PatronNotFound(patron))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::duplicates::MergeError;
+    use crate::content::media::Metadata;
+
+    const TODAY: Day = Day(20_000);
+
+    fn catalog_with_book() -> (Catalog, MediaId) {
+        let mut catalog = Catalog::new();
+        let item = catalog.add_media(Media::Book {
+            title: String::from("Dune"),
+            author: String::from("Frank Herbert"),
+            metadata: Metadata::default(),
+        });
+        (catalog, item)
+    }
+
+    #[test]
+    fn holds_queue_in_order() {
+        let (mut catalog, item) = catalog_with_book();
+        let mut library = Library::new();
+        let first = library.add_patron("First");
+        let second = library.add_patron("Second");
+        let third = library.add_patron("Third");
+
+        library.check_out(&mut catalog, item, first, TODAY).unwrap();
+        assert_eq!(library.place_hold(&catalog, item, second), Ok(1));
+        assert_eq!(library.place_hold(&catalog, item, third), Ok(2));
+        assert_eq!(
+            library.place_hold(&catalog, item, first),
+            Err(LendingError::AlreadyHolding {
+                item,
+                patron: first
+            })
+        );
+
+        let returned = library
+            .check_in(&mut catalog, item, TODAY.plus_days(20).unwrap())
+            .unwrap();
+        assert_eq!(returned.days_overdue, 6);
+        assert_eq!(returned.hold_for, Some(second));
+
+        assert_eq!(
+            library
+                .check_out(&mut catalog, item, third, TODAY)
+                .map(|loan| loan.patron),
+            Err(LendingError::OnHold {
+                item,
+                patron: second
+            })
+        );
+        library
+            .check_out(&mut catalog, item, second, TODAY)
+            .unwrap();
+        assert_eq!(library.holds(item).collect::<Vec<_>>(), vec![third]);
+    }
+
+    #[test]
+    fn overdue_lists_the_latest_first() {
+        let (mut catalog, first) = catalog_with_book();
+        let second = catalog.add_media(Media::Placeholder {
+            title: String::from("Lost"),
+            metadata: Metadata::default(),
+        });
+        let mut library = Library::with_loan_days(7);
+        let patron = library.add_patron("Reader");
+
+        assert_eq!(
+            library
+                .check_out(&mut catalog, second, patron, TODAY)
+                .map(|loan| loan.item),
+            Err(LendingError::NotLendable(second))
+        );
+        library
+            .check_out(&mut catalog, first, patron, TODAY)
+            .unwrap();
+
+        assert!(library.overdue(TODAY.plus_days(7).unwrap()).is_empty());
+        let overdue = library.overdue(TODAY.plus_days(10).unwrap());
+        assert_eq!(overdue.len(), 1);
+        assert_eq!((overdue[0].0.item, overdue[0].1), (first, 3));
+    }
+
+    #[test]
+    fn a_lent_item_is_not_removed() {
+        let (mut catalog, item) = catalog_with_book();
+        let mut library = Library::new();
+        let reader = library.add_patron("Reader");
+        let waiting = library.add_patron("Waiting");
+        library
+            .check_out(&mut catalog, item, reader, TODAY)
+            .unwrap();
+        library.place_hold(&catalog, item, waiting).unwrap();
+
+        assert_eq!(
+            library.remove_item(&mut catalog, item, OnRemove::Refuse),
+            Err(LendingError::StillLent {
+                item,
+                patron: reader
+            })
+        );
+        assert!(catalog.get(item).is_some());
+
+        library.check_in(&mut catalog, item, TODAY).unwrap();
+        library
+            .remove_item(&mut catalog, item, OnRemove::Refuse)
+            .unwrap();
+        assert!(catalog.get(item).is_none());
+        assert_eq!(library.holds(item).count(), 0);
+        assert_eq!(
+            library.remove_item(&mut catalog, item, OnRemove::Refuse),
+            Err(LendingError::ItemNotFound(item))
+        );
+    }
+
+    #[test]
+    fn the_catalog_keeps_a_lent_item_until_it_is_checked_in() {
+        let (mut catalog, item) = catalog_with_book();
+        let other = catalog.add_media(Media::Book {
+            title: String::from("Dune"),
+            author: String::from("Frank Herbert"),
+            metadata: Metadata::default(),
+        });
+        let added = catalog.add_media(Media::Book {
+            title: String::from("Emma"),
+            author: String::from("Jane Austen"),
+            metadata: Metadata::default(),
+        });
+        let mut library = Library::new();
+        let reader = library.add_patron("Reader");
+        library
+            .check_out(&mut catalog, item, reader, TODAY)
+            .unwrap();
+        library
+            .check_out(&mut catalog, added, reader, TODAY)
+            .unwrap();
+
+        assert!(matches!(
+            catalog.remove_with(item, OnRemove::Cascade),
+            Err(CollectionError::Lent(id)) if id == item
+        ));
+        assert!(matches!(
+            catalog.merge(other, item),
+            Err(MergeError::Lent(id)) if id == item
+        ));
+        //undoing the add of an item that has been lent out since leaves it in the catalog
+        catalog.undo().unwrap();
+        assert!(catalog.get(added).is_some());
+
+        //a library read back from a file marks its loans in the catalog again
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("library.json");
+        library.save(path.to_str().unwrap()).unwrap();
+        let (mut reopened, item) = catalog_with_book();
+        let mut library = Library::load(path.to_str().unwrap(), &mut reopened).unwrap();
+        assert!(reopened.is_lent(item));
+        assert!(reopened.remove(item).is_err());
+
+        library.check_in(&mut reopened, item, TODAY).unwrap();
+        assert!(!reopened.is_lent(item));
+        assert!(reopened.remove(item).is_ok());
+    }
+
+    #[test]
+    fn a_loan_too_long_for_a_day_is_refused() {
+        let (mut catalog, item) = catalog_with_book();
+        let mut library = Library::with_loan_days(u32::MAX);
+        let reader = library.add_patron("Reader");
+
+        assert_eq!(
+            library
+                .check_out(&mut catalog, item, reader, TODAY)
+                .map(|loan| loan.due),
+            Err(LendingError::LoanTooLong(u32::MAX))
+        );
+        assert!(!catalog.is_lent(item));
+    }
+
+    #[test]
+    fn a_patron_with_loans_is_not_removed() {
+        let (mut catalog, item) = catalog_with_book();
+        let mut library = Library::new();
+        let patron = library.add_patron("Reader");
+        library
+            .check_out(&mut catalog, item, patron, TODAY)
+            .unwrap();
+
+        assert_eq!(
+            library.remove_patron(patron).map(|patron| patron.id),
+            Err(LendingError::HasLoans(patron))
+        );
+        library.check_in(&mut catalog, item, TODAY).unwrap();
+        assert_eq!(
+            library.remove_patron(patron).map(|patron| patron.id),
+            Ok(patron)
+        );
+    }
+}
@@ -1,14 +1,253 @@
-#[derive(Debug)]
+use serde::de;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::item::CustomMedia;
+
+///The kind of a Media item, without any of its data, for filtering by kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MediaKind {
+    Book,
+    Movie,
+    Audiobook,
+    Podcast,
+    Placeholder,
+    //a kind from outside this crate, by the name it is saved as
+    Custom(&'static str),
+}
+
+impl MediaKind {
+    pub const BUILT_IN: [MediaKind; 5] = [
+        MediaKind::Book,
+        MediaKind::Movie,
+        MediaKind::Audiobook,
+        MediaKind::Podcast,
+        MediaKind::Placeholder,
+    ];
+
+    ///The name used for this kind in saved files, like "book".
+    pub fn name(&self) -> &'static str {
+        match self {
+            MediaKind::Book => "book",
+            MediaKind::Movie => "movie",
+            MediaKind::Audiobook => "audiobook",
+            MediaKind::Podcast => "podcast",
+            MediaKind::Placeholder => "placeholder",
+            MediaKind::Custom(name) => name,
+        }
+    }
+
+    ///Finds the built-in kind with the given name, ignoring case.  `KindRegistry::kind` finds registered kinds too.
+    pub fn from_name(name: &str) -> Option<MediaKind> {
+        MediaKind::BUILT_IN
+            .into_iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+///Details that every kind of Media can have, all of them optional.
+//fields that aren't filled in are left out when saving, and default to empty when loading
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<u16>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub genres: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_minutes: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub isbn: Option<String>, //International Standard Book Number, for books and audiobooks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub isan: Option<String>, //International Standard Audiovisual Number, for movies
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publisher: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl Metadata {
+    ///Formats whichever fields are filled in, like "1999, Sci-Fi/Action, 136 min, English", or an empty string if none are.
+    pub fn summary(&self) -> String {
+        let mut parts = vec![];
+
+        if let Some(year) = self.year {
+            parts.push(year.to_string());
+        }
+        if !self.genres.is_empty() {
+            parts.push(self.genres.join("/"));
+        }
+        if let Some(minutes) = self.duration_minutes {
+            parts.push(format!("{} min", minutes));
+        }
+        if let Some(language) = &self.language {
+            parts.push(language.clone());
+        }
+        if let Some(publisher) = &self.publisher {
+            parts.push(publisher.clone());
+        }
+        if let Some(isbn) = &self.isbn {
+            parts.push(format!("ISBN {}", isbn));
+        }
+        if let Some(isan) = &self.isan {
+            parts.push(format!("ISAN {}", isan));
+        }
+        if !self.tags.is_empty() {
+            parts.push(format!("tags: {}", self.tags.join(", ")));
+        }
+
+        parts.join(", ")
+    }
+}
+
+///One episode of a podcast show.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Episode {
+    pub number: u32,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_minutes: Option<u32>,
+}
+
+//when saved, each item is an object with a "kind" field saying which variant it is, like {"kind": "book", "title": ...}
+//remote = "Self" makes the derives write Media::serialize and Media::deserialize functions instead of implementing
+//the traits, so the implementations below can use them for the built-in kinds and handle Custom themselves
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(remote = "Self", tag = "kind", rename_all = "snake_case")]
 pub enum Media {
-    Book { title: String, author: String },
-    Movie { title: String, director: String },
-    Audiobook { title: String },
+    Book {
+        title: String,
+        author: String,
+        #[serde(default)]
+        metadata: Metadata,
+    },
+    Movie {
+        title: String,
+        director: String,
+        #[serde(default)]
+        metadata: Metadata,
+    },
+    Audiobook {
+        title: String,
+        #[serde(default)]
+        metadata: Metadata,
+    },
     // Podcast { episode_number: u32 },
-    Podcast(u32), //this is syntax that works like above, but where 'episode_number' is implied and takes less typing to work with
-    Placeholder,
+    // Podcast(u32), //this is syntax that works like above, but where 'episode_number' is implied and takes less typing to work with
+    //a Podcast used to be just an episode number, now it is a whole show with the episodes that belong to it
+    Podcast {
+        show: String,
+        #[serde(default)]
+        episodes: Vec<Episode>,
+        #[serde(default)]
+        metadata: Metadata,
+    },
+    //a Placeholder used to have no data at all, it now has a title so it can stand in for something that isn't catalogued yet
+    Placeholder {
+        title: String,
+        #[serde(default)]
+        metadata: Metadata,
+    },
+    //a kind from outside this crate, which can't add variants here, implementing the MediaItem trait instead
+    #[serde(skip)]
+    Custom(CustomMedia),
+}
+
+impl Serialize for Media {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Media::Custom(custom) => custom.serialize(serializer),
+            _ => Media::serialize(self, serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Media {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        //the kind has to be read before knowing how to read the rest, so the fields are read into a map first
+        let mut fields = serde_json::Map::deserialize(deserializer)?;
+
+        //kinds that aren't built in are kept as they were saved, until loading reads them with a KindRegistry
+        let unregistered = fields
+            .get("kind")
+            .and_then(serde_json::Value::as_str)
+            .filter(|name| MediaKind::from_name(name).is_none())
+            .map(String::from);
+        match unregistered {
+            Some(kind) => {
+                fields.remove("kind");
+                Ok(Media::Custom(CustomMedia::unregistered(&kind, fields)))
+            }
+            None => {
+                Media::deserialize(serde_json::Value::Object(fields)).map_err(de::Error::custom)
+            }
+        }
+    }
 }
 
 impl Media {
+    pub fn kind(&self) -> MediaKind {
+        match self {
+            Media::Book { .. } => MediaKind::Book,
+            Media::Movie { .. } => MediaKind::Movie,
+            Media::Audiobook { .. } => MediaKind::Audiobook,
+            Media::Podcast { .. } => MediaKind::Podcast,
+            Media::Placeholder { .. } => MediaKind::Placeholder,
+            Media::Custom(custom) => MediaKind::Custom(custom.item().kind_name()),
+        }
+    }
+
+    ///Returns the title, which is the show's name for a Podcast.
+    pub fn title(&self) -> &str {
+        match self {
+            Media::Book { title, .. }
+            | Media::Movie { title, .. }
+            | Media::Audiobook { title, .. }
+            | Media::Placeholder { title, .. } => title,
+            Media::Podcast { show, .. } => show,
+            Media::Custom(custom) => custom.item().title(),
+        }
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        match self {
+            Media::Book { metadata, .. }
+            | Media::Movie { metadata, .. }
+            | Media::Audiobook { metadata, .. }
+            | Media::Podcast { metadata, .. }
+            | Media::Placeholder { metadata, .. } => metadata,
+            Media::Custom(custom) => custom.item().metadata(),
+        }
+    }
+
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        match self {
+            Media::Book { metadata, .. }
+            | Media::Movie { metadata, .. }
+            | Media::Audiobook { metadata, .. }
+            | Media::Podcast { metadata, .. }
+            | Media::Placeholder { metadata, .. } => metadata,
+            Media::Custom(custom) => custom.item_mut().metadata_mut(),
+        }
+    }
+
+    ///Returns the person who made it, the author of a Book or the director of a Movie.
+    ///For other kinds that can have several creators, this is the first one.
+    pub fn creator(&self) -> Option<&str> {
+        self.creators().into_iter().next()
+    }
+
+    ///Returns everyone who made it, which is at most one person for the built-in kinds.
+    pub fn creators(&self) -> Vec<&str> {
+        match self {
+            Media::Book { author, .. } => vec![author],
+            Media::Movie { director, .. } => vec![director],
+            Media::Custom(custom) => custom.item().creators(),
+            _ => vec![],
+        }
+    }
+
     pub fn description(&self) -> String {
         //Rust will not allow you to process the properties of self, like self.title, until the type has been first determined,
         //even though all three variants have a .title property
@@ -37,27 +276,141 @@ impl Media {
         //which in this case, looks to be an implicit return statement of the format!() macro
 
         //it is also possible to wrap curly braces around the statement after the => arrow, which allows multiline blocks of statements
-        match self {
-            Media::Book { title, author } => {
+        let description = match self {
+            //'..' skips the fields that aren't needed in this arm, metadata is added for every variant below the match
+            Media::Book { title, author, .. } => {
                 format!("Book: {} by {}", title, author)
             }
-            Media::Movie { title, director } => {
+            Media::Movie {
+                title, director, ..
+            } => {
                 format!("Movie: {} by {}", title, director)
             }
-            Media::Audiobook { title } => {
+            Media::Audiobook { title, .. } => {
                 format!("Audiobook: {}", title)
             }
-            //above, Podcast(u32) is how the enum variant is defined,
-            //so an arbitrary name like "episode_number" can be made up here
-            //to be able to use the u32 value in this match arm
-            Media::Podcast(episode_number) => {
-                format!("Podcast episode number: {}", episode_number)
+            //above, Podcast(u32) used to be how the enum variant was defined,
+            //so an arbitrary name like "episode_number" could be made up in this match arm
+            //to be able to use the u32 value, it now has named fields like the other variants
+            Media::Podcast { show, episodes, .. } => {
+                let latest = episodes
+                    .iter()
+                    .max_by_key(|episode| episode.number)
+                    .map(|episode| format!(", latest is {}: {}", episode.number, episode.title))
+                    .unwrap_or_default();
+                format!(
+                    "Podcast: {} with {} episodes{}",
+                    show,
+                    episodes.len(),
+                    latest
+                )
             }
-            //above, Placeholder had no curly braces or fields at all
-            //so it pattern matches in this way here
-            Media::Placeholder => {
-                format!("Placeholder")
+            Media::Placeholder { title, .. } => {
+                format!("Placeholder: {}", title)
             }
+            Media::Custom(custom) => custom.item().description(),
+        };
+
+        let metadata = self.metadata().summary();
+        if metadata.is_empty() {
+            description
+        } else {
+            format!("{} ({})", description, metadata)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn episode(number: u32, title: &str) -> Episode {
+        Episode {
+            number,
+            title: title.to_string(),
+            duration_minutes: None,
+        }
+    }
+
+    #[test]
+    fn describes_each_kind_with_its_metadata() {
+        let movie = Media::Movie {
+            title: "The Matrix".to_string(),
+            director: "The Wachowskis".to_string(),
+            metadata: Metadata {
+                year: Some(1999),
+                genres: vec!["Sci-Fi".to_string(), "Action".to_string()],
+                duration_minutes: Some(136),
+                language: Some("English".to_string()),
+                ..Metadata::default()
+            },
+        };
+        assert_eq!(
+            movie.description(),
+            "Movie: The Matrix by The Wachowskis (1999, Sci-Fi/Action, 136 min, English)"
+        );
+
+        let podcast = Media::Podcast {
+            show: "Hardcore History".to_string(),
+            episodes: vec![
+                episode(2, "Second"),
+                episode(7, "Latest"),
+                episode(5, "Fifth"),
+            ],
+            metadata: Metadata::default(),
+        };
+        assert_eq!(
+            podcast.description(),
+            "Podcast: Hardcore History with 3 episodes, latest is 7: Latest"
+        );
+        assert_eq!(podcast.title(), "Hardcore History");
+        assert_eq!(podcast.creator(), None);
+
+        let placeholder = Media::Placeholder {
+            title: "Untitled".to_string(),
+            metadata: Metadata {
+                isbn: Some("9780261103573".to_string()),
+                tags: vec!["to find".to_string(), "gift".to_string()],
+                ..Metadata::default()
+            },
+        };
+        assert_eq!(
+            placeholder.description(),
+            "Placeholder: Untitled (ISBN 9780261103573, tags: to find, gift)"
+        );
+    }
+
+    #[test]
+    fn saves_only_the_metadata_that_is_filled_in() {
+        let book = Media::Book {
+            title: "Dune".to_string(),
+            author: "Frank Herbert".to_string(),
+            metadata: Metadata {
+                year: Some(1965),
+                ..Metadata::default()
+            },
+        };
+        let json = serde_json::to_string(&book).unwrap();
+        assert_eq!(
+            json,
+            r#"{"kind":"book","title":"Dune","author":"Frank Herbert","metadata":{"year":1965}}"#
+        );
+        assert_eq!(serde_json::from_str::<Media>(&json).unwrap(), book);
+
+        //items saved before metadata was added load with none
+        let old: Media = serde_json::from_str(r#"{"kind":"audiobook","title":"Dracula"}"#).unwrap();
+        assert_eq!(old.metadata(), &Metadata::default());
+        let old: Media = serde_json::from_str(r#"{"kind":"podcast","show":"Serial"}"#).unwrap();
+        assert_eq!(old.description(), "Podcast: Serial with 0 episodes");
+    }
+
+    #[test]
+    fn finds_kinds_by_name() {
+        assert_eq!(MediaKind::from_name(" Movie "), Some(MediaKind::Movie));
+        assert_eq!(MediaKind::from_name("PODCAST"), Some(MediaKind::Podcast));
+        assert_eq!(MediaKind::from_name("vinyl"), None);
+        for kind in MediaKind::BUILT_IN {
+            assert_eq!(MediaKind::from_name(kind.name()), Some(kind));
         }
     }
 }
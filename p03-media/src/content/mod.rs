@@ -1,2 +1,15 @@
 pub mod catalog;
-pub mod media;
\ No newline at end of file
+pub mod collections;
+pub mod dates;
+pub mod duplicates;
+pub mod enrich;
+pub mod history;
+pub mod index;
+pub mod isbn;
+pub mod item;
+pub mod lending;
+pub mod media;
+pub mod query;
+pub mod reviews;
+pub mod sort;
+pub mod storage;
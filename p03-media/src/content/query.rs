@@ -0,0 +1,163 @@
+use super::media::{Media, MediaKind};
+
+///A condition that Media items can be checked against when searching a Catalog.
+///Filters can be combined with `and`, `or` and `not`, for example books by an author whose title contains a word:
+///`Filter::kind(MediaKind::Book).and(Filter::creator_contains("tolkien")).and(Filter::title_contains("ring"))`
+#[derive(Debug, Clone)]
+pub enum Filter {
+    //the text in these is stored lowercased so matching ignores case
+    TitleContains(String),
+    CreatorContains(String),
+    Kind(MediaKind),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    ///Matches items whose title contains the text, ignoring case.
+    pub fn title_contains(text: &str) -> Filter {
+        Filter::TitleContains(text.to_lowercase())
+    }
+
+    ///Matches items whose author or director, or any of their creators for other kinds, contains the text, ignoring case.
+    pub fn creator_contains(text: &str) -> Filter {
+        Filter::CreatorContains(text.to_lowercase())
+    }
+
+    ///Matches items of the given kind.
+    pub fn kind(kind: MediaKind) -> Filter {
+        Filter::Kind(kind)
+    }
+
+    ///Matches items that match both filters.
+    pub fn and(self, other: Filter) -> Filter {
+        Filter::And(Box::new(self), Box::new(other))
+    }
+
+    ///Matches items that match either filter.
+    pub fn or(self, other: Filter) -> Filter {
+        Filter::Or(Box::new(self), Box::new(other))
+    }
+
+    ///Matches items that don't match this filter.
+    #[allow(clippy::should_implement_trait)] //reads better in a chain than `!filter`
+    pub fn not(self) -> Filter {
+        Filter::Not(Box::new(self))
+    }
+
+    pub fn matches(&self, media: &Media) -> bool {
+        match self {
+            Filter::TitleContains(text) => contains_ignoring_case(media.title(), text),
+            //items without a creator have no creators to match, so searching by creator won't return an
+            //Audiobook, Podcast or Placeholder
+            Filter::CreatorContains(text) => media
+                .creators()
+                .into_iter()
+                .any(|creator| contains_ignoring_case(creator, text)),
+            Filter::Kind(kind) => media.kind() == *kind,
+            Filter::And(first, second) => first.matches(media) && second.matches(media),
+            Filter::Or(first, second) => first.matches(media) || second.matches(media),
+            Filter::Not(filter) => !filter.matches(media),
+        }
+    }
+}
+
+fn contains_ignoring_case(field: &str, lowercase_text: &str) -> bool {
+    field.to_lowercase().contains(lowercase_text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::catalog::Catalog;
+    use crate::content::media::Metadata;
+
+    fn catalog() -> Catalog {
+        let mut catalog = Catalog::new();
+        for media in [
+            Media::Book {
+                title: "The Lord of the Rings".to_string(),
+                author: "J.R.R. Tolkien".to_string(),
+                metadata: Metadata::default(),
+            },
+            Media::Book {
+                title: "The Hobbit".to_string(),
+                author: "J.R.R. Tolkien".to_string(),
+                metadata: Metadata::default(),
+            },
+            Media::Movie {
+                title: "The Lord of the Rings".to_string(),
+                director: "Peter Jackson".to_string(),
+                metadata: Metadata::default(),
+            },
+            Media::Audiobook {
+                title: "Rings of Saturn".to_string(),
+                metadata: Metadata::default(),
+            },
+        ] {
+            catalog.add_media(media);
+        }
+        catalog
+    }
+
+    //the titles and kinds of the items the filter finds, in the order they were added
+    fn found(catalog: &Catalog, filter: Filter) -> Vec<String> {
+        catalog
+            .search(&filter)
+            .map(|(_, media)| format!("{} {}", media.kind().name(), media.title()))
+            .collect()
+    }
+
+    #[test]
+    fn matches_text_ignoring_case() {
+        let catalog = catalog();
+        assert_eq!(
+            found(&catalog, Filter::title_contains("RINGS")),
+            vec![
+                "book The Lord of the Rings",
+                "movie The Lord of the Rings",
+                "audiobook Rings of Saturn"
+            ]
+        );
+        assert_eq!(
+            found(&catalog, Filter::creator_contains("jackson")),
+            vec!["movie The Lord of the Rings"]
+        );
+        //an audiobook has no creator, so it never matches a creator filter
+        assert_eq!(found(&catalog, Filter::creator_contains("")).len(), 3);
+        assert!(found(&catalog, Filter::title_contains("dune")).is_empty());
+    }
+
+    #[test]
+    fn combines_filters() {
+        let catalog = catalog();
+        assert_eq!(
+            found(
+                &catalog,
+                Filter::kind(MediaKind::Book)
+                    .and(Filter::creator_contains("tolkien"))
+                    .and(Filter::title_contains("ring"))
+            ),
+            vec!["book The Lord of the Rings"]
+        );
+        assert_eq!(
+            found(
+                &catalog,
+                Filter::kind(MediaKind::Movie).or(Filter::kind(MediaKind::Audiobook))
+            ),
+            vec!["movie The Lord of the Rings", "audiobook Rings of Saturn"]
+        );
+        assert_eq!(
+            found(
+                &catalog,
+                Filter::title_contains("rings").and(Filter::kind(MediaKind::Book).not())
+            ),
+            vec!["movie The Lord of the Rings", "audiobook Rings of Saturn"]
+        );
+        //results come out in the order the items were added
+        let filter = Filter::title_contains("the");
+        let first = catalog.search(&filter).next();
+        assert_eq!(first.map(|(id, _)| id.0), Some(1));
+    }
+}
@@ -0,0 +1,510 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::catalog::{Catalog, MediaId};
+use super::duplicates::normalize;
+use super::media::Media;
+
+pub const MIN_RATING: u8 = 1;
+pub const MAX_RATING: u8 = 5;
+//reviews are meant to be a few sentences, not essays
+pub const MAX_REVIEW_LENGTH: usize = 500;
+
+//a rating at or above this counts as liking an item when recommending for a reviewer
+const LIKED_RATING: u8 = 4;
+
+//how much each kind of evidence counts towards a recommendation's score
+const GENRE_WEIGHT: f64 = 1.0;
+const CREATOR_WEIGHT: f64 = 1.0;
+const TAG_WEIGHT: f64 = 0.5;
+const RATING_WEIGHT: f64 = 2.0;
+//co-rating similarity from only a couple of shared reviewers is shrunk towards 0, since it could easily be chance
+const SHARED_REVIEWER_SHRINK: f64 = 2.0;
+
+///A rating from 1 to 5 stars that someone gave an item, with an optional short review.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Review {
+    pub reviewer: String,
+    pub rating: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+///Why a review couldn't be added.
+#[derive(Debug, PartialEq)]
+pub enum ReviewError {
+    ItemNotFound(MediaId),
+    InvalidRating(u8),
+    NoReviewer,
+    TooLong(usize),
+}
+
+impl fmt::Display for ReviewError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReviewError::ItemNotFound(id) => write!(f, "there is no item with ID {}", id),
+            ReviewError::InvalidRating(rating) => {
+                write!(
+                    f,
+                    "a rating must be from {} to {}, not {}",
+                    MIN_RATING, MAX_RATING, rating
+                )
+            }
+            ReviewError::NoReviewer => write!(f, "a review needs the name of who wrote it"),
+            ReviewError::TooLong(length) => {
+                write!(
+                    f,
+                    "a review can be at most {} characters, this one is {}",
+                    MAX_REVIEW_LENGTH, length
+                )
+            }
+        }
+    }
+}
+
+///An item suggested because of another item or a reviewer's ratings, and why.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recommendation {
+    pub id: MediaId,
+    pub score: f64,
+    pub reasons: Vec<String>,
+}
+
+///Every review in a Catalog, by the item they are for.  Each reviewer has at most one review per item.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Reviews {
+    by_item: BTreeMap<MediaId, Vec<Review>>,
+}
+
+impl Reviews {
+    pub fn is_empty(&self) -> bool {
+        self.by_item.is_empty()
+    }
+
+    ///Iterates over every item that has reviews, along with its reviews.
+    pub fn iter(&self) -> impl Iterator<Item = (MediaId, &[Review])> {
+        self.by_item
+            .iter()
+            .map(|(id, reviews)| (*id, reviews.as_slice()))
+    }
+
+    ///Adds a review, replacing the reviewer's earlier review of the same item if they had one.
+    pub(super) fn insert(&mut self, id: MediaId, review: Review) {
+        let reviews = self.by_item.entry(id).or_default();
+        match reviews
+            .iter_mut()
+            .find(|existing| same_reviewer(&existing.reviewer, &review.reviewer))
+        {
+            Some(existing) => *existing = review,
+            None => reviews.push(review),
+        }
+    }
+
+    ///Replaces every review of an item, for undoing changes to them.
+    pub(super) fn set(&mut self, id: MediaId, reviews: Vec<Review>) {
+        if reviews.is_empty() {
+            self.by_item.remove(&id);
+        } else {
+            self.by_item.insert(id, reviews);
+        }
+    }
+
+    pub(super) fn remove_item(&mut self, id: MediaId) {
+        self.by_item.remove(&id);
+    }
+
+    ///Moves the reviews of one item onto another, like when two items are merged.
+    ///A reviewer who reviewed both keeps the review they gave the item they are moved to.
+    pub(super) fn move_reviews(&mut self, from: MediaId, to: MediaId) {
+        for review in self.by_item.remove(&from).unwrap_or_default() {
+            let existing = self.by_item.entry(to).or_default();
+            if !existing
+                .iter()
+                .any(|other| same_reviewer(&other.reviewer, &review.reviewer))
+            {
+                existing.push(review);
+            }
+        }
+    }
+
+    //every reviewer's rating of every item they reviewed, with reviewer names normalized
+    fn ratings_by_item(&self) -> HashMap<MediaId, HashMap<String, f64>> {
+        self.by_item
+            .iter()
+            .map(|(id, reviews)| {
+                let ratings = reviews
+                    .iter()
+                    .map(|review| (normalize(&review.reviewer), review.rating as f64))
+                    .collect();
+                (*id, ratings)
+            })
+            .collect()
+    }
+}
+
+impl Catalog {
+    ///Rates an item for a reviewer, with an optional short review.  Rating the same item again replaces the old review.
+    pub fn review(
+        &mut self,
+        id: MediaId,
+        reviewer: &str,
+        rating: u8,
+        text: Option<&str>,
+    ) -> Result<(), ReviewError> {
+        if self.get(id).is_none() {
+            return Err(ReviewError::ItemNotFound(id));
+        }
+        if !(MIN_RATING..=MAX_RATING).contains(&rating) {
+            return Err(ReviewError::InvalidRating(rating));
+        }
+        let reviewer = reviewer.trim();
+        if reviewer.is_empty() {
+            return Err(ReviewError::NoReviewer);
+        }
+
+        let text = text.map(str::trim).filter(|text| !text.is_empty());
+        if let Some(length) = text
+            .map(|text| text.chars().count())
+            .filter(|length| *length > MAX_REVIEW_LENGTH)
+        {
+            return Err(ReviewError::TooLong(length));
+        }
+
+        let review = Review {
+            reviewer: reviewer.to_string(),
+            rating,
+            text: text.map(String::from),
+        };
+        self.reviews_mut().insert(id, review);
+        Ok(())
+    }
+
+    pub fn reviews(&self, id: MediaId) -> &[Review] {
+        self.all_reviews()
+            .by_item
+            .get(&id)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    ///The mean rating of an item, or None if nobody has rated it.
+    pub fn average_rating(&self, id: MediaId) -> Option<f64> {
+        let reviews = self.reviews(id);
+        if reviews.is_empty() {
+            return None;
+        }
+        Some(
+            reviews
+                .iter()
+                .map(|review| review.rating as f64)
+                .sum::<f64>()
+                / reviews.len() as f64,
+        )
+    }
+
+    ///"If you liked this, try these": up to `count` other items, most similar first.  Items are similar when they share
+    ///genres, tags or a creator, and when the people who rated both rated them alike.
+    pub fn recommend_similar(&self, id: MediaId, count: usize) -> Vec<Recommendation> {
+        let Some(liked) = self.get(id) else {
+            return vec![];
+        };
+        let ratings = self.all_reviews().ratings_by_item();
+
+        let mut recommendations: Vec<Recommendation> = self
+            .iter()
+            .filter(|(other_id, other)| *other_id != id && is_recommendable(other))
+            .filter_map(|(other_id, other)| {
+                let (score, reasons) =
+                    similarity(liked, other, ratings.get(&id), ratings.get(&other_id));
+                (score > 0.0).then_some(Recommendation {
+                    id: other_id,
+                    score,
+                    reasons,
+                })
+            })
+            .collect();
+
+        sort_and_truncate(&mut recommendations, count);
+        recommendations
+    }
+
+    ///Suggests up to `count` items the reviewer hasn't rated yet, from the items they rated highly.
+    pub fn recommend_for(&self, reviewer: &str, count: usize) -> Vec<Recommendation> {
+        let reviewer = normalize(reviewer);
+        let ratings = self.all_reviews().ratings_by_item();
+
+        let rated: BTreeSet<MediaId> = ratings
+            .iter()
+            .filter(|(_, item_ratings)| item_ratings.contains_key(&reviewer))
+            .map(|(id, _)| *id)
+            .collect();
+        let liked: Vec<(MediaId, &Media, f64)> = rated
+            .iter()
+            .filter_map(|id| {
+                let rating = ratings[id][&reviewer];
+                let media = self.get(*id)?;
+                (rating >= LIKED_RATING as f64).then_some((*id, media, rating))
+            })
+            .collect();
+
+        let mut recommendations: Vec<Recommendation> = self
+            .iter()
+            .filter(|(id, media)| !rated.contains(id) && is_recommendable(media))
+            .filter_map(|(id, media)| {
+                let mut score = 0.0;
+                let mut reasons = vec![];
+
+                for (liked_id, liked_media, rating) in &liked {
+                    let (similarity, _) =
+                        similarity(liked_media, media, ratings.get(liked_id), ratings.get(&id));
+                    if similarity > 0.0 {
+                        //a 5 star favourite counts for more than something rated 4
+                        score += similarity * (rating - LIKED_RATING as f64 + 1.0);
+                        reasons.push(format!("you liked {}", liked_media.title()));
+                    }
+                }
+
+                (score > 0.0).then_some(Recommendation { id, score, reasons })
+            })
+            .collect();
+
+        sort_and_truncate(&mut recommendations, count);
+        recommendations
+    }
+}
+
+///How alike two items are, and the reasons why, from their shared details and from how people rated them.
+fn similarity(
+    first: &Media,
+    second: &Media,
+    first_ratings: Option<&HashMap<String, f64>>,
+    second_ratings: Option<&HashMap<String, f64>>,
+) -> (f64, Vec<String>) {
+    let mut score = 0.0;
+    let mut reasons = vec![];
+
+    let shared_genres = shared(&first.metadata().genres, &second.metadata().genres);
+    if !shared_genres.is_empty() {
+        score += GENRE_WEIGHT
+            * overlap(
+                &first.metadata().genres,
+                &second.metadata().genres,
+                shared_genres.len(),
+            );
+        reasons.push(format!("also {}", shared_genres.join(", ")));
+    }
+
+    if let (Some(first_creator), Some(second_creator)) = (first.creator(), second.creator()) {
+        if normalize(first_creator) == normalize(second_creator) {
+            score += CREATOR_WEIGHT;
+            reasons.push(format!("also by {}", second_creator));
+        }
+    }
+
+    let shared_tags = shared(&first.metadata().tags, &second.metadata().tags);
+    if !shared_tags.is_empty() {
+        score += TAG_WEIGHT
+            * overlap(
+                &first.metadata().tags,
+                &second.metadata().tags,
+                shared_tags.len(),
+            );
+        reasons.push(format!("also tagged {}", shared_tags.join(", ")));
+    }
+
+    if let (Some(first_ratings), Some(second_ratings)) = (first_ratings, second_ratings) {
+        let rating_similarity = co_rating_similarity(first_ratings, second_ratings);
+        if rating_similarity > 0.0 {
+            score += RATING_WEIGHT * rating_similarity;
+            reasons.push(String::from("rated alike by the same people"));
+        }
+    }
+
+    (score, reasons)
+}
+
+///The cosine similarity of two items' ratings from the reviewers who rated both, after taking away the middle of
+///the rating scale so that two low ratings count as agreeing rather than as two small positive numbers.
+///It goes from -1.0 (always rated oppositely) to 1.0 (always rated alike).
+fn co_rating_similarity(first: &HashMap<String, f64>, second: &HashMap<String, f64>) -> f64 {
+    let middle = (MIN_RATING + MAX_RATING) as f64 / 2.0;
+    let mut product = 0.0;
+    let mut first_length = 0.0;
+    let mut second_length = 0.0;
+    let mut shared = 0;
+
+    for (reviewer, first_rating) in first {
+        if let Some(second_rating) = second.get(reviewer) {
+            let (first_rating, second_rating) = (first_rating - middle, second_rating - middle);
+            product += first_rating * second_rating;
+            first_length += first_rating * first_rating;
+            second_length += second_rating * second_rating;
+            shared += 1;
+        }
+    }
+
+    if first_length == 0.0 || second_length == 0.0 {
+        return 0.0;
+    }
+
+    let cosine = product / (first_length.sqrt() * second_length.sqrt());
+    cosine * shared as f64 / (shared as f64 + SHARED_REVIEWER_SHRINK)
+}
+
+//the values in both lists, ignoring case, in the order of the second list
+fn shared(first: &[String], second: &[String]) -> Vec<String> {
+    second
+        .iter()
+        .filter(|value| first.iter().any(|other| other.eq_ignore_ascii_case(value)))
+        .cloned()
+        .collect()
+}
+
+//the Jaccard index: how much of the two lists together is shared, from 0.0 to 1.0
+fn overlap(first: &[String], second: &[String], shared: usize) -> f64 {
+    shared as f64 / (first.len() + second.len() - shared) as f64
+}
+
+fn same_reviewer(first: &str, second: &str) -> bool {
+    normalize(first) == normalize(second)
+}
+
+//placeholders aren't on the shelf, so there is nothing to suggest reading or watching
+fn is_recommendable(media: &Media) -> bool {
+    !matches!(media, Media::Placeholder { .. })
+}
+
+fn sort_and_truncate(recommendations: &mut Vec<Recommendation>, count: usize) {
+    recommendations.sort_by(|first, second| {
+        second
+            .score
+            .total_cmp(&first.score)
+            .then(first.id.cmp(&second.id))
+    });
+    recommendations.truncate(count);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::media::Metadata;
+
+    fn book(title: &str, author: &str, genres: &[&str]) -> Media {
+        Media::Book {
+            title: title.to_string(),
+            author: author.to_string(),
+            metadata: Metadata {
+                genres: genres.iter().map(|genre| genre.to_string()).collect(),
+                ..Metadata::default()
+            },
+        }
+    }
+
+    fn ids(recommendations: &[Recommendation]) -> Vec<u32> {
+        recommendations
+            .iter()
+            .map(|recommendation| recommendation.id.0)
+            .collect()
+    }
+
+    #[test]
+    fn checks_reviews_and_keeps_one_per_reviewer() {
+        let mut catalog = Catalog::new();
+        let id = catalog.add_media(book("Dune", "Herbert", &[]));
+
+        assert_eq!(
+            catalog.review(MediaId(9), "ann", 5, None),
+            Err(ReviewError::ItemNotFound(MediaId(9)))
+        );
+        assert_eq!(
+            catalog.review(id, "ann", 6, None),
+            Err(ReviewError::InvalidRating(6))
+        );
+        assert_eq!(
+            catalog.review(id, "ann", 0, None),
+            Err(ReviewError::InvalidRating(0))
+        );
+        assert_eq!(
+            catalog.review(id, "  ", 3, None),
+            Err(ReviewError::NoReviewer)
+        );
+        let essay = "word ".repeat(101);
+        assert_eq!(
+            catalog.review(id, "ann", 3, Some(&essay)),
+            Err(ReviewError::TooLong(504))
+        );
+        assert_eq!(catalog.average_rating(id), None);
+
+        catalog.review(id, "Ann", 2, Some("slow")).unwrap();
+        catalog.review(id, "Bo", 5, Some("  ")).unwrap();
+        //the same reviewer, written differently, replaces their review
+        catalog.review(id, " ann ", 4, Some("grew on me")).unwrap();
+
+        let reviews = catalog.reviews(id);
+        assert_eq!(reviews.len(), 2);
+        assert_eq!(
+            (reviews[0].rating, reviews[0].text.as_deref()),
+            (4, Some("grew on me"))
+        );
+        assert_eq!(reviews[1].text, None);
+        assert_eq!(catalog.average_rating(id), Some(4.5));
+    }
+
+    #[test]
+    fn recommends_items_with_shared_details() {
+        let mut catalog = Catalog::new();
+        let dune = catalog.add_media(book("Dune", "Frank Herbert", &["Sci-Fi", "Classic"]));
+        catalog.add_media(book("Children of Dune", "frank herbert", &["Sci-Fi"]));
+        catalog.add_media(book("Foundation", "Asimov", &["sci-fi", "Classic"]));
+        catalog.add_media(book("Emma", "Austen", &["Romance"]));
+        catalog.add_media(Media::Placeholder {
+            title: "Sci-Fi to find".to_string(),
+            metadata: Metadata {
+                genres: vec!["Sci-Fi".to_string()],
+                ..Metadata::default()
+            },
+        });
+
+        let recommendations = catalog.recommend_similar(dune, 10);
+        //the same author and a genre beats two genres, and placeholders and unrelated items aren't suggested
+        assert_eq!(ids(&recommendations), vec![2, 3]);
+        assert_eq!(
+            recommendations[0].reasons,
+            vec!["also Sci-Fi", "also by frank herbert"]
+        );
+        assert_eq!(recommendations[1].reasons, vec!["also sci-fi, Classic"]);
+        assert_eq!(ids(&catalog.recommend_similar(dune, 1)), vec![2]);
+        assert!(catalog.recommend_similar(MediaId(99), 5).is_empty());
+    }
+
+    #[test]
+    fn recommends_from_how_people_rated_things() {
+        let mut catalog = Catalog::new();
+        let liked = catalog.add_media(book("Liked", "One", &[]));
+        let alike = catalog.add_media(book("Alike", "Two", &[]));
+        let opposite = catalog.add_media(book("Opposite", "Three", &[]));
+        catalog.add_media(book("Unrated", "Four", &[]));
+
+        for reviewer in ["ann", "bo", "cy"] {
+            catalog.review(liked, reviewer, 5, None).unwrap();
+            catalog.review(alike, reviewer, 5, None).unwrap();
+            catalog.review(opposite, reviewer, 1, None).unwrap();
+        }
+        let similar = catalog.recommend_similar(liked, 10);
+        assert_eq!(ids(&similar), vec![2]);
+        assert_eq!(similar[0].reasons, vec!["rated alike by the same people"]);
+        //three shared reviewers who all agree are shrunk from 1.0 towards 0
+        assert!((similar[0].score - RATING_WEIGHT * 3.0 / 5.0).abs() < 1e-9);
+
+        //dee only rated the liked book, so gets the one the others rated alike, and nothing already rated
+        catalog.review(liked, "Dee", 5, None).unwrap();
+        let for_dee = catalog.recommend_for("DEE", 10);
+        assert_eq!(ids(&for_dee), vec![2]);
+        assert_eq!(for_dee[0].reasons, vec!["you liked Liked"]);
+        assert!(catalog.recommend_for("ann", 10).is_empty());
+        assert!(catalog.recommend_for("nobody", 10).is_empty());
+    }
+}
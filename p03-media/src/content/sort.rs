@@ -0,0 +1,484 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::catalog::{Catalog, MediaId};
+use super::media::{Media, MediaKind};
+use super::query::Filter;
+
+//words at the start of a title that are skipped when sorting, so "The Hobbit" sorts under H
+const LEADING_ARTICLES: [&str; 3] = ["the", "a", "an"];
+
+///Something catalog items can be put in order by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    Title,
+    Creator,
+    Year,
+    Kind,
+}
+
+impl SortKey {
+    ///Finds the key with the given name, like "title", ignoring case.
+    pub fn from_name(name: &str) -> Option<SortKey> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "title" => Some(SortKey::Title),
+            "creator" => Some(SortKey::Creator),
+            "year" => Some(SortKey::Year),
+            "kind" => Some(SortKey::Kind),
+            _ => None,
+        }
+    }
+}
+
+///An order to list catalog items in, made of sort keys that each break ties left by the ones before.
+///Items that are still tied are kept in the order they were added, so the order is always the same.
+///For example, newest first and then by title: `Sort::by(SortKey::Year).descending().then(SortKey::Title)`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sort {
+    //each key and whether it is descending
+    keys: Vec<(SortKey, bool)>,
+}
+
+impl Sort {
+    pub fn by(key: SortKey) -> Sort {
+        Sort {
+            keys: vec![(key, false)],
+        }
+    }
+
+    ///Adds a key that is used when the keys before it are tied.
+    pub fn then(mut self, key: SortKey) -> Sort {
+        self.keys.push((key, false));
+        self
+    }
+
+    ///Reverses the key added last.  Items missing the value, like a Podcast's creator, still come last.
+    pub fn descending(mut self) -> Sort {
+        if let Some((_, descending)) = self.keys.last_mut() {
+            *descending = true;
+        }
+        self
+    }
+
+    ///Parses a list of key names separated by commas, each one descending if it starts with '-', like "-year,title".
+    pub fn parse(text: &str) -> Option<Sort> {
+        let mut keys = vec![];
+        for part in text.split(',') {
+            let part = part.trim();
+            let (name, descending) = match part.strip_prefix('-') {
+                Some(name) => (name, true),
+                None => (part, false),
+            };
+            keys.push((SortKey::from_name(name)?, descending));
+        }
+        Some(Sort { keys })
+    }
+
+    ///Compares two items' sort values, falling back to their IDs so no two items are ever equal.
+    fn compare(
+        &self,
+        (first, first_id): (&[SortValue], MediaId),
+        (second, second_id): (&[SortValue], MediaId),
+    ) -> Ordering {
+        self.keys
+            .iter()
+            .zip(first.iter().zip(second))
+            .map(|((_, descending), (first, second))| first.compare(second, *descending))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| first_id.cmp(&second_id))
+    }
+}
+
+///The value of one sort key for an item, worked out once so it isn't recomputed for every comparison.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+enum SortValue {
+    //the collation key first, then the original text to break ties between texts that only differ in case or accents
+    Text(Option<(String, String)>),
+    Number(Option<u32>),
+}
+
+impl SortValue {
+    fn of(key: SortKey, media: &Media) -> SortValue {
+        match key {
+            SortKey::Title => SortValue::Text(Some((
+                collation_key(strip_article(media.title())),
+                media.title().to_string(),
+            ))),
+            SortKey::Creator => SortValue::Text(
+                media
+                    .creator()
+                    .map(|creator| (collation_key(creator), creator.to_string())),
+            ),
+            SortKey::Year => SortValue::Number(media.metadata().year.map(u32::from)),
+            SortKey::Kind => SortValue::Number(Some(kind_order(media.kind()))),
+        }
+    }
+
+    //missing values always go last, whichever direction the key is sorted in
+    fn compare(&self, other: &SortValue, descending: bool) -> Ordering {
+        let ordering = match (self, other) {
+            (SortValue::Text(Some(_)), SortValue::Text(None))
+            | (SortValue::Number(Some(_)), SortValue::Number(None)) => return Ordering::Less,
+            (SortValue::Text(None), SortValue::Text(Some(_)))
+            | (SortValue::Number(None), SortValue::Number(Some(_))) => return Ordering::Greater,
+            (SortValue::Text(first), SortValue::Text(second)) => first.cmp(second),
+            (SortValue::Number(first), SortValue::Number(second)) => first.cmp(second),
+            //values of the same key are always the same variant
+            _ => Ordering::Equal,
+        };
+
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+///Where the previous page ended, to be handed back to get the next page.  Because it remembers the sort values of the
+///last item rather than a position, items added or removed between pages don't cause items to be skipped or repeated.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cursor {
+    sort: Sort,
+    after: Vec<SortValue>,
+    after_id: MediaId,
+}
+
+impl fmt::Display for Cursor {
+    //written as hex so it can be passed around in URLs and command lines without quoting
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let json = serde_json::to_string(self).expect("a cursor should always serialize to JSON");
+        for byte in json.bytes() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl Cursor {
+    ///Reads a cursor written with `to_string`, or returns None if the text isn't one.
+    pub fn parse(text: &str) -> Option<Cursor> {
+        let text = text.trim();
+        if !text.len().is_multiple_of(2) {
+            return None;
+        }
+
+        let bytes: Option<Vec<u8>> = (0..text.len())
+            .step_by(2)
+            .map(|start| u8::from_str_radix(text.get(start..start + 2)?, 16).ok())
+            .collect();
+        serde_json::from_slice(&bytes?).ok()
+    }
+}
+
+///One page of items, and the cursor for the page after it if there is one.
+#[derive(Debug)]
+pub struct Page<'a> {
+    pub items: Vec<(MediaId, &'a Media)>,
+    pub next: Option<Cursor>,
+}
+
+///Why a page couldn't be fetched.
+#[derive(Debug, PartialEq)]
+pub enum PageError {
+    //the cursor came from a page sorted a different way, so its position means nothing in this order
+    SortMismatch,
+}
+
+impl fmt::Display for PageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PageError::SortMismatch => write!(f, "the cursor is for a differently sorted list"),
+        }
+    }
+}
+
+impl Catalog {
+    ///Returns every item in the given order.
+    pub fn sorted(&self, sort: &Sort) -> Vec<(MediaId, &Media)> {
+        sorted_values(self.iter(), sort)
+            .into_iter()
+            .map(|(id, media, _)| (id, media))
+            .collect()
+    }
+
+    ///Returns up to `limit` items in the given order that match the filter, if one is given, starting after the cursor,
+    ///or from the start if there isn't one.  The page's `next` cursor fetches the page after it, and is None on the last page.
+    pub fn page(
+        &self,
+        filter: Option<&Filter>,
+        sort: &Sort,
+        cursor: Option<&Cursor>,
+        limit: usize,
+    ) -> Result<Page<'_>, PageError> {
+        let matching = self
+            .iter()
+            .filter(|(_, media)| filter.map(|filter| filter.matches(media)).unwrap_or(true));
+        let sorted = sorted_values(matching, sort);
+
+        let start = match cursor {
+            Some(cursor) if cursor.sort != *sort => return Err(PageError::SortMismatch),
+            Some(cursor) => sorted.partition_point(|(id, _, values)| {
+                sort.compare((values, *id), (&cursor.after, cursor.after_id))
+                    .is_le()
+            }),
+            None => 0,
+        };
+
+        //one more item than asked for is taken just to find out whether there is another page
+        let items: Vec<(MediaId, &Media, Vec<SortValue>)> = sorted
+            .into_iter()
+            .skip(start)
+            .take(limit.saturating_add(1))
+            .collect();
+        let has_more = items.len() > limit;
+
+        let items: Vec<(MediaId, &Media, Vec<SortValue>)> = items.into_iter().take(limit).collect();
+        let next = match items.last() {
+            Some((id, _, values)) if has_more => Some(Cursor {
+                sort: sort.clone(),
+                after: values.clone(),
+                after_id: *id,
+            }),
+            _ => None,
+        };
+
+        Ok(Page {
+            items: items
+                .into_iter()
+                .map(|(id, media, _)| (id, media))
+                .collect(),
+            next,
+        })
+    }
+}
+
+fn sorted_values<'a>(
+    items: impl Iterator<Item = (MediaId, &'a Media)>,
+    sort: &Sort,
+) -> Vec<(MediaId, &'a Media, Vec<SortValue>)> {
+    let mut items: Vec<(MediaId, &Media, Vec<SortValue>)> = items
+        .map(|(id, media)| {
+            (
+                id,
+                media,
+                sort.keys
+                    .iter()
+                    .map(|(key, _)| SortValue::of(*key, media))
+                    .collect(),
+            )
+        })
+        .collect();
+
+    items.sort_by(|(first_id, _, first), (second_id, _, second)| {
+        sort.compare((first, *first_id), (second, *second_id))
+    });
+    items
+}
+
+///Removes a leading "The", "A" or "An" from a title, unless that is the whole title.
+pub fn strip_article(title: &str) -> &str {
+    let trimmed = title.trim_start();
+
+    for article in LEADING_ARTICLES {
+        if let Some(prefix) = trimmed.get(..article.len()) {
+            let rest = &trimmed[article.len()..];
+            if prefix.eq_ignore_ascii_case(article)
+                && rest.starts_with(char::is_whitespace)
+                && !rest.trim().is_empty()
+            {
+                return rest.trim_start();
+            }
+        }
+    }
+
+    trimmed
+}
+
+///Turns text into a key that sorts the way people expect rather than by character code: case and accents are ignored,
+///so "émile" sorts next to "Emile" instead of after "Z", and punctuation is skipped.
+pub fn collation_key(text: &str) -> String {
+    let mut key = String::new();
+
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            match fold_accent(c) {
+                Some(letters) => key.push_str(letters),
+                None => key.push(c),
+            }
+        } else if c.is_whitespace() && !key.is_empty() && !key.ends_with(' ') {
+            key.push(' ');
+        }
+    }
+
+    key.trim_end().to_string()
+}
+
+//the letters with accents most often found in Latin script titles and names, as the letters they sort with
+fn fold_accent(c: char) -> Option<&'static str> {
+    let letters = match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'č' => "c",
+        'ď' | 'đ' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ę' | 'ě' => "e",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' => "i",
+        'ł' => "l",
+        'ñ' | 'ń' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+        'œ' => "oe",
+        'ř' => "r",
+        'ś' | 'š' => "s",
+        'ß' => "ss",
+        'ť' => "t",
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => "u",
+        'ý' | 'ÿ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    };
+    Some(letters)
+}
+
+//kinds sort in the order they are listed in MediaKind, with kinds from outside this crate after the built-in ones
+fn kind_order(kind: MediaKind) -> u32 {
+    match kind {
+        MediaKind::Book => 0,
+        MediaKind::Movie => 1,
+        MediaKind::Audiobook => 2,
+        MediaKind::Podcast => 3,
+        MediaKind::Placeholder => 4,
+        MediaKind::Custom(_) => 5,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::media::Metadata;
+
+    fn book(title: &str, author: &str, year: Option<u16>) -> Media {
+        Media::Book {
+            title: title.to_string(),
+            author: author.to_string(),
+            metadata: Metadata {
+                year,
+                ..Metadata::default()
+            },
+        }
+    }
+
+    fn titles(items: &[(MediaId, &Media)]) -> Vec<String> {
+        items
+            .iter()
+            .map(|(_, media)| media.title().to_string())
+            .collect()
+    }
+
+    fn catalog() -> Catalog {
+        let mut catalog = Catalog::new();
+        for media in [
+            book("The Hobbit", "Tolkien", Some(1937)),
+            book("Emma", "Austen", Some(1815)),
+            book("Émile", "Rousseau", Some(1762)),
+            book("A Zoo", "Zed", None),
+            Media::Podcast {
+                show: "Anthems".to_string(),
+                episodes: vec![],
+                metadata: Metadata::default(),
+            },
+            book("emma", "Austen", Some(1815)),
+        ] {
+            catalog.add_media(media);
+        }
+        catalog
+    }
+
+    #[test]
+    fn titles_ignore_articles_case_punctuation_and_accents() {
+        assert_eq!(strip_article("The Hobbit"), "Hobbit");
+        assert_eq!(strip_article("  an Apple"), "Apple");
+        assert_eq!(strip_article("The"), "The");
+        assert_eq!(strip_article("Theory"), "Theory");
+        assert_eq!(
+            collation_key("Émile, or  On Education!"),
+            "emile or on education"
+        );
+        assert_eq!(collation_key("Straße Æon"), "strasse aeon");
+
+        let catalog = catalog();
+        assert_eq!(
+            titles(&catalog.sorted(&Sort::by(SortKey::Title))),
+            vec!["Anthems", "Émile", "Emma", "emma", "The Hobbit", "A Zoo"]
+        );
+    }
+
+    #[test]
+    fn later_keys_break_ties_and_missing_values_go_last() {
+        let catalog = catalog();
+        let newest_first = Sort::by(SortKey::Year).descending().then(SortKey::Title);
+        assert_eq!(
+            titles(&catalog.sorted(&newest_first)),
+            vec!["The Hobbit", "Emma", "emma", "Émile", "Anthems", "A Zoo"]
+        );
+        assert_eq!(
+            titles(&catalog.sorted(&Sort::by(SortKey::Creator).descending())),
+            vec!["A Zoo", "The Hobbit", "Émile", "Emma", "emma", "Anthems"]
+        );
+        assert_eq!(
+            titles(&catalog.sorted(&Sort::by(SortKey::Kind).descending().then(SortKey::Title)))[0],
+            "Anthems"
+        );
+        assert_eq!(Sort::parse("-year, title"), Some(newest_first));
+        assert_eq!(Sort::parse("year,colour"), None);
+        assert_eq!(SortKey::from_name(" KIND "), Some(SortKey::Kind));
+    }
+
+    #[test]
+    fn pages_carry_on_from_the_cursor_when_items_change() {
+        let mut catalog = catalog();
+        let sort = Sort::by(SortKey::Title);
+
+        let first = catalog.page(None, &sort, None, 2).unwrap();
+        assert_eq!(titles(&first.items), vec!["Anthems", "Émile"]);
+        let cursor = first.next.unwrap();
+        //the cursor survives being written out and read back in
+        let cursor = Cursor::parse(&cursor.to_string()).unwrap();
+
+        //items before the cursor are removed and added, and one is added after it, without skipping or repeating any
+        catalog.remove(MediaId(5)).unwrap();
+        catalog.add_media(book("Dune", "Herbert", None));
+        catalog.add_media(book("Fables", "Aesop", None));
+        let second = catalog.page(None, &sort, Some(&cursor), 2).unwrap();
+        assert_eq!(titles(&second.items), vec!["Emma", "emma"]);
+
+        let rest = catalog.page(None, &sort, second.next.as_ref(), 10).unwrap();
+        assert_eq!(titles(&rest.items), vec!["Fables", "The Hobbit", "A Zoo"]);
+        assert!(rest.next.is_none());
+
+        let filter = Filter::title_contains("emma");
+        let filtered = catalog.page(Some(&filter), &sort, None, 1).unwrap();
+        assert_eq!(titles(&filtered.items), vec!["Emma"]);
+        assert!(filtered.next.is_some());
+    }
+
+    #[test]
+    fn refuses_cursors_it_did_not_make() {
+        let catalog = catalog();
+        let cursor = catalog
+            .page(None, &Sort::by(SortKey::Title), None, 1)
+            .unwrap()
+            .next
+            .unwrap();
+        let error = catalog
+            .page(None, &Sort::by(SortKey::Year), Some(&cursor), 1)
+            .unwrap_err();
+        assert_eq!(error, PageError::SortMismatch);
+
+        assert!(Cursor::parse("abc").is_none());
+        assert!(Cursor::parse("zz").is_none());
+        assert!(Cursor::parse("7b7d").is_none());
+    }
+}
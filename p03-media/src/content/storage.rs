@@ -0,0 +1,1097 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::catalog::{Catalog, MediaId};
+use super::collections::{Collection, CollectionEntry, CollectionError, CollectionId, Collections};
+use super::history::{ChangeSet, History};
+use super::item::{CustomMedia, KindRegistry};
+use super::media::{Episode, Media, MediaKind, Metadata};
+use super::reviews::{ReviewError, Reviews};
+
+//the columns that come after the kind specific ones in every CSV section except episodes
+const METADATA_COLUMNS: [&str; 8] = [
+    "year",
+    "genres",
+    "duration_minutes",
+    "isbn",
+    "isan",
+    "language",
+    "tags",
+    "publisher",
+];
+
+//podcast episodes get their own CSV section, one row per episode, pointing back at their show by ID
+const EPISODE_KIND: &str = "episode";
+const EPISODE_COLUMNS: [&str; 5] = ["kind", "podcast_id", "number", "title", "duration_minutes"];
+
+//collections get their own CSV section too, with their entries written like "item 3;collection 2"
+const COLLECTION_KIND: &str = "collection";
+const COLLECTION_COLUMNS: [&str; 4] = ["kind", "id", "name", "entries"];
+
+//and so do reviews, pointing at the item they are for by ID like episodes do
+const REVIEW_KIND: &str = "review";
+const REVIEW_COLUMNS: [&str; 5] = ["kind", "item_id", "reviewer", "rating", "text"];
+
+//kinds from outside this crate have one section each like the built-in kinds, with their fields written as JSON
+const CUSTOM_COLUMNS: [&str; 3] = ["kind", "id", "data"];
+
+//the changes that can be undone and redone, one row per ChangeSet with its changes written as JSON, so undo still
+//works after a catalog is saved as CSV and loaded again.  The state is "done" or "undone".
+const HISTORY_KIND: &str = "history";
+const HISTORY_COLUMNS: [&str; 4] = ["kind", "state", "description", "changes"];
+const HISTORY_DONE: &str = "done";
+const HISTORY_UNDONE: &str = "undone";
+
+//the catalog's own settings get a section with a single row, so the next ID survives being saved as CSV
+const CATALOG_KIND: &str = "catalog";
+const CATALOG_COLUMNS: [&str; 2] = ["kind", "next_id"];
+
+//lists like genres and tags are written into a single CSV cell separated by this, with a backslash in front of
+//any separator (or backslash) inside a value
+const LIST_SEPARATOR: char = ';';
+const LIST_ESCAPE: char = '\\';
+
+///Why a catalog file couldn't be loaded.
+#[derive(Debug)]
+pub enum ImportError {
+    Io(io::Error),
+    //the line is where the problem was found in the file, starting from 1
+    Malformed {
+        line: usize,
+        message: String,
+    },
+    //JSON items don't keep track of which line they were on, so the line is only known for CSV
+    DuplicateId {
+        id: MediaId,
+        line: Option<usize>,
+    },
+    //an item's ID, or the next ID, is so large that no ID would be left for new items
+    IdTooLarge(MediaId),
+    //an item of a registered kind whose saved fields that kind can't read, with the line it was on for CSV
+    UnreadableItem {
+        line: Option<usize>,
+        message: String,
+    },
+    //a saved collection points at something that doesn't exist or contains itself
+    Collection(CollectionError),
+    Review(ReviewError),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportError::Io(error) => write!(f, "{}", error),
+            ImportError::Malformed { line, message } => write!(f, "line {}: {}", line, message),
+            ImportError::DuplicateId {
+                id,
+                line: Some(line),
+            } => {
+                write!(f, "line {}: ID {} is used more than once", line, id)
+            }
+            ImportError::DuplicateId { id, line: None } => {
+                write!(f, "ID {} is used more than once", id)
+            }
+            ImportError::IdTooLarge(id) => {
+                write!(f, "ID {} is too large to leave an ID for new items", id)
+            }
+            ImportError::UnreadableItem {
+                line: Some(line),
+                message,
+            } => write!(f, "line {}: {}", line, message),
+            ImportError::UnreadableItem {
+                line: None,
+                message,
+            } => write!(f, "{}", message),
+            ImportError::Collection(error) => write!(f, "{}", error),
+            ImportError::Review(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<io::Error> for ImportError {
+    fn from(error: io::Error) -> Self {
+        ImportError::Io(error)
+    }
+}
+
+impl From<CollectionError> for ImportError {
+    fn from(error: CollectionError) -> Self {
+        ImportError::Collection(error)
+    }
+}
+
+impl From<ReviewError> for ImportError {
+    fn from(error: ReviewError) -> Self {
+        ImportError::Review(error)
+    }
+}
+
+impl From<serde_json::Error> for ImportError {
+    fn from(error: serde_json::Error) -> Self {
+        ImportError::Malformed {
+            line: error.line(),
+            message: error.to_string(),
+        }
+    }
+}
+
+pub(super) fn malformed(line: usize, message: String) -> ImportError {
+    ImportError::Malformed { line, message }
+}
+
+//the JSON file is an object holding the items, plus the next ID so IDs of removed items aren't handed out again after loading
+#[derive(Serialize)]
+struct CatalogFileRef<'a> {
+    next_id: u32,
+    items: Vec<EntryRef<'a>>,
+    #[serde(skip_serializing_if = "Collections::is_empty")]
+    collections: &'a Collections,
+    #[serde(skip_serializing_if = "Reviews::is_empty")]
+    reviews: &'a Reviews,
+    #[serde(skip_serializing_if = "History::is_empty")]
+    history: &'a History,
+}
+
+#[derive(Serialize)]
+struct EntryRef<'a> {
+    id: MediaId,
+    #[serde(flatten)]
+    media: &'a Media,
+}
+
+#[derive(Deserialize)]
+struct CatalogFile {
+    #[serde(default)]
+    next_id: u32,
+    items: Vec<Entry>,
+    #[serde(default)]
+    collections: Collections,
+    #[serde(default)]
+    reviews: Reviews,
+    #[serde(default)]
+    history: History,
+}
+
+#[derive(Deserialize)]
+struct Entry {
+    //items written by hand may leave the ID out, they are given one when loaded
+    #[serde(default)]
+    id: Option<MediaId>,
+    #[serde(flatten)]
+    media: Media,
+}
+
+impl Catalog {
+    ///Writes the catalog to a file, as CSV if the path ends in .csv and as JSON otherwise.
+    pub fn save(&self, path: &str) -> Result<(), io::Error> {
+        let text = if is_csv(path) {
+            self.to_csv()
+        } else {
+            self.to_json()
+        };
+        fs::write(path, text)
+    }
+
+    ///Reads a catalog that was saved with `save`, keeping the IDs from the file.  Items of kinds from outside this
+    ///crate are kept as they were saved, see `load_with_kinds`.
+    pub fn load(path: &str) -> Result<Catalog, ImportError> {
+        Catalog::load_with_kinds(path, &KindRegistry::new())
+    }
+
+    ///Like `load`, but items of the kinds in the registry are read as those kinds.
+    pub fn load_with_kinds(path: &str, kinds: &KindRegistry) -> Result<Catalog, ImportError> {
+        let text = fs::read_to_string(path)?;
+
+        if is_csv(path) {
+            Catalog::from_csv_with_kinds(&text, kinds)
+        } else {
+            Catalog::from_json_with_kinds(&text, kinds)
+        }
+    }
+
+    ///Adds every item in a catalog file to this catalog as new items with new IDs, and returns those IDs.
+    ///Collections and reviews in the file aren't imported, since they are organized around the other catalog's items.
+    ///Nothing is added if any part of the file is malformed, and the whole import is undone by a single `undo`.
+    pub fn import_file(&mut self, path: &str) -> Result<Vec<MediaId>, ImportError> {
+        self.import_file_with_kinds(path, &KindRegistry::new())
+    }
+
+    ///Like `import_file`, but items of the kinds in the registry are read as those kinds.
+    pub fn import_file_with_kinds(
+        &mut self,
+        path: &str,
+        kinds: &KindRegistry,
+    ) -> Result<Vec<MediaId>, ImportError> {
+        let imported = Catalog::load_with_kinds(path, kinds)?;
+
+        Ok(self.batch(&format!("import {}", path), |catalog| {
+            imported
+                .into_media()
+                .map(|media| catalog.add_media(media))
+                .collect()
+        }))
+    }
+
+    pub fn to_json(&self) -> String {
+        let file = CatalogFileRef {
+            next_id: self.next_id(),
+            items: self
+                .iter()
+                .map(|(id, media)| EntryRef { id, media })
+                .collect(),
+            collections: self.collections(),
+            reviews: self.all_reviews(),
+            history: self.history_log(),
+        };
+
+        //serializing plain structs and enums to a String can't fail
+        serde_json::to_string_pretty(&file).expect("catalog should always serialize to JSON")
+    }
+
+    pub fn from_json(text: &str) -> Result<Catalog, ImportError> {
+        Catalog::from_json_with_kinds(text, &KindRegistry::new())
+    }
+
+    pub fn from_json_with_kinds(text: &str, kinds: &KindRegistry) -> Result<Catalog, ImportError> {
+        let file: CatalogFile = serde_json::from_str(text)?;
+
+        let entries = file
+            .items
+            .into_iter()
+            .map(|entry| (None, entry.id, entry.media))
+            .collect();
+
+        let mut catalog = collect_entries(entries, file.next_id, kinds)?;
+        catalog.restore_collections(file.collections)?;
+
+        //reviews are added back one at a time so that each one is checked
+        for (id, reviews) in file.reviews.iter() {
+            for review in reviews {
+                catalog.review(id, &review.reviewer, review.rating, review.text.as_deref())?;
+            }
+        }
+
+        //the history replaces the changes recorded while the items were being added back
+        *catalog.history_log_mut() = file.history;
+        read_history_kinds(&mut catalog, kinds)?;
+        Ok(catalog)
+    }
+
+    ///Writes a section with the catalog's next ID, then one section per kind of media, each starting with its own
+    ///header row whose first column is "kind", followed by sections of podcast episodes, collections, reviews and history.
+    ///Sections are separated by blank lines.
+    pub fn to_csv(&self) -> String {
+        let mut sections = vec![(
+            CATALOG_COLUMNS
+                .iter()
+                .map(|column| column.to_string())
+                .collect(),
+            vec![vec![CATALOG_KIND.to_string(), self.next_id().to_string()]],
+        )];
+
+        //kinds from outside this crate get a section each after the built-in ones, in the order they first appear
+        let mut kinds = MediaKind::BUILT_IN.to_vec();
+        for (_, media) in self.iter() {
+            if !kinds.contains(&media.kind()) {
+                kinds.push(media.kind());
+            }
+        }
+
+        for kind in kinds {
+            let rows: Vec<Vec<String>> = self
+                .iter()
+                .filter(|(_, media)| media.kind() == kind)
+                .map(|(id, media)| media_row(id, media))
+                .collect();
+
+            if !rows.is_empty() {
+                sections.push((csv_header(kind), rows));
+            }
+        }
+
+        let episode_rows: Vec<Vec<String>> = self
+            .iter()
+            .flat_map(|(id, media)| match media {
+                Media::Podcast { episodes, .. } => episodes
+                    .iter()
+                    .map(|episode| {
+                        vec![
+                            EPISODE_KIND.to_string(),
+                            id.to_string(),
+                            episode.number.to_string(),
+                            episode.title.clone(),
+                            optional_to_string(&episode.duration_minutes),
+                        ]
+                    })
+                    .collect(),
+                _ => vec![],
+            })
+            .collect();
+
+        if !episode_rows.is_empty() {
+            sections.push((
+                EPISODE_COLUMNS
+                    .iter()
+                    .map(|column| column.to_string())
+                    .collect(),
+                episode_rows,
+            ));
+        }
+
+        let collection_rows: Vec<Vec<String>> = self
+            .collections()
+            .iter()
+            .map(|(id, collection)| {
+                let entries: Vec<String> = collection
+                    .entries
+                    .iter()
+                    .map(|entry| entry.to_string())
+                    .collect();
+                vec![
+                    COLLECTION_KIND.to_string(),
+                    id.to_string(),
+                    collection.name.clone(),
+                    entries.join(&LIST_SEPARATOR.to_string()),
+                ]
+            })
+            .collect();
+
+        if !collection_rows.is_empty() {
+            sections.push((
+                COLLECTION_COLUMNS
+                    .iter()
+                    .map(|column| column.to_string())
+                    .collect(),
+                collection_rows,
+            ));
+        }
+
+        let review_rows: Vec<Vec<String>> = self
+            .all_reviews()
+            .iter()
+            .flat_map(|(id, reviews)| {
+                reviews.iter().map(move |review| {
+                    vec![
+                        REVIEW_KIND.to_string(),
+                        id.to_string(),
+                        review.reviewer.clone(),
+                        review.rating.to_string(),
+                        review.text.clone().unwrap_or_default(),
+                    ]
+                })
+            })
+            .collect();
+
+        if !review_rows.is_empty() {
+            sections.push((
+                REVIEW_COLUMNS
+                    .iter()
+                    .map(|column| column.to_string())
+                    .collect(),
+                review_rows,
+            ));
+        }
+
+        let history_rows: Vec<Vec<String>> = [
+            (HISTORY_DONE, self.history()),
+            (HISTORY_UNDONE, self.undone()),
+        ]
+        .into_iter()
+        .flat_map(|(state, change_sets)| {
+            change_sets.iter().map(move |change_set| {
+                vec![
+                    HISTORY_KIND.to_string(),
+                    state.to_string(),
+                    change_set.description.clone(),
+                    serde_json::to_string(&change_set.changes)
+                        .expect("changes should always serialize to JSON"),
+                ]
+            })
+        })
+        .collect();
+
+        if !history_rows.is_empty() {
+            sections.push((
+                HISTORY_COLUMNS
+                    .iter()
+                    .map(|column| column.to_string())
+                    .collect(),
+                history_rows,
+            ));
+        }
+
+        sections
+            .iter()
+            .map(|(header, rows)| {
+                let mut section = csv_row(header);
+                for row in rows {
+                    section += &csv_row(row);
+                }
+                section
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    ///Reads CSV written by `to_csv`.  The sections can come in any order, the columns within a section can be
+    ///in any order as long as the header row names them, and the ID column can be left empty for new items.
+    pub fn from_csv(text: &str) -> Result<Catalog, ImportError> {
+        Catalog::from_csv_with_kinds(text, &KindRegistry::new())
+    }
+
+    pub fn from_csv_with_kinds(text: &str, kinds: &KindRegistry) -> Result<Catalog, ImportError> {
+        let mut header: Option<Vec<String>> = None;
+        let mut entries = vec![];
+        let mut episodes = vec![];
+        let mut collections = Collections::default();
+        let mut reviews = vec![];
+        let mut next_id = 0;
+        let mut done = vec![];
+        let mut undone = vec![];
+
+        for (line, record) in read_csv_records(text)? {
+            //blank lines separate the sections
+            if record.iter().all(|field| field.trim().is_empty()) {
+                continue;
+            }
+
+            let kind_name = record[0].trim();
+            if kind_name.eq_ignore_ascii_case("kind") {
+                header = Some(
+                    record
+                        .iter()
+                        .map(|column| column.trim().to_lowercase())
+                        .collect(),
+                );
+                continue;
+            }
+
+            let header = header
+                .as_ref()
+                .ok_or_else(|| malformed(line, String::from("row comes before any header row")))?;
+            let row = Row {
+                line,
+                header,
+                record: &record,
+            };
+
+            if kind_name.eq_ignore_ascii_case(CATALOG_KIND) {
+                next_id = row.number("next_id")?.unwrap_or(next_id);
+                continue;
+            }
+
+            if kind_name.eq_ignore_ascii_case(HISTORY_KIND) {
+                let changes = serde_json::from_str(&row.required("changes")?).map_err(|error| {
+                    malformed(line, format!("'changes' is not valid JSON: {}", error))
+                })?;
+                let change_set = ChangeSet {
+                    description: row.required("description")?,
+                    changes,
+                };
+                match row.required("state")?.trim() {
+                    HISTORY_DONE => done.push(change_set),
+                    HISTORY_UNDONE => undone.push(change_set),
+                    other => {
+                        return Err(malformed(
+                            line,
+                            format!("history state '{}' is not 'done' or 'undone'", other),
+                        ))
+                    }
+                }
+                continue;
+            }
+
+            if kind_name.eq_ignore_ascii_case(EPISODE_KIND) {
+                let podcast_id = MediaId(
+                    row.number("podcast_id")?
+                        .ok_or_else(|| row.missing("podcast_id"))?,
+                );
+                let episode = Episode {
+                    number: row.number("number")?.ok_or_else(|| row.missing("number"))?,
+                    title: row.required("title")?,
+                    duration_minutes: row.number("duration_minutes")?,
+                };
+                episodes.push((line, podcast_id, episode));
+                continue;
+            }
+
+            if kind_name.eq_ignore_ascii_case(REVIEW_KIND) {
+                let item_id = MediaId(
+                    row.number("item_id")?
+                        .ok_or_else(|| row.missing("item_id"))?,
+                );
+                let rating = row.number("rating")?.ok_or_else(|| row.missing("rating"))?;
+                reviews.push((
+                    line,
+                    item_id,
+                    row.required("reviewer")?,
+                    rating,
+                    row.optional("text"),
+                ));
+                continue;
+            }
+
+            if kind_name.eq_ignore_ascii_case(COLLECTION_KIND) {
+                let id = CollectionId(row.number("id")?.ok_or_else(|| row.missing("id"))?);
+                let entries = row
+                    .list("entries")
+                    .iter()
+                    .map(|entry| {
+                        parse_collection_entry(entry).ok_or_else(|| {
+                            malformed(
+                                line,
+                                format!("'{}' is not like 'item 3' or 'collection 2'", entry),
+                            )
+                        })
+                    })
+                    .collect::<Result<Vec<CollectionEntry>, ImportError>>()?;
+
+                let collection = Collection {
+                    name: row.required("name")?,
+                    entries,
+                };
+                if !collections.insert(id, collection) {
+                    return Err(malformed(
+                        line,
+                        format!("collection ID {} is used more than once", id),
+                    ));
+                }
+                continue;
+            }
+
+            let media = match MediaKind::from_name(kind_name) {
+                Some(kind) => row.media(kind)?,
+                None => row.custom(kind_name)?,
+            };
+            let id = row.number("id")?.map(MediaId);
+            entries.push((Some(line), id, media));
+        }
+
+        //episodes are attached before the catalog is built, so they are part of each podcast from the start
+        for (line, podcast_id, episode) in episodes {
+            let podcast = entries
+                .iter_mut()
+                .find(|(_, id, _)| *id == Some(podcast_id))
+                .map(|(_, _, media)| media);
+
+            match podcast {
+                Some(Media::Podcast { episodes, .. }) => episodes.push(episode),
+                Some(_) => {
+                    return Err(malformed(
+                        line,
+                        format!("item {} is not a podcast", podcast_id),
+                    ))
+                }
+                None => {
+                    return Err(malformed(
+                        line,
+                        format!("there is no podcast with ID {}", podcast_id),
+                    ))
+                }
+            }
+        }
+
+        let mut catalog = collect_entries(entries, next_id, kinds)?;
+        catalog.restore_collections(collections)?;
+
+        for (line, id, reviewer, rating, text) in reviews {
+            catalog
+                .review(id, &reviewer, rating, text.as_deref())
+                .map_err(|error| malformed(line, error.to_string()))?;
+        }
+
+        //the saved history replaces the changes recorded while the items were being added back
+        *catalog.history_log_mut() = History::from_saved(done, undone);
+        read_history_kinds(&mut catalog, kinds)?;
+        Ok(catalog)
+    }
+}
+
+fn parse_collection_entry(text: &str) -> Option<CollectionEntry> {
+    let (kind, id) = text.trim().split_once(' ')?;
+    let id = id.trim().parse().ok()?;
+
+    match kind.to_ascii_lowercase().as_str() {
+        "item" => Some(CollectionEntry::Item(MediaId(id))),
+        "collection" => Some(CollectionEntry::Collection(CollectionId(id))),
+        _ => None,
+    }
+}
+
+///Builds a Catalog from items that might have IDs, giving the ones that don't an ID after all the others.
+fn collect_entries(
+    entries: Vec<(Option<usize>, Option<MediaId>, Media)>,
+    next_id: u32,
+    kinds: &KindRegistry,
+) -> Result<Catalog, ImportError> {
+    let mut items = BTreeMap::new();
+    let mut without_id = vec![];
+
+    for (line, id, mut media) in entries {
+        kinds
+            .read(&mut media)
+            .map_err(|message| ImportError::UnreadableItem { line, message })?;
+        match id {
+            Some(id) => {
+                if items.insert(id, media).is_some() {
+                    return Err(ImportError::DuplicateId { id, line });
+                }
+            }
+            None => without_id.push(media),
+        }
+    }
+
+    let mut catalog = Catalog::from_items(items, next_id).map_err(ImportError::IdTooLarge)?;
+    for media in without_id {
+        catalog.add_media(media);
+    }
+
+    Ok(catalog)
+}
+
+//items in the history are put back by undo and redo, so they are read with the same kinds as the catalog's items
+fn read_history_kinds(catalog: &mut Catalog, kinds: &KindRegistry) -> Result<(), ImportError> {
+    for media in catalog.history_log_mut().media_mut() {
+        kinds
+            .read(media)
+            .map_err(|message| ImportError::UnreadableItem {
+                line: None,
+                message,
+            })?;
+    }
+    Ok(())
+}
+
+fn is_csv(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .map(|extension| extension.eq_ignore_ascii_case("csv"))
+        .unwrap_or(false)
+}
+
+fn csv_header(kind: MediaKind) -> Vec<String> {
+    let kind_columns: &[&str] = match kind {
+        MediaKind::Book => &["kind", "id", "title", "author"],
+        MediaKind::Movie => &["kind", "id", "title", "director"],
+        MediaKind::Audiobook | MediaKind::Placeholder => &["kind", "id", "title"],
+        MediaKind::Podcast => &["kind", "id", "show"],
+        //their fields aren't known here, so they are all kept as JSON in one column, metadata included
+        MediaKind::Custom(_) => {
+            return CUSTOM_COLUMNS
+                .iter()
+                .map(|column| column.to_string())
+                .collect()
+        }
+    };
+
+    kind_columns
+        .iter()
+        .chain(METADATA_COLUMNS.iter())
+        .map(|column| column.to_string())
+        .collect()
+}
+
+fn media_row(id: MediaId, media: &Media) -> Vec<String> {
+    let mut row = vec![media.kind().name().to_string(), id.to_string()];
+
+    match media {
+        Media::Book { title, author, .. } => row.extend([title.clone(), author.clone()]),
+        Media::Movie {
+            title, director, ..
+        } => row.extend([title.clone(), director.clone()]),
+        Media::Audiobook { title, .. } | Media::Placeholder { title, .. } => {
+            row.push(title.clone())
+        }
+        Media::Podcast { show, .. } => row.push(show.clone()),
+        Media::Custom(custom) => {
+            row.push(custom.item().to_json().to_string());
+            return row;
+        }
+    }
+
+    let metadata = media.metadata();
+    row.extend([
+        optional_to_string(&metadata.year),
+        join_list(&metadata.genres),
+        optional_to_string(&metadata.duration_minutes),
+        metadata.isbn.clone().unwrap_or_default(),
+        metadata.isan.clone().unwrap_or_default(),
+        metadata.language.clone().unwrap_or_default(),
+        join_list(&metadata.tags),
+        metadata.publisher.clone().unwrap_or_default(),
+    ]);
+
+    row
+}
+
+fn optional_to_string<T: ToString>(value: &Option<T>) -> String {
+    value
+        .as_ref()
+        .map(|value| value.to_string())
+        .unwrap_or_default()
+}
+
+fn join_list(list: &[String]) -> String {
+    list.iter()
+        .map(|value| {
+            value
+                .replace(LIST_ESCAPE, "\\\\")
+                .replace(LIST_SEPARATOR, "\\;")
+        })
+        .collect::<Vec<String>>()
+        .join(&LIST_SEPARATOR.to_string())
+}
+
+//the other way around from join_list, a backslash keeps the character after it as part of the value
+fn split_list(text: &str) -> Vec<String> {
+    let mut values = vec![];
+    let mut value = String::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            LIST_ESCAPE => value.extend(chars.next()),
+            LIST_SEPARATOR => values.push(std::mem::take(&mut value)),
+            _ => value.push(c),
+        }
+    }
+    values.push(value);
+
+    values
+        .into_iter()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+///One data row of a CSV section, with the header it should be read with.
+struct Row<'a> {
+    line: usize,
+    header: &'a [String],
+    record: &'a [String],
+}
+
+impl Row<'_> {
+    ///The trimmed value in the named column, or None if the section has no such column or the cell is empty.
+    fn get(&self, column: &str) -> Option<&str> {
+        let index = self.header.iter().position(|name| name == column)?;
+        let value = self.record.get(index)?.trim();
+
+        if value.is_empty() {
+            None
+        } else {
+            Some(value)
+        }
+    }
+
+    fn missing(&self, column: &str) -> ImportError {
+        malformed(self.line, format!("missing value for '{}'", column))
+    }
+
+    ///The text in a column that every row of the section has to have.  The cell can be empty, since an empty
+    ///title or author can be saved, but the column can't be left out.
+    fn required(&self, column: &str) -> Result<String, ImportError> {
+        if !self.header.iter().any(|name| name == column) {
+            return Err(malformed(
+                self.line,
+                format!("the section has no '{}' column", column),
+            ));
+        }
+        Ok(self.get(column).unwrap_or("").to_string())
+    }
+
+    fn optional(&self, column: &str) -> Option<String> {
+        self.get(column).map(String::from)
+    }
+
+    fn number<T: std::str::FromStr>(&self, column: &str) -> Result<Option<T>, ImportError> {
+        match self.get(column) {
+            None => Ok(None),
+            Some(value) => value.parse().map(Some).map_err(|_| {
+                malformed(
+                    self.line,
+                    format!("'{}' is not a valid number for '{}'", value, column),
+                )
+            }),
+        }
+    }
+
+    fn list(&self, column: &str) -> Vec<String> {
+        self.get(column).map(split_list).unwrap_or_default()
+    }
+
+    fn media(&self, kind: MediaKind) -> Result<Media, ImportError> {
+        let metadata = Metadata {
+            year: self.number("year")?,
+            genres: self.list("genres"),
+            duration_minutes: self.number("duration_minutes")?,
+            isbn: self.optional("isbn"),
+            isan: self.optional("isan"),
+            language: self.optional("language"),
+            tags: self.list("tags"),
+            publisher: self.optional("publisher"),
+        };
+
+        Ok(match kind {
+            MediaKind::Book => Media::Book {
+                title: self.required("title")?,
+                author: self.required("author")?,
+                metadata,
+            },
+            MediaKind::Movie => Media::Movie {
+                title: self.required("title")?,
+                director: self.required("director")?,
+                metadata,
+            },
+            MediaKind::Audiobook => Media::Audiobook {
+                title: self.required("title")?,
+                metadata,
+            },
+            //the episodes are added from their own section once every row has been read
+            MediaKind::Podcast => Media::Podcast {
+                show: self.required("show")?,
+                episodes: vec![],
+                metadata,
+            },
+            MediaKind::Placeholder => Media::Placeholder {
+                title: self.required("title")?,
+                metadata,
+            },
+            MediaKind::Custom(name) => return self.custom(name),
+        })
+    }
+
+    //a kind from outside this crate is read as it was saved, with the metadata in the JSON along with everything
+    //else, and then by its registered kind if there is one once every row has been read
+    fn custom(&self, kind: &str) -> Result<Media, ImportError> {
+        let fields = serde_json::from_str(&self.required("data")?).map_err(|error| {
+            malformed(self.line, format!("'data' is not valid JSON: {}", error))
+        })?;
+        let serde_json::Value::Object(fields) = fields else {
+            return Err(malformed(
+                self.line,
+                String::from("'data' is not a JSON object"),
+            ));
+        };
+        Ok(Media::Custom(CustomMedia::unregistered(kind, fields)))
+    }
+}
+
+///Formats one CSV row, quoting any field that has a comma, quote or line break in it.
+fn csv_row(fields: &[String]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect();
+
+    fields.join(",") + "\n"
+}
+
+///Splits CSV text into records along with the line each one starts on.  Quoted fields can hold commas,
+///doubled quotes ("") and line breaks, which is what spreadsheets write when a cell has them.
+pub(super) fn read_csv_records(text: &str) -> Result<Vec<(usize, Vec<String>)>, ImportError> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push((record_line, std::mem::take(&mut record)));
+                line += 1;
+                record_line = line;
+            }
+            '\n' => {
+                field.push(c);
+                line += 1;
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(malformed(
+            record_line,
+            String::from("quoted field is never closed"),
+        ));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((record_line, record));
+    }
+
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(title: &str, author: &str) -> Media {
+        Media::Book {
+            title: title.to_string(),
+            author: author.to_string(),
+            metadata: Metadata::default(),
+        }
+    }
+
+    //saves the catalog both ways and checks that each file loads back the same
+    fn round_trips(catalog: &Catalog) -> [Catalog; 2] {
+        let from_csv = Catalog::from_csv(&catalog.to_csv()).unwrap();
+        let from_json = Catalog::from_json(&catalog.to_json()).unwrap();
+
+        for loaded in [&from_csv, &from_json] {
+            let items: Vec<(MediaId, &Media)> = loaded.iter().collect();
+            assert_eq!(items, catalog.iter().collect::<Vec<_>>());
+        }
+        [from_csv, from_json]
+    }
+
+    #[test]
+    fn ids_of_removed_items_are_not_reused_after_loading() {
+        let mut catalog = Catalog::new();
+        catalog.add_media(book("One", "A"));
+        let removed = catalog.add_media(book("Two", "B"));
+        catalog.remove(removed).unwrap();
+
+        for mut loaded in round_trips(&catalog) {
+            assert_eq!(loaded.add_media(book("Three", "C")), MediaId(3));
+        }
+    }
+
+    #[test]
+    fn empty_fields_load_back() {
+        let mut catalog = Catalog::new();
+        catalog.add_media(book("T", ""));
+        catalog.add_media(book("", "Someone"));
+
+        round_trips(&catalog);
+    }
+
+    #[test]
+    fn list_values_can_hold_the_separator() {
+        let mut catalog = Catalog::new();
+        catalog.add_media(Media::Movie {
+            title: String::from("Movie"),
+            director: String::from("Director"),
+            metadata: Metadata {
+                genres: vec![String::from("Sci-Fi; Horror"), String::from("Drama")],
+                tags: vec![String::from("back\\slash"), String::from(";")],
+                ..Metadata::default()
+            },
+        });
+
+        round_trips(&catalog);
+        assert!(catalog.to_csv().contains(r"Sci-Fi\; Horror;Drama"));
+    }
+
+    #[test]
+    fn older_csv_without_a_catalog_section_still_loads() {
+        let text = "kind,id,title,author,tags\nbook,4,Title,Author,a;b\nbook,,New,Author,\n";
+        let catalog = Catalog::from_csv(text).unwrap();
+
+        let titles: Vec<(MediaId, &str)> = catalog
+            .iter()
+            .map(|(id, media)| (id, media.title()))
+            .collect();
+        assert_eq!(titles, vec![(MediaId(4), "Title"), (MediaId(5), "New")]);
+        assert_eq!(
+            catalog.get(MediaId(4)).unwrap().metadata().tags,
+            vec![String::from("a"), String::from("b")]
+        );
+    }
+
+    #[test]
+    fn ids_too_large_to_count_past_are_refused() {
+        for id in [u32::MAX, u32::MAX - 1] {
+            let json = format!(
+                r#"{{"items": [{{"id": {}, "kind": "book", "title": "T", "author": "A"}}]}}"#,
+                id
+            );
+            let error = Catalog::from_json(&json).err().unwrap();
+            assert!(matches!(error, ImportError::IdTooLarge(_)), "{}", error);
+
+            let csv = format!("kind,id,title,author\nbook,{},T,A\n", id);
+            assert!(matches!(
+                Catalog::from_csv(&csv),
+                Err(ImportError::IdTooLarge(_))
+            ));
+        }
+
+        let error = Catalog::from_csv(&format!("kind,next_id\ncatalog,{}\n", u32::MAX))
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "ID 4294967295 is too large to leave an ID for new items"
+        );
+        assert!(Catalog::from_csv("kind,id,title,author\nbook,4294967293,T,A\n").is_ok());
+    }
+
+    #[test]
+    fn csv_errors_name_the_line() {
+        let cases = [
+            ("book,1,T,A\n", "line 1: row comes before any header row"),
+            (
+                "kind,id,title\nbook,1,T\n",
+                "line 2: the section has no 'author' column",
+            ),
+            (
+                "kind,id,title,author\nbook,x,T,A\n",
+                "line 2: 'x' is not a valid number for 'id'",
+            ),
+            (
+                "kind,id,title,author\nbook,1,T,A\nbook,1,U,B\n",
+                "line 3: ID 1 is used more than once",
+            ),
+            (
+                "kind,id,title\nvinyl,1,T\n",
+                "line 2: the section has no 'data' column",
+            ),
+            (
+                "kind,id,data\nvinyl,1,[]\n",
+                "line 2: 'data' is not a JSON object",
+            ),
+            (
+                "kind,id,title,author\nbook,1,\"T,A\n",
+                "line 2: quoted field is never closed",
+            ),
+            (
+                "kind,state,description,changes\nhistory,later,add,[]\n",
+                "line 2: history state 'later' is not 'done' or 'undone'",
+            ),
+            (
+                "kind,state,description,changes\nhistory,done,add,oops\n",
+                "line 2: 'changes' is not valid JSON: expected value at line 1 column 1",
+            ),
+        ];
+
+        for (text, expected) in cases {
+            let error = Catalog::from_csv(text).err().unwrap();
+            assert_eq!(error.to_string(), expected, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,2 @@
+//the content module is shared by the p03-media binary in main.rs and by the benchmarks in the benches folder
+pub mod content;
@@ -1,273 +1,1314 @@
-mod content;
+use std::env;
+use std::io::ErrorKind;
+use std::process::ExitCode;
+
+use serde::Serialize;
+
+use p03_media::content::catalog::{Catalog, MediaId};
+use p03_media::content::collections::{CollectionEntry, CollectionError, CollectionId, OnRemove};
+use p03_media::content::enrich::{Enrichment, MetadataDump};
+use p03_media::content::history::ChangeSet;
+use p03_media::content::media::{Media, MediaKind, Metadata};
+use p03_media::content::query::Filter;
+use p03_media::content::reviews::Recommendation;
+use p03_media::content::sort::{Cursor, Page, Sort, SortKey};
+use p03_media::content::storage::ImportError;
+
+//used when --file isn't given, in the directory the command is run from
+const DEFAULT_CATALOG_FILE: &str = "catalog.json";
+
+const USAGE: &str = "\
+Usage: p03-media [--file <catalog>] [--json] <command> [arguments]
+
+Commands:
+  add <kind> <title> [creator]   add an item, kind is book, movie, audiobook, podcast or placeholder
+      [--year <year>] [--genre <genre>]... [--tag <tag>]... [--duration <minutes>]
+      [--isbn <isbn>] [--isan <isan>] [--language <language>] [--publisher <publisher>]
+  list [--kind <kind>] [--sort <keys>] [--limit <count>] [--cursor <cursor>]
+                                 list every item, or only items of one kind, sorted by keys like
+                                 'title' or '-year,creator' (title, creator, year or kind, '-' for descending),
+                                 a page of --limit items at a time starting after --cursor
+  search <words>...              find items by words in their title, creator or tags
+  show <id>                      show everything about one item
+  remove <id> [--cascade]        remove an item, --cascade also takes it out of any collections it is in
+  import <file>                  add the items in a JSON or CSV file as new items
+  export <file>                  write the catalog to a JSON or CSV file, CSV if it ends in .csv
+  review <id> <name> <rating> [text]
+                                 rate an item from 1 to 5 with an optional short review
+  reviews <id>                   list the ratings and reviews of an item
+  recommend <id> [--limit <count>]
+                                 suggest items for someone who liked this one
+  recommend --for <name> [--limit <count>]
+                                 suggest items for a reviewer from the items they rated highly
+  enrich <dump>                  fill in the missing author, year and publisher of books by ISBN
+                                 from a local JSON or CSV metadata dump, like an Open Library dump
+  duplicates                     list items that look like the same thing entered twice
+  merge <keep> <duplicate>       merge the duplicate into the kept item, keeping the richer details
+  undo                           undo the latest change, an import or merge is undone all at once
+  redo                           do the latest undone change again
+  history                        list the changes that can be undone and redone
+  collection list                list the collections
+  collection new <name>          make an empty collection, like a reading list or a watch queue
+  collection add <name> <id>...  add items to the end of a collection
+  collection nest <name> <child> put another collection inside a collection
+  collection show <name>         list the items in a collection, including nested ones, in order
+  collection delete <name> [--cascade]
+                                 delete a collection, --cascade also takes it out of collections it is in
+
+Options:
+  --file <catalog>   the catalog to work with, catalog.json if not given, created when first added to
+  --json             print results as JSON instead of a table
+
+Exit status:
+  0  the command worked
+  1  the command was understood but couldn't be done, like removing an ID that doesn't exist
+  2  the command line was wrong
+  3  a file couldn't be read or written
+";
+
+//options that are followed by a value, and switches like --json that aren't.  Anything else is a mistake
+const VALUE_OPTIONS: [&str; 14] = [
+    "file",
+    "kind",
+    "year",
+    "genre",
+    "tag",
+    "duration",
+    "isbn",
+    "isan",
+    "language",
+    "publisher",
+    "sort",
+    "limit",
+    "cursor",
+    "for",
+];
+const SWITCHES: [&str; 3] = ["json", "cascade", "help"];
+
+//options that only mean something to some commands, which are refused with the others instead of being ignored
+const COMMAND_OPTIONS: [(&str, &[&str]); 14] = [
+    ("kind", &["list"]),
+    ("year", &["add"]),
+    ("genre", &["add"]),
+    ("tag", &["add"]),
+    ("duration", &["add"]),
+    ("isbn", &["add"]),
+    ("isan", &["add"]),
+    ("language", &["add"]),
+    ("publisher", &["add"]),
+    ("sort", &["list"]),
+    ("limit", &["list", "recommend"]),
+    ("cursor", &["list"]),
+    ("for", &["recommend"]),
+    ("cascade", &["remove", "collection"]),
+];
+
+//how many recommendations are shown when --limit isn't given
+const DEFAULT_RECOMMENDATIONS: usize = 5;
+
+///What went wrong running a command, each kind exits with its own code so scripts can tell them apart.
+enum CliError {
+    //the command line itself was wrong, the usage text is printed along with the message
+    Usage(String),
+    //the command was understood but couldn't be done, like removing an ID that doesn't exist
+    Failed(String),
+    //a file that couldn't be read or written, with the path of the file in the message
+    Io(String),
+}
 
-use std::option;
-use content::media::Media;
-use content::catalog::Catalog;
+///The command line split into positional arguments and --options.
+struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+    switches: Vec<String>,
+}
 
+impl Args {
+    fn parse(raw: impl Iterator<Item = String>) -> Result<Args, CliError> {
+        let mut args = Args {
+            positional: vec![],
+            options: vec![],
+            switches: vec![],
+        };
+        let mut raw = raw.peekable();
+
+        while let Some(arg) = raw.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                args.positional.push(arg);
+                continue;
+            };
+
+            //both --year 1999 and --year=1999 work
+            let (name, inline_value) = match name.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (name.to_string(), None),
+            };
+
+            if VALUE_OPTIONS.contains(&name.as_str()) {
+                let value = match inline_value {
+                    Some(value) => value,
+                    None => raw
+                        .next()
+                        .ok_or_else(|| CliError::Usage(format!("--{} needs a value", name)))?,
+                };
+                args.options.push((name, value));
+            } else if !SWITCHES.contains(&name.as_str()) {
+                return Err(CliError::Usage(format!("unknown option --{}", name)));
+            } else if inline_value.is_some() {
+                return Err(CliError::Usage(format!("--{} doesn't take a value", name)));
+            } else {
+                args.switches.push(name);
+            }
+        }
 
-///Custom 'Option' Enum as demonstrated by guide
-///This one requires a lifetime annotation added to the syntax, 'a ,
-///but the guide doesn't explain it much yet,
-///as they say it will be covered in a later topic of the course
+        Ok(args)
+    }
 
-//commenting this enum out and modifying 'get_by_index_custom_option_enum to no longer return 'mighthaveavalue' but return 'Option' instead
+    ///Refuses options that were given to a command they don't apply to, like --year with list.
+    fn check_options_for(&self, command: &str) -> Result<(), CliError> {
+        let given = self
+            .options
+            .iter()
+            .map(|(name, _)| name)
+            .chain(self.switches.iter());
+        for name in given {
+            if let Some((_, only_for)) = COMMAND_OPTIONS
+                .iter()
+                .find(|(option, only_for)| option == name && !only_for.contains(&command))
+            {
+                return Err(CliError::Usage(format!(
+                    "--{} only works with {}",
+                    name,
+                    only_for.join(" and ")
+                )));
+            }
+        }
+        Ok(())
+    }
 
-// #[derive(Debug)]
-// enum MightHaveAValue<'a> {
-//     ThereIsAValue(&'a Media),
-//     NoValueAvailable,
-// }
+    ///The last value given for an option, so a later --file overrides an earlier one.
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(option, _)| option == name)
+            .map(|(_, value)| value.as_str())
+    }
 
-//I made this function first as my own guess at how to make a custom Vec::get, before watching the instructor build the function,
-//to see how close my guess would be to the real thing.  The instructor's version of this is done by adding a method onto the Catalog struct
-///Making a custom version of Vec::get to further understand the reason why Vec::get gives Option::Some(value) and Option::None returns
-fn get_by_index_version1(vector: &Vec<Media>, index: usize) -> &Media {
-    &vector[index]
-}
+    ///Every value given for an option that can be repeated, like --tag.
+    fn all(&self, name: &str) -> Vec<String> {
+        self.options
+            .iter()
+            .filter(|(option, _)| option == name)
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
 
-/*
+    fn number<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>, CliError> {
+        match self.option(name) {
+            None => Ok(None),
+            Some(value) => value.parse().map(Some).map_err(|_| {
+                CliError::Usage(format!("'{}' is not a valid number for --{}", value, name))
+            }),
+        }
+    }
 
-///A custom made enum to demonstrate what is going on when Option::Some() and Option::None are being returned by methods in Rust
-enum CustomOptionVersion2 {
+    fn switch(&self, name: &str) -> bool {
+        self.switches.iter().any(|switch| switch == name)
+    }
+}
 
-    //This one is something I'm trying to make by guessing how it would be done before seeing how the guide does it
-    //My guess appeared to look about right, but it ran into a problem where the type specified for Some wasn't able to be Some(_)
-    //and using Some(Media) to make it expect the exact type for the practice experiment revealed other issues about it preferring something with a Copy trait
-    //while using Some(&Media) was causing errors talking about how the lifetime might not be appropriate somehow
-    //so it didn't become fully functional and I want to see what the guide does here
-    //UPDATE:  It seemed to have to do with this enum needing a "lifetime annotation" added to it in order to clear some errors talking about lifetime issues
-    Some(Media),
-    None,
+//how an item is printed as JSON, its ID followed by the same fields it is saved with
+#[derive(Serialize)]
+struct Entry<'a> {
+    id: MediaId,
+    #[serde(flatten)]
+    media: &'a Media,
 }
 
-fn get_by_index_version2(vector: &Vec<Media>, index: usize) -> CustomOptionVersion2 {
-    if vector.len() > index {
-        CustomOptionVersion2::Some(vector[index])
-    }
-    else {
-        CustomOptionVersion2::None
+fn main() -> ExitCode {
+    let result = Args::parse(env::args().skip(1)).and_then(|args| run(&args));
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(CliError::Usage(message)) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            ExitCode::from(2)
+        }
+        Err(CliError::Failed(message)) => {
+            eprintln!("error: {}", message);
+            ExitCode::from(1)
+        }
+        Err(CliError::Io(message)) => {
+            eprintln!("error: {}", message);
+            ExitCode::from(3)
+        }
     }
 }
 
-*/
+fn run(args: &Args) -> Result<(), CliError> {
+    if args.switch("help") {
+        print!("{}", USAGE);
+        return Ok(());
+    }
 
-fn main() {
-    let audiobook = Media::Audiobook {
-        title: String::from("An Audiobook"),
+    let Some((command, rest)) = args.positional.split_first() else {
+        return Err(CliError::Usage(String::from("no command given")));
     };
-    let good_movie = Media::Movie {
-        title: String::from("Good Movie"),
-        director: String::from("Good Director"),
+    args.check_options_for(command)?;
+    let path = args.option("file").unwrap_or(DEFAULT_CATALOG_FILE);
+    let json = args.switch("json");
+    //removing something that is in a collection is refused unless --cascade is given
+    let on_remove = if args.switch("cascade") {
+        OnRemove::Cascade
+    } else {
+        OnRemove::Refuse
     };
-    let book = Media::Book {
-        title: String::from("A Book"),
-        author: String::from("Author Authorman"),
-    };
-    //this enum variant is created in a similar syntax to how it was defined in the Enum block
-    let podcast = Media::Podcast(1);
-    //this enum variant is created with no curly braces nor parentheses like how it was defined in the enum block
-    let placeholder = Media::Placeholder;
-
-    let mut catalog = Catalog::new();
 
-    catalog.add_media(audiobook);
-    catalog.add_media(book);
-    catalog.add_media(good_movie);
-    catalog.add_media(podcast);
-    catalog.add_media(placeholder);
-
-    println!("Catalog object: {:#?}\n", catalog);
-
-    println!("Catalog item descriptions:");
-    //this needs to use an immutable reference to catalog.items otherwise it moves them
-    for media in &catalog.items {
-        println!("{}", media.description());
+    match command.as_str() {
+        "add" => {
+            let mut catalog = open_catalog(path)?;
+            let id = catalog.add_media(media_from_args(rest, args)?);
+            save_catalog(&catalog, path)?;
+            print_item(&catalog, id, json);
+        }
+        "list" => {
+            let catalog = open_catalog(path)?;
+            let filter = args
+                .option("kind")
+                .map(parse_kind)
+                .transpose()?
+                .map(Filter::kind);
+            //a plain list is in the order items were added, while pages are sorted by title unless --sort says otherwise
+            let sort = match args.option("sort") {
+                Some(text) => Sort::parse(text)
+                    .ok_or_else(|| CliError::Usage(format!("'{}' is not a valid sort", text)))?,
+                None => Sort::by(SortKey::Title),
+            };
+            let cursor = args
+                .option("cursor")
+                .map(|text| {
+                    Cursor::parse(text)
+                        .ok_or_else(|| CliError::Usage(format!("'{}' is not a valid cursor", text)))
+                })
+                .transpose()?;
+
+            let limit = args.number("limit")?;
+            if args.option("sort").is_none() && limit.is_none() && cursor.is_none() {
+                let items: Vec<(MediaId, &Media)> = match &filter {
+                    Some(filter) => catalog.search(filter).collect(),
+                    None => catalog.iter().collect(),
+                };
+                print_items(&items, json);
+                return Ok(());
+            }
+
+            let page = catalog
+                .page(
+                    filter.as_ref(),
+                    &sort,
+                    cursor.as_ref(),
+                    limit.unwrap_or(usize::MAX),
+                )
+                .map_err(|error| CliError::Failed(error.to_string()))?;
+            print_page(&page, json);
+        }
+        "search" => {
+            if rest.is_empty() {
+                return Err(CliError::Usage(String::from(
+                    "search needs at least one word",
+                )));
+            }
+            let catalog = open_catalog(path)?;
+            print_items(&catalog.search_text(&rest.join(" ")), json);
+        }
+        "show" => {
+            let catalog = open_catalog(path)?;
+            let id = parse_id(rest)?;
+            if catalog.get(id).is_none() {
+                return Err(CliError::Failed(format!("there is no item with ID {}", id)));
+            }
+            print_item(&catalog, id, json);
+        }
+        "remove" => {
+            let mut catalog = open_catalog(path)?;
+            let id = parse_id(rest)?;
+            let removed = catalog
+                .remove_with(id, on_remove)
+                .map_err(|error| CliError::Failed(error.to_string()))?;
+            save_catalog(&catalog, path)?;
+
+            if json {
+                println!(
+                    "{}",
+                    to_json(&Entry {
+                        id,
+                        media: &removed
+                    })
+                );
+            } else {
+                println!("Removed {}: {}", id, removed.description());
+            }
+        }
+        "import" => {
+            let [file] = rest else {
+                return Err(CliError::Usage(String::from(
+                    "import needs exactly one file",
+                )));
+            };
+            let mut catalog = open_catalog(path)?;
+            let ids = catalog
+                .import_file(file)
+                .map_err(|error| read_error(file, error))?;
+            save_catalog(&catalog, path)?;
+
+            let imported: Vec<(MediaId, &Media)> = ids
+                .iter()
+                .filter_map(|id| catalog.get(*id).map(|media| (*id, media)))
+                .collect();
+            print_items(&imported, json);
+        }
+        "export" => {
+            let [file] = rest else {
+                return Err(CliError::Usage(String::from(
+                    "export needs exactly one file",
+                )));
+            };
+            let catalog = open_catalog(path)?;
+            save_catalog(&catalog, file)?;
+
+            if json {
+                println!(
+                    "{}",
+                    serde_json::json!({ "file": file, "items": catalog.len() })
+                );
+            } else {
+                println!("Exported {} items to {}", catalog.len(), file);
+            }
+        }
+        "review" => {
+            let (id, reviewer, rating, text) = match rest {
+                [id, reviewer, rating] => (id, reviewer, rating, None),
+                [id, reviewer, rating, text] => (id, reviewer, rating, Some(text.as_str())),
+                _ => {
+                    return Err(CliError::Usage(String::from(
+                        "review needs an ID, a name, a rating and optionally a review",
+                    )))
+                }
+            };
+            let id = parse_id(std::slice::from_ref(id))?;
+            let rating = rating
+                .parse()
+                .map_err(|_| CliError::Usage(format!("'{}' is not a valid rating", rating)))?;
+
+            let mut catalog = open_catalog(path)?;
+            catalog
+                .review(id, reviewer, rating, text)
+                .map_err(|error| CliError::Failed(error.to_string()))?;
+            save_catalog(&catalog, path)?;
+            print_reviews(&catalog, id, json);
+        }
+        "reviews" => {
+            let catalog = open_catalog(path)?;
+            let id = parse_id(rest)?;
+            if catalog.get(id).is_none() {
+                return Err(CliError::Failed(format!("there is no item with ID {}", id)));
+            }
+            print_reviews(&catalog, id, json);
+        }
+        "recommend" => {
+            let catalog = open_catalog(path)?;
+            let count = args.number("limit")?.unwrap_or(DEFAULT_RECOMMENDATIONS);
+            let recommendations = match (args.option("for"), rest) {
+                (Some(reviewer), []) => catalog.recommend_for(reviewer, count),
+                (None, [_]) => {
+                    let id = parse_id(rest)?;
+                    if catalog.get(id).is_none() {
+                        return Err(CliError::Failed(format!("there is no item with ID {}", id)));
+                    }
+                    catalog.recommend_similar(id, count)
+                }
+                _ => {
+                    return Err(CliError::Usage(String::from(
+                        "recommend needs either an ID or --for <name>",
+                    )))
+                }
+            };
+            print_recommendations(&catalog, &recommendations, json);
+        }
+        "duplicates" => {
+            let catalog = open_catalog(path)?;
+            print_duplicates(&catalog, json);
+        }
+        "merge" => {
+            let (keep, duplicate) = match rest {
+                [keep, duplicate] => (
+                    parse_id(std::slice::from_ref(keep))?,
+                    parse_id(std::slice::from_ref(duplicate))?,
+                ),
+                _ => {
+                    return Err(CliError::Usage(String::from(
+                        "merge needs the ID to keep and the ID of the duplicate",
+                    )))
+                }
+            };
+            let mut catalog = open_catalog(path)?;
+            catalog
+                .merge(keep, duplicate)
+                .map_err(|error| CliError::Failed(error.to_string()))?;
+            save_catalog(&catalog, path)?;
+            print_item(&catalog, keep, json);
+        }
+        "enrich" => {
+            let [file] = rest else {
+                return Err(CliError::Usage(String::from(
+                    "enrich needs exactly one metadata dump file",
+                )));
+            };
+            let dump = MetadataDump::load(file).map_err(|error| read_error(file, error))?;
+            let mut catalog = open_catalog(path)?;
+            let enrichment = catalog.enrich(&dump);
+            save_catalog(&catalog, path)?;
+            print_enrichment(&catalog, &enrichment, json);
+        }
+        "undo" | "redo" => {
+            let mut catalog = open_catalog(path)?;
+            let changes = if command == "undo" {
+                catalog.undo()
+            } else {
+                catalog.redo()
+            };
+            let Some(changes) = changes else {
+                return Err(CliError::Failed(format!("there is nothing to {}", command)));
+            };
+
+            if json {
+                println!(
+                    "{}",
+                    to_json(&serde_json::json!({ command.as_str(): changes.description }))
+                );
+            } else {
+                let verb = if command == "undo" { "Undid" } else { "Redid" };
+                let count = changes.changes.len();
+                println!(
+                    "{} {} ({} change{})",
+                    verb,
+                    changes.description,
+                    count,
+                    if count == 1 { "" } else { "s" }
+                );
+            }
+            save_catalog(&catalog, path)?;
+        }
+        "history" => {
+            let catalog = open_catalog(path)?;
+            print_history(&catalog, json);
+        }
+        "collection" => run_collection(rest, path, json, on_remove)?,
+        _ => return Err(CliError::Usage(format!("unknown command '{}'", command))),
     }
 
-    println!(
-        "\nUsing catalog.items.get() to show match checking the returned Some() and None Options:"
-    );
-    //Example of using Some() and None match checking when using items.get()
-    match catalog.items.get(1) {
-        Option::Some(value) => {
-            println!("Item found: {:#?}", value)
+    Ok(())
+}
+
+fn run_collection(
+    rest: &[String],
+    path: &str,
+    json: bool,
+    on_remove: OnRemove,
+) -> Result<(), CliError> {
+    let mut catalog = open_catalog(path)?;
+    let failed = |error: CollectionError| CliError::Failed(error.to_string());
+
+    match rest {
+        [command] if command == "list" => {
+            let collections = catalog.collections();
+            if json {
+                let collections: Vec<serde_json::Value> = collections
+                    .iter()
+                    .map(|(id, collection)| {
+                        serde_json::json!({ "id": id, "name": collection.name, "entries": collection.entries })
+                    })
+                    .collect();
+                println!("{}", to_json(&collections));
+                return Ok(());
+            }
+
+            let rows: Vec<[String; 3]> = collections
+                .iter()
+                .map(|(id, collection)| {
+                    [
+                        id.to_string(),
+                        collection.name.clone(),
+                        collection.entries.len().to_string(),
+                    ]
+                })
+                .collect();
+            print_table(["ID", "NAME", "ENTRIES"], &rows);
+        }
+        [command, name] if command == "new" => {
+            let id = catalog.create_collection(name).map_err(failed)?;
+            save_catalog(&catalog, path)?;
+            println!("Made collection {}: {}", id, name.trim());
+        }
+        [command, name, ids @ ..] if command == "add" && !ids.is_empty() => {
+            let collection = find_collection(&catalog, name)?;
+            for id in ids {
+                let id = parse_id(std::slice::from_ref(id))?;
+                catalog
+                    .add_to_collection(collection, CollectionEntry::Item(id))
+                    .map_err(failed)?;
+            }
+            save_catalog(&catalog, path)?;
+            println!(
+                "Added {} item{} to {}",
+                ids.len(),
+                if ids.len() == 1 { "" } else { "s" },
+                name
+            );
+        }
+        [command, name, child_name] if command == "nest" => {
+            let collection = find_collection(&catalog, name)?;
+            let child = find_collection(&catalog, child_name)?;
+            catalog
+                .add_to_collection(collection, CollectionEntry::Collection(child))
+                .map_err(failed)?;
+            save_catalog(&catalog, path)?;
+            println!("Put {} inside {}", child_name, name);
         }
-        Option::None => {
-            println!("No item at that index")
+        [command, name] if command == "show" => {
+            let collection = find_collection(&catalog, name)?;
+            let ids = catalog.collection_items(collection).map_err(failed)?;
+            let items: Vec<(MediaId, &Media)> = ids
+                .iter()
+                .filter_map(|id| catalog.get(*id).map(|media| (*id, media)))
+                .collect();
+            print_items(&items, json);
+        }
+        [command, name] if command == "delete" => {
+            let collection = find_collection(&catalog, name)?;
+            let removed = catalog
+                .remove_collection(collection, on_remove)
+                .map_err(failed)?;
+            save_catalog(&catalog, path)?;
+            println!("Deleted collection {}: {}", collection, removed.name);
+        }
+        _ => {
+            return Err(CliError::Usage(String::from(
+                "unknown or incomplete collection command",
+            )))
         }
     }
 
-    //it is possible to omit the "Option::" part of the enum variant checking
-    match catalog.items.get(8) {
-        Some(value) => println!("Value found: {:#?}", value),
-        None => println!("No value found at that index"),
-    }
+    Ok(())
+}
 
-    //testing the function I made on my own before seeing the instructor's explanations
+fn find_collection(catalog: &Catalog, name: &str) -> Result<CollectionId, CliError> {
+    catalog
+        .collections()
+        .find(name)
+        .ok_or_else(|| CliError::Failed(format!("there is no collection named '{}'", name)))
+}
 
-    //these function calls return the value and print it nicely
-    // println!("Get_By_Index 1: {:#?}", get_by_index_version1(&catalog.items, 1));
-    // println!("Get_By_Index 1: {:#?}", get_by_index_version1(&catalog.items, 1));
+///Loads the catalog file, or starts an empty catalog if the file doesn't exist yet.
+fn open_catalog(path: &str) -> Result<Catalog, CliError> {
+    match Catalog::load(path) {
+        Ok(catalog) => Ok(catalog),
+        Err(ImportError::Io(error)) if error.kind() == ErrorKind::NotFound => Ok(Catalog::new()),
+        Err(error) => Err(read_error(path, error)),
+    }
+}
 
-    //this function call causes a panic at runtime due to the index being out of bounds
-    // println!("Get_By_Index 1: {:#?}", get_by_index_version1(&catalog.items, 100));
+fn save_catalog(catalog: &Catalog, path: &str) -> Result<(), CliError> {
+    catalog
+        .save(path)
+        .map_err(|error| CliError::Io(format!("could not write {}: {}", path, error)))
+}
 
-    //this demonstrates why having an Option return type can be helpful to avoid a panic at runtime
+fn read_error(path: &str, error: ImportError) -> CliError {
+    CliError::Io(format!("could not read {}: {}", path, error))
+}
 
-    //using the instructor's example method calls:
-    // println!("Index 1: {:#?}", catalog.get_by_index(1));     //returns &Media
-    // println!("Index 4: {:#?}", catalog.get_by_index(4));     //returns &Media
-    // println!("Index 40: {:#?}", catalog.get_by_index(40));   //causes a panic due to out of bounds index
+fn media_from_args(rest: &[String], args: &Args) -> Result<Media, CliError> {
+    let (kind, title, creator) = match rest {
+        [kind, title] => (parse_kind(kind)?, title.clone(), None),
+        [kind, title, creator] => (parse_kind(kind)?, title.clone(), Some(creator.clone())),
+        _ => {
+            return Err(CliError::Usage(String::from(
+                "add needs a kind, a title and for books and movies a creator",
+            )))
+        }
+    };
 
-    println!("\nUsing custom made 'Vec::get' type method with custom made 'Option' type enums:");
-    //using the intructor's example of a method call that returns a custom made enum called MightHaveAValue:
-    println!("Index 1: {:#?}", catalog.get_by_index_custom_option_enum(1)); //returns ThereIsAValue(&'a Media)
-    println!("Index 4: {:#?}", catalog.get_by_index_custom_option_enum(4)); //returns ThereIsAValue(&'a Media)
-    println!("Index 40: {:#?}", catalog.get_by_index_custom_option_enum(40)); //returns NoValueAvailable, doesn't panic
+    let metadata = Metadata {
+        year: args.number("year")?,
+        genres: args.all("genre"),
+        duration_minutes: args.number("duration")?,
+        isbn: args.option("isbn").map(String::from),
+        isan: args.option("isan").map(String::from),
+        language: args.option("language").map(String::from),
+        publisher: args.option("publisher").map(String::from),
+        tags: args.all("tag"),
+    };
 
+    let needs_creator =
+        |kind: &str| CliError::Usage(format!("a {} needs a creator after its title", kind));
+    let takes_no_creator =
+        |kind: &str| CliError::Usage(format!("a {} doesn't have a creator", kind));
+
+    Ok(match kind {
+        MediaKind::Book => Media::Book {
+            title,
+            author: creator.ok_or_else(|| needs_creator("book"))?,
+            metadata,
+        },
+        MediaKind::Movie => Media::Movie {
+            title,
+            director: creator.ok_or_else(|| needs_creator("movie"))?,
+            metadata,
+        },
+        MediaKind::Custom(name) => {
+            return Err(CliError::Usage(format!(
+                "{} items can only be added by the program that defines them",
+                name
+            )))
+        }
+        _ if creator.is_some() => return Err(takes_no_creator(kind.name())),
+        MediaKind::Audiobook => Media::Audiobook { title, metadata },
+        MediaKind::Podcast => Media::Podcast {
+            show: title,
+            episodes: vec![],
+            metadata,
+        },
+        MediaKind::Placeholder => Media::Placeholder { title, metadata },
+    })
+}
 
-    // old block of code that demonstrated the custom enum of MightHaveAValue before replacing it to use Option again
-    // println!("\nGet the actual item with match statement rather than getting the enum:");
-    // match catalog.get_by_index_custom_option_enum(1) {
-    //     MightHaveAValue::ThereIsAValue(value) => println!("Value found: {:#?}", value),
-    //     MightHaveAValue::NoValueAvailable => println!("No value was available at that index")
-    // }
+fn parse_kind(name: &str) -> Result<MediaKind, CliError> {
+    MediaKind::from_name(name)
+        .ok_or_else(|| CliError::Usage(format!("'{}' is not a kind of media", name)))
+}
 
+fn parse_id(rest: &[String]) -> Result<MediaId, CliError> {
+    match rest {
+        [id] => id
+            .parse()
+            .map(MediaId)
+            .map_err(|_| CliError::Usage(format!("'{}' is not a valid ID", id))),
+        _ => Err(CliError::Usage(String::from("expected exactly one ID"))),
+    }
+}
 
-    // println!("\nGet the actual item using 'if let' pattern matching:");
-    // if let MightHaveAValue::ThereIsAValue(value) = catalog.get_by_index_custom_option_enum(1) {
-    //     println!("Value found: {:#?}", value)
-    // }
-    // else {
-    //     println!("No value found")
-    // }
-    
+fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string_pretty(value).expect("catalog items should always serialize to JSON")
+}
 
-    //new versions of the above block of code that use the Option type for its matching
-    println!("\nGet the actual item with match statement rather than getting the enum:");
-    match catalog.get_by_index_custom_option_enum(1) {
-        Some(value) => println!("Value found: {:#?}", value),
-        None => println!("No value was available at that index")
+fn print_items(items: &[(MediaId, &Media)], json: bool) {
+    if json {
+        let entries: Vec<Entry> = items
+            .iter()
+            .map(|(id, media)| Entry { id: *id, media })
+            .collect();
+        println!("{}", to_json(&entries));
+        return;
     }
 
+    let rows: Vec<[String; 5]> = items
+        .iter()
+        .map(|(id, media)| {
+            [
+                id.to_string(),
+                media.kind().name().to_string(),
+                media.title().to_string(),
+                media.creator().unwrap_or("").to_string(),
+                media
+                    .metadata()
+                    .year
+                    .map(|year| year.to_string())
+                    .unwrap_or_default(),
+            ]
+        })
+        .collect();
+    print_table(["ID", "KIND", "TITLE", "CREATOR", "YEAR"], &rows);
+    println!(
+        "{} item{}",
+        rows.len(),
+        if rows.len() == 1 { "" } else { "s" }
+    );
+}
 
-    println!("\nGet the actual item using 'if let' pattern matching:");
-    if let Some(value) = catalog.get_by_index_custom_option_enum(1) {
-        println!("Value found: {:#?}", value)
-    }
-    else {
-        println!("No value found")
+///Prints rows with each column padded to the width of its longest value.
+fn print_table<const N: usize>(header: [&str; N], rows: &[[String; N]]) {
+    let mut widths = header.map(|name| name.chars().count());
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
     }
 
+    let format_row = |cells: Vec<&str>| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell))
+            .collect();
+        padded.join("  ").trim_end().to_string()
+    };
 
+    println!("{}", format_row(header.to_vec()));
+    for row in rows {
+        println!("{}", format_row(row.iter().map(String::as_str).collect()));
+    }
+}
 
+fn print_page(page: &Page, json: bool) {
+    if json {
+        let entries: Vec<Entry> = page
+            .items
+            .iter()
+            .map(|(id, media)| Entry { id: *id, media })
+            .collect();
+        let next = page.next.as_ref().map(|cursor| cursor.to_string());
+        println!(
+            "{}",
+            to_json(&serde_json::json!({ "items": entries, "next_cursor": next }))
+        );
+        return;
+    }
 
-    // ADDITIONAL WAYS TO CHECK OPTION TYPES: 
-    
-    //      .unwrap(), .expect(""), .unwrap_or(&placeholder)
+    print_items(&page.items, false);
+    if let Some(next) = &page.next {
+        println!("Next page: --cursor {}", next);
+    }
+}
 
+fn print_reviews(catalog: &Catalog, id: MediaId, json: bool) {
+    let reviews = catalog.reviews(id);
+    let average = catalog.average_rating(id);
+
+    if json {
+        println!(
+            "{}",
+            to_json(
+                &serde_json::json!({ "id": id, "average_rating": average, "reviews": reviews })
+            )
+        );
+        return;
+    }
 
-    //using .unwrap()
-    //this gets the value if it is a Some(), and panics if it is a None
+    match average {
+        Some(average) => {
+            let plural = if reviews.len() == 1 { "" } else { "s" };
+            println!(
+                "Average rating {:.1} from {} review{}",
+                average,
+                reviews.len(),
+                plural
+            )
+        }
+        None => println!("No reviews yet"),
+    }
+    for review in reviews {
+        let stars = "*".repeat(review.rating as usize);
+        match &review.text {
+            Some(text) => println!("  {:<5} {}: {}", stars, review.reviewer, text),
+            None => println!("  {:<5} {}", stars, review.reviewer),
+        }
+    }
+}
 
-    
-    let mut item = catalog.get_by_index_custom_option_enum(1);
-    
-    //this prints the item
-    println!("Item at index 1 unwrapped: {:#?}", item.unwrap());
-    
-    item = catalog.get_by_index_custom_option_enum(10);
-    
-    //this panics
-    // println!("Item at index 10 unwrapped: {:#?}", item.unwrap());
+fn print_recommendations(catalog: &Catalog, recommendations: &[Recommendation], json: bool) {
+    if json {
+        let recommendations: Vec<serde_json::Value> = recommendations
+            .iter()
+            .map(|recommendation| {
+                serde_json::json!({
+                    "id": recommendation.id,
+                    "score": recommendation.score,
+                    "reasons": recommendation.reasons,
+                })
+            })
+            .collect();
+        println!("{}", to_json(&recommendations));
+        return;
+    }
 
+    let rows: Vec<[String; 4]> = recommendations
+        .iter()
+        .map(|recommendation| {
+            [
+                recommendation.id.to_string(),
+                catalog
+                    .get(recommendation.id)
+                    .map(|media| media.title().to_string())
+                    .unwrap_or_default(),
+                format!("{:.2}", recommendation.score),
+                recommendation.reasons.join("; "),
+            ]
+        })
+        .collect();
+    print_table(["ID", "TITLE", "SCORE", "BECAUSE"], &rows);
+}
 
+fn print_duplicates(catalog: &Catalog, json: bool) {
+    let suggestions = catalog.find_duplicates();
+
+    if json {
+        let suggestions: Vec<serde_json::Value> = suggestions
+            .iter()
+            .map(|suggestion| {
+                serde_json::json!({
+                    "keep": suggestion.keep,
+                    "duplicate": suggestion.duplicate,
+                    "similarity": suggestion.similarity,
+                    "reason": suggestion.reason,
+                })
+            })
+            .collect();
+        println!("{}", to_json(&suggestions));
+        return;
+    }
 
-    //using .expect()
-    //this gets the value if there is Some, and if None, it prints a message and panics
+    let title = |id: MediaId| {
+        catalog
+            .get(id)
+            .map(|media| media.title().to_string())
+            .unwrap_or_default()
+    };
+    let rows: Vec<[String; 5]> = suggestions
+        .iter()
+        .map(|suggestion| {
+            [
+                suggestion.keep.to_string(),
+                title(suggestion.keep),
+                suggestion.duplicate.to_string(),
+                title(suggestion.duplicate),
+                suggestion.reason.clone(),
+            ]
+        })
+        .collect();
+    print_table(["KEEP", "TITLE", "DUPLICATE", "TITLE", "REASON"], &rows);
+    println!(
+        "{} suggested merge{}",
+        rows.len(),
+        if rows.len() == 1 { "" } else { "s" }
+    );
+}
 
-    item = catalog.get_by_index_custom_option_enum(1);
+fn print_history(catalog: &Catalog, json: bool) {
+    if json {
+        let descriptions = |changes: &[ChangeSet]| -> Vec<String> {
+            changes
+                .iter()
+                .map(|changes| changes.description.clone())
+                .collect()
+        };
+        println!(
+            "{}",
+            to_json(&serde_json::json!({
+                "done": descriptions(catalog.history()),
+                "undone": descriptions(catalog.undone()),
+            }))
+        );
+        return;
+    }
 
-    //this prints the item
-    println!("Item at 1 using .expect(): {:#?}", item.expect(".expect() error message: There was no value at the index selected"));
+    //the undone changes are listed after the rest in the order they were made, which is the order redo does them
+    let done = catalog.history().iter().map(|changes| (changes, ""));
+    let undone = catalog
+        .undone()
+        .iter()
+        .rev()
+        .map(|changes| (changes, "undone"));
+    let rows: Vec<[String; 4]> = done
+        .chain(undone)
+        .enumerate()
+        .map(|(number, (changes, state))| {
+            [
+                (number + 1).to_string(),
+                changes.description.clone(),
+                changes.changes.len().to_string(),
+                state.to_string(),
+            ]
+        })
+        .collect();
+    print_table(["#", "CHANGE", "CHANGES", ""], &rows);
+}
 
-    item = catalog.get_by_index_custom_option_enum(10);
+fn print_enrichment(catalog: &Catalog, enrichment: &Enrichment, json: bool) {
+    if json {
+        let filled: Vec<serde_json::Value> = enrichment
+            .filled
+            .iter()
+            .map(|(id, fields)| serde_json::json!({ "id": id, "filled": fields }))
+            .collect();
+        let invalid: Vec<serde_json::Value> = enrichment
+            .invalid
+            .iter()
+            .map(|(id, error)| serde_json::json!({ "id": id, "error": error.to_string() }))
+            .collect();
+        println!(
+            "{}",
+            to_json(&serde_json::json!({
+                "filled": filled,
+                "unchanged": enrichment.unchanged,
+                "not_found": enrichment.not_found,
+                "invalid": invalid,
+            }))
+        );
+        return;
+    }
 
-    //this prints a message and then panics
-    // println!("Item at 1 using .expect(): {:#?}", item.expect(".expect() error message: There was no value at the index selected"));
+    let title = |id: &MediaId| {
+        catalog
+            .get(*id)
+            .map(|media| media.title().to_string())
+            .unwrap_or_default()
+    };
+    let mut rows: Vec<[String; 3]> = vec![];
+    for (id, fields) in &enrichment.filled {
+        rows.push([
+            id.to_string(),
+            title(id),
+            format!("filled in {}", fields.join(", ")),
+        ]);
+    }
+    for id in &enrichment.unchanged {
+        rows.push([id.to_string(), title(id), String::from("nothing missing")]);
+    }
+    for id in &enrichment.not_found {
+        rows.push([id.to_string(), title(id), String::from("not in the dump")]);
+    }
+    for (id, error) in &enrichment.invalid {
+        rows.push([
+            id.to_string(),
+            title(id),
+            format!("invalid ISBN: {}", error),
+        ]);
+    }
 
+    print_table(["ID", "TITLE", "RESULT"], &rows);
+    let count = enrichment.filled.len();
+    println!(
+        "Filled in {} book{}",
+        count,
+        if count == 1 { "" } else { "s" }
+    );
+}
 
-    //using .unwrap_or()
-    //this gets the value in the Some() if there is one, or else gives another value if there is a None, and does not panic
+fn print_item(catalog: &Catalog, id: MediaId, json: bool) {
+    let Some(media) = catalog.get(id) else {
+        return;
+    };
+    if json {
+        println!("{}", to_json(&Entry { id, media }));
+        return;
+    }
 
-    item = catalog.get_by_index_custom_option_enum(1);
+    let metadata = media.metadata();
+    let mut fields: Vec<(&str, String)> = vec![
+        ("ID", id.to_string()),
+        ("Kind", media.kind().name().to_string()),
+        ("Title", media.title().to_string()),
+    ];
 
-    //this gives the value in the Some()
-    //this needed to be given a default value that was of type &Media, so I used a reference to a newly created placeholder variant 
-    println!("Index 1 using the .unwrap_or() method: {:#?}", item.unwrap_or(&Media::Placeholder));
-    item = catalog.get_by_index_custom_option_enum(10);
+    if let Some(creator) = media.creator() {
+        fields.push(("Creator", creator.to_string()));
+    }
+    if let Some(year) = metadata.year {
+        fields.push(("Year", year.to_string()));
+    }
+    if !metadata.genres.is_empty() {
+        fields.push(("Genres", metadata.genres.join(", ")));
+    }
+    if let Some(minutes) = metadata.duration_minutes {
+        fields.push(("Duration", format!("{} min", minutes)));
+    }
+    if let Some(isbn) = &metadata.isbn {
+        fields.push(("ISBN", isbn.clone()));
+    }
+    if let Some(isan) = &metadata.isan {
+        fields.push(("ISAN", isan.clone()));
+    }
+    if let Some(language) = &metadata.language {
+        fields.push(("Language", language.clone()));
+    }
+    if let Some(publisher) = &metadata.publisher {
+        fields.push(("Publisher", publisher.clone()));
+    }
+    if !metadata.tags.is_empty() {
+        fields.push(("Tags", metadata.tags.join(", ")));
+    }
 
-    //this has a None Option type so it gives 'Placeholder' in the output AND DOES NOT PANIC / CRASH THE PROGRAM
-    //this needed to be given a default value that was of type &Media, so I used a reference to a newly created placeholder variant 
-    println!("Index 10 using the .unwrap_or() method: {:#?}", item.unwrap_or(&Media::Placeholder));
+    for (name, value) in fields {
+        println!("{:<11}{}", format!("{}:", name), value);
+    }
 
+    if let Media::Podcast { episodes, .. } = media {
+        println!("Episodes: {}", episodes.len());
+        for episode in episodes {
+            let duration = episode
+                .duration_minutes
+                .map(|minutes| format!(" ({} min)", minutes))
+                .unwrap_or_default();
+            println!("  {}. {}{}", episode.number, episode.title, duration);
+        }
+    }
+}
 
-    /*
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    //--------------------experimenting with checking all values with a loop
+    fn parse_args(raw: &[&str]) -> Result<Args, CliError> {
+        Args::parse(raw.iter().map(|arg| arg.to_string()))
+    }
 
-        //got unexpected results when using 'for i in [0..5]' vs 'for i in [0,1,2,3,4,5]'
+    //runs a command against the catalog file, like the command line would
+    fn run_with(file: &str, raw: &[&str]) -> Result<(), CliError> {
+        let mut with_file = vec!["--file", file];
+        with_file.extend(raw);
+        parse_args(&with_file).and_then(|args| run(&args))
+    }
 
-    //for i in [0..5] was intended to produce "value found:" 5 times and "No item.." one time,
-    //but instead it said "Value found" once and showed all items
-    //compiler sees this as Range<usize>
-    for i in [0..5] {
-        match catalog.items.get(i) {
-            Some(value) => println!("Value found: {:#?}", value),
-            None => println!("No item found at that index"),
+    fn usage_error(result: Result<(), CliError>) -> String {
+        match result {
+            Err(CliError::Usage(message)) => message,
+            Err(_) => panic!("expected a usage error, got another error"),
+            Ok(()) => panic!("expected a usage error, but the command ran"),
         }
     }
 
-    //writing the for loop in this way did result in either "Value Found" or "No item found.." appearing a total of 6 times
-    //compiler sees this as usize
-    for i in [0,1,2,3,4,5] {
-        match catalog.items.get(i) {
-            Some(value) => println!("Value found: {:#?}", value),
-            None => println!("No item found at index of {}", i)
+    fn failure(result: Result<(), CliError>) -> String {
+        match result {
+            Err(CliError::Failed(message)) => message,
+            Err(_) => panic!("expected the command to fail, got another error"),
+            Ok(()) => panic!("expected the command to fail, but it ran"),
         }
     }
 
-    */
-
-    /*
-
-    //--------------------experimenting with Vec::get and index slicing
-
-    //using catalog.items.get(index) doesn't move the value, and it also wraps the result in 'Some()'
-    println!("Index 1: {:#?}", catalog.items.get(1));
-    println!("Index 1: {:#?}", catalog.items.get(1));
-
-    //using catalog.items.get(100) for an index that is not in the collection, it returns a None value
-    println!("Index 100: {:#?}", catalog.items.get(100));
-
-    //if you want to get all the items rather than one index or a subset, use ..
-    println!("All items: {:#?}", catalog.items.get(..));
-
-    //to get the 3rd item to the last item, use 2..
-    println!("Item index 2 to the end: {:#?}", catalog.items.get(2..));
-
-
-    //there isn't a 'stride' option like in python, but similar functionality would work like this, using iter().step_by()
-    //this example of step_by().collect()::<Vec<_>> no longer has the Option Some() or None appear with it as was seen when using Vec::get
-
-    println!("Every second item: {:#?}", catalog.items.iter().step_by(2).collect::<Vec<_>>());
+    fn titles(catalog: &Catalog) -> Vec<String> {
+        catalog
+            .iter()
+            .map(|(_, media)| media.title().to_string())
+            .collect()
+    }
 
-    //using either <Vec<_>> or <Vec<&Media>> both work, though I don't yet know what the meaning of the former is
+    #[test]
+    fn splits_positional_arguments_options_and_switches() {
+        let args = parse_args(&[
+            "add",
+            "book",
+            "Dune",
+            "--year=1965",
+            "--tag",
+            "sf",
+            "--json",
+            "Herbert",
+            "--tag",
+            "classic",
+        ])
+        .ok()
+        .unwrap();
+        assert_eq!(args.positional, vec!["add", "book", "Dune", "Herbert"]);
+        assert_eq!(args.option("year"), Some("1965"));
+        assert_eq!(args.all("tag"), vec!["sf", "classic"]);
+        assert!(args.switch("json") && !args.switch("cascade"));
+        assert!(matches!(args.number::<u16>("year"), Ok(Some(1965))));
+
+        let later_wins = parse_args(&["--file", "a.json", "--file", "b.json"])
+            .ok()
+            .unwrap();
+        assert_eq!(later_wins.option("file"), Some("b.json"));
+    }
 
-    //I checked and <_> means it's a type placeholder that allows the compiler to infer the type based on the context, which it could tell was &Media
-    //however, using just .collect() wasn't enough to be able to infer that it was needing <Vec<&Media>>, and it needed <Vec<_>> to help it infer
+    #[test]
+    fn explains_what_is_wrong_with_the_command_line() {
+        let parse_error = |raw: &[&str]| usage_error(parse_args(raw).map(|_| ()));
+        assert_eq!(parse_error(&["list", "--sort"]), "--sort needs a value");
+        assert_eq!(
+            parse_error(&["list", "--json=yes"]),
+            "--json doesn't take a value"
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("catalog.json");
+        let file = file.to_str().unwrap();
+        let cases: [(&[&str], &str); 15] = [
+            (&[], "no command given"),
+            (&["fly"], "unknown command 'fly'"),
+            (&["list", "--verbose"], "unknown option --verbose"),
+            (
+                &["show", "1", "--year", "1999"],
+                "--year only works with add",
+            ),
+            (
+                &["search", "dune", "--limit=3"],
+                "--limit only works with list and recommend",
+            ),
+            (
+                &["list", "--cascade"],
+                "--cascade only works with remove and collection",
+            ),
+            (
+                &["recommend", "1", "--kind", "book"],
+                "--kind only works with list",
+            ),
+            (
+                &["add", "book", "Dune"],
+                "a book needs a creator after its title",
+            ),
+            (
+                &["add", "podcast", "Serial", "Koenig"],
+                "a podcast doesn't have a creator",
+            ),
+            (
+                &["add", "vinyl", "Abbey Road"],
+                "'vinyl' is not a kind of media",
+            ),
+            (
+                &["add", "movie", "Heat", "Mann", "--year", "soon"],
+                "'soon' is not a valid number for --year",
+            ),
+            (&["show", "one"], "'one' is not a valid ID"),
+            (
+                &["list", "--sort", "colour"],
+                "'colour' is not a valid sort",
+            ),
+            (
+                &["recommend"],
+                "recommend needs either an ID or --for <name>",
+            ),
+            (
+                &["collection", "shuffle"],
+                "unknown or incomplete collection command",
+            ),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(usage_error(run_with(file, raw)), expected, "{:?}", raw);
+        }
+        //nothing was added by the commands that were refused
+        assert!(!dir.path().join("catalog.json").exists());
+    }
 
-    println!("Every second item: {:#?}", catalog.items.iter().step_by(2).collect::<Vec<&Media>>());
+    #[test]
+    fn commands_change_the_catalog_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("catalog.json");
+        let file = path.to_str().unwrap();
+
+        run_with(
+            file,
+            &[
+                "add",
+                "book",
+                "Dune",
+                "Frank Herbert",
+                "--year",
+                "1965",
+                "--genre",
+                "Sci-Fi",
+            ],
+        )
+        .ok()
+        .unwrap();
+        run_with(file, &["add", "movie", "Heat", "Michael Mann"])
+            .ok()
+            .unwrap();
+        run_with(file, &["add", "podcast", "Serial", "--json"])
+            .ok()
+            .unwrap();
+        let catalog = Catalog::load(file).unwrap();
+        assert_eq!(titles(&catalog), vec!["Dune", "Heat", "Serial"]);
+        assert_eq!(catalog.get(MediaId(1)).unwrap().metadata().year, Some(1965));
+
+        run_with(file, &["review", "1", "ann", "5", "a classic"])
+            .ok()
+            .unwrap();
+        assert_eq!(Catalog::load(file).unwrap().reviews(MediaId(1)).len(), 1);
+        assert_eq!(
+            failure(run_with(file, &["review", "1", "ann", "9"])),
+            "a rating must be from 1 to 5, not 9"
+        );
+
+        run_with(file, &["collection", "new", "Weekend"])
+            .ok()
+            .unwrap();
+        run_with(file, &["collection", "add", "Weekend", "2", "3"])
+            .ok()
+            .unwrap();
+        //an item in a collection is only removed with --cascade
+        assert_eq!(
+            failure(run_with(file, &["remove", "2"])),
+            "item 2 is still in collection 1"
+        );
+        run_with(file, &["remove", "2", "--cascade"]).ok().unwrap();
+        let catalog = Catalog::load(file).unwrap();
+        assert_eq!(titles(&catalog), vec!["Dune", "Serial"]);
+
+        //undo still works after the catalog was saved and loaded again
+        run_with(file, &["undo"]).ok().unwrap();
+        let catalog = Catalog::load(file).unwrap();
+        assert_eq!(titles(&catalog), vec!["Dune", "Heat", "Serial"]);
+        let weekend = catalog.collections().find("weekend").unwrap();
+        assert_eq!(
+            catalog.collection_items(weekend).unwrap(),
+            vec![MediaId(2), MediaId(3)]
+        );
+        run_with(file, &["redo"]).ok().unwrap();
+        assert_eq!(Catalog::load(file).unwrap().len(), 2);
+
+        assert_eq!(
+            failure(run_with(file, &["show", "2"])),
+            "there is no item with ID 2"
+        );
+        assert_eq!(
+            failure(run_with(file, &["collection", "show", "Later"])),
+            "there is no collection named 'Later'"
+        );
+    }
 
-    */
+    #[test]
+    fn exports_and_imports_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("catalog.json");
+        let file = file.to_str().unwrap();
+        let csv = dir.path().join("export.csv");
+        let csv = csv.to_str().unwrap();
+        let other = dir.path().join("other.json");
+        let other = other.to_str().unwrap();
+
+        run_with(
+            file,
+            &["add", "book", "Emma", "Jane Austen", "--tag", "classic"],
+        )
+        .ok()
+        .unwrap();
+        run_with(file, &["add", "audiobook", "Dracula"])
+            .ok()
+            .unwrap();
+        run_with(file, &["export", csv]).ok().unwrap();
+
+        run_with(other, &["add", "movie", "Heat", "Michael Mann"])
+            .ok()
+            .unwrap();
+        run_with(other, &["import", csv]).ok().unwrap();
+        let catalog = Catalog::load(other).unwrap();
+        assert_eq!(titles(&catalog), vec!["Heat", "Emma", "Dracula"]);
+        assert_eq!(
+            catalog.get(MediaId(2)).unwrap().metadata().tags,
+            vec!["classic"]
+        );
+
+        let missing = dir.path().join("missing.csv");
+        assert!(matches!(
+            run_with(other, &["import", missing.to_str().unwrap()]),
+            Err(CliError::Io(message)) if message.starts_with("could not read")
+        ));
+        std::fs::write(dir.path().join("broken.json"), "{oops").unwrap();
+        assert!(matches!(
+            run_with(dir.path().join("broken.json").to_str().unwrap(), &["list"]),
+            Err(CliError::Io(message)) if message.contains("broken.json")
+        ));
+    }
 }
@@ -0,0 +1,156 @@
+//Picks the log records to keep by their level, their text and when they were written
+
+use crate::level::{Level, LevelFilter};
+use crate::record::LogRecord;
+use crate::timestamp::Timestamp;
+
+///What a record needs to be kept.  Every part that is set has to match.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    //None keeps every record, including ones without a level and lines that couldn't be parsed
+    pub levels: Option<LevelFilter>,
+    //text that has to be somewhere in the line, all of them if there are several
+    pub patterns: Vec<String>,
+    pub ignore_case: bool,
+    pub since: Option<Timestamp>,
+    pub until: Option<Timestamp>,
+}
+
+//by default a filter keeps errors, which is what this program was first written to pull out of a log
+impl Default for Filter {
+    fn default() -> Self {
+        Filter {
+            levels: Some(LevelFilter::at_least(Level::Error)),
+            patterns: vec![],
+            ignore_case: false,
+            since: None,
+            until: None,
+        }
+    }
+}
+
+impl Filter {
+    ///Whether a record is kept.  Records without a timestamp are left out when --since or --until is set, since
+    ///there is no telling when they were written.
+    pub fn matches(&self, record: &LogRecord) -> bool {
+        if let Some(levels) = &self.levels {
+            if !levels.matches(record.level) {
+                return false;
+            }
+        }
+
+        if self.since.is_some() || self.until.is_some() {
+            let Some(timestamp) = &record.timestamp else {
+                return false;
+            };
+            if self
+                .since
+                .is_some_and(|since| timestamp.compare_loosely(&since).is_lt())
+            {
+                return false;
+            }
+            if self
+                .until
+                .is_some_and(|until| timestamp.compare_loosely(&until).is_gt())
+            {
+                return false;
+            }
+        }
+
+        //a stack trace is searched along with its record, so an error can be found by a frame in its trace
+        if record.continuation.is_empty() {
+            self.matches_text(&record.text)
+        } else {
+            let mut text = record.text.clone();
+            for line in &record.continuation {
+                text.push('\n');
+                text.push_str(line);
+            }
+            self.matches_text(&text)
+        }
+    }
+
+    ///Whether a line that couldn't be parsed is kept, which it only can be if the filter doesn't need anything
+    ///that comes from parsing.
+    pub fn matches_unparsed(&self, text: &str) -> bool {
+        self.levels.is_none()
+            && self.since.is_none()
+            && self.until.is_none()
+            && self.matches_text(text)
+    }
+
+    fn matches_text(&self, text: &str) -> bool {
+        if self.ignore_case {
+            let text = text.to_lowercase();
+            self.patterns
+                .iter()
+                .all(|pattern| text.contains(&pattern.to_lowercase()))
+        } else {
+            self.patterns
+                .iter()
+                .all(|pattern| text.contains(pattern.as_str()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::parse_line;
+
+    fn record(text: &str) -> LogRecord {
+        parse_line(1, text).unwrap()
+    }
+
+    fn at(text: &str) -> Option<Timestamp> {
+        Some(Timestamp::parse(text).unwrap())
+    }
+
+    #[test]
+    fn keeps_errors_by_default() {
+        let filter = Filter::default();
+        assert!(filter.matches(&record("ERROR 14:33:45 disk full")));
+        assert!(filter.matches(&record("FATAL 14:33:45 out of memory")));
+        assert!(!filter.matches(&record("WARN 14:33:45 disk nearly full")));
+        assert!(!filter.matches_unparsed("just some words"));
+    }
+
+    #[test]
+    fn needs_every_pattern_and_searches_the_trace() {
+        let mut filter = Filter {
+            levels: None,
+            patterns: vec!["disk".to_string(), "Full".to_string()],
+            ..Filter::default()
+        };
+        assert!(!filter.matches(&record("INFO 14:33:45 disk full")));
+        filter.ignore_case = true;
+        assert!(filter.matches(&record("INFO 14:33:45 disk full")));
+        assert!(!filter.matches(&record("INFO 14:33:45 disk ok")));
+
+        let mut traced = record("ERROR 14:33:45 write failed");
+        traced
+            .continuation
+            .push("Caused by: java.io.IOException: disk full".to_string());
+        assert!(filter.matches(&traced));
+
+        assert!(filter.matches_unparsed("DISK FULL, no level"));
+        assert!(!filter.matches_unparsed("nothing to see"));
+    }
+
+    #[test]
+    fn keeps_records_between_since_and_until() {
+        let filter = Filter {
+            levels: None,
+            since: at("2024-01-15T14:30:00Z"),
+            until: at("2024-01-15T15:00:00Z"),
+            ..Filter::default()
+        };
+        assert!(filter.matches(&record("[2024-01-15T14:30:00Z INFO app] first")));
+        assert!(filter.matches(&record("[2024-01-15T15:00:00Z INFO app] last")));
+        assert!(!filter.matches(&record("[2024-01-15T14:29:59Z INFO app] early")));
+        assert!(!filter.matches(&record("[2024-01-15T15:00:01Z INFO app] late")));
+        //there is no telling when these were written
+        assert!(!filter.matches(&record("INFO no time")));
+        assert!(!filter.matches_unparsed("just some words"));
+    }
+}
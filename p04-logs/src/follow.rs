@@ -0,0 +1,426 @@
+//Watches a log file for new lines like `tail -F`, carrying on when the file is rotated or truncated
+
+use std::fs::{self, File, Metadata};
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+use crate::reader::{self, Decoded, InvalidUtf8, Line, MAX_LINE_BYTES};
+
+//how much is read from the file at a time
+const CHUNK_BYTES: usize = 64 * 1024;
+//how many lines one poll reads at most, so a large file read from its start is handed out a piece at a time
+const MAX_LINES_PER_POLL: usize = 10_000;
+
+///Something that happened to the followed file since the last poll.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Line(Line),
+    //the file got shorter, so it was truncated (like by `> app.log` or logrotate's copytruncate) and is read again
+    //from the start
+    Truncated,
+    //another file was put where the followed one was, like logrotate does, and the new one is read from its start
+    Rotated,
+    //the file was moved or deleted and nothing has taken its place yet
+    Missing,
+    //the file appeared after being missing
+    Appeared,
+}
+
+//which file a path leads to, to tell when a different file has been put in its place
+type FileId = (u64, u64);
+
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+//without inode numbers rotation can't be seen directly, but a new file is usually shorter than the old one was, so
+//it is still picked up, as a truncation
+#[cfg(not(unix))]
+fn file_id(_metadata: &Metadata) -> Option<FileId> {
+    None
+}
+
+///Follows one log file by polling it, returning what was added to it since the last poll.
+pub struct Follower {
+    path: PathBuf,
+    file: Option<File>,
+    id: Option<FileId>,
+    //how far into the file has been read
+    position: u64,
+    //line numbers count from where following started, since the lines before it aren't read
+    number: usize,
+    //the end of the file when it doesn't end in a newline yet, kept until the rest of the line is written
+    partial: Vec<u8>,
+    partial_truncated: bool,
+    invalid_utf8: InvalidUtf8,
+    missing: bool,
+    max_lines: usize,
+    //whether the last poll stopped at max_lines before reaching the end of the file
+    behind: bool,
+}
+
+impl Follower {
+    ///Starts following a file from its end, or from its start if `from_start` is set.  The file doesn't have to
+    ///exist yet, it is read from its start once it does.
+    pub fn new(path: &str, from_start: bool) -> io::Result<Follower> {
+        let mut follower = Follower {
+            path: PathBuf::from(path),
+            file: None,
+            id: None,
+            position: 0,
+            number: 0,
+            partial: vec![],
+            partial_truncated: false,
+            invalid_utf8: InvalidUtf8::default(),
+            missing: false,
+            max_lines: MAX_LINES_PER_POLL,
+            behind: false,
+        };
+
+        match File::open(&follower.path) {
+            Ok(file) => {
+                let metadata = file.metadata()?;
+                follower.id = file_id(&metadata);
+                follower.position = if from_start { 0 } else { metadata.len() };
+                follower.file = Some(file);
+            }
+            Err(error) if error.kind() == ErrorKind::NotFound => follower.missing = true,
+            Err(error) => return Err(error),
+        }
+        Ok(follower)
+    }
+
+    pub fn invalid_utf8(mut self, invalid_utf8: InvalidUtf8) -> Self {
+        self.invalid_utf8 = invalid_utf8;
+        self
+    }
+
+    ///Sets how many lines a poll reads at most, so tests can see the rest being left for the next poll.
+    #[cfg(test)]
+    pub fn max_lines_per_poll(mut self, max_lines: usize) -> Self {
+        self.max_lines = max_lines;
+        self
+    }
+
+    ///Whether the last poll stopped before the end of the file, so the next one has lines waiting for it.
+    pub fn is_behind(&self) -> bool {
+        self.behind
+    }
+
+    ///Whether the file didn't exist the last time it was looked at.
+    pub fn is_missing(&self) -> bool {
+        self.missing
+    }
+
+    ///Reads whatever has been added to the file since the last poll, and notices if it was rotated, truncated or
+    ///removed.  A line is only returned once its newline has been written, so half written lines aren't split in two.
+    pub fn poll(&mut self) -> io::Result<Vec<Event>> {
+        let mut events = vec![];
+
+        match fs::metadata(&self.path) {
+            Ok(metadata) if self.file.is_none() => {
+                self.switch_to_new_file(&metadata)?;
+                self.missing = false;
+                events.push(Event::Appeared);
+            }
+            Ok(metadata) if file_id(&metadata).is_some() && file_id(&metadata) != self.id => {
+                //whatever was written to the old file before it was rotated is read first, so no lines are lost
+                self.read_available(&mut events)?;
+                if self.behind {
+                    //the rotation is noticed again by the next poll, once the old file has been read to its end
+                    return Ok(events);
+                }
+                self.finish_partial(&mut events);
+                self.switch_to_new_file(&metadata)?;
+                self.missing = false;
+                events.push(Event::Rotated);
+            }
+            Ok(metadata) if metadata.len() < self.position => {
+                self.position = 0;
+                self.number = 0;
+                self.partial.clear();
+                self.partial_truncated = false;
+                events.push(Event::Truncated);
+            }
+            Ok(_) => {}
+            //a moved file can still be read through the open handle until a new one takes its place
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                if !self.missing {
+                    self.missing = true;
+                    events.push(Event::Missing);
+                }
+            }
+            Err(error) => return Err(error),
+        }
+
+        self.read_available(&mut events)?;
+        Ok(events)
+    }
+
+    fn switch_to_new_file(&mut self, metadata: &Metadata) -> io::Result<()> {
+        self.file = Some(File::open(&self.path)?);
+        self.id = file_id(metadata);
+        self.position = 0;
+        self.number = 0;
+        self.partial.clear();
+        self.partial_truncated = false;
+        Ok(())
+    }
+
+    //reads from where the last read stopped to the end of the file, or until max_lines lines have been read,
+    //turning every finished line into an event
+    fn read_available(&mut self, events: &mut Vec<Event>) -> io::Result<()> {
+        self.behind = false;
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        file.seek(SeekFrom::Start(self.position))?;
+
+        let mut lines = 0;
+        let mut chunk = vec![0; CHUNK_BYTES];
+        loop {
+            let count = match file.read(&mut chunk) {
+                Ok(0) => return Ok(()),
+                Ok(count) => count,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            };
+
+            let mut rest = &chunk[..count];
+            while !rest.is_empty() {
+                let newline = rest.iter().position(|byte| *byte == b'\n');
+                let line_part = &rest[..newline.unwrap_or(rest.len())];
+
+                //like the LineReader, the rest of a line over the limit is left out so memory stays bounded
+                let room = MAX_LINE_BYTES.saturating_sub(self.partial.len());
+                if line_part.len() > room {
+                    self.partial_truncated = true;
+                }
+                self.partial
+                    .extend_from_slice(&line_part[..line_part.len().min(room)]);
+
+                match newline {
+                    Some(newline) => {
+                        //position only moves past whole lines and the partial one, so stopping early loses nothing
+                        self.position += newline as u64 + 1;
+                        rest = &rest[newline + 1..];
+                        Self::finish_line(
+                            &mut self.partial,
+                            &mut self.partial_truncated,
+                            &mut self.number,
+                            self.invalid_utf8,
+                            events,
+                        );
+                        lines += 1;
+                        if lines == self.max_lines {
+                            self.behind = true;
+                            return Ok(());
+                        }
+                    }
+                    None => {
+                        self.position += rest.len() as u64;
+                        rest = &[];
+                    }
+                }
+            }
+        }
+    }
+
+    //a file that was rotated away won't have the rest of its last line written to it, so that line ends where it is
+    fn finish_partial(&mut self, events: &mut Vec<Event>) {
+        if !self.partial.is_empty() {
+            Self::finish_line(
+                &mut self.partial,
+                &mut self.partial_truncated,
+                &mut self.number,
+                self.invalid_utf8,
+                events,
+            );
+        }
+    }
+
+    //takes the parts of self it needs one by one, since the file is still borrowed while lines are being finished
+    fn finish_line(
+        partial: &mut Vec<u8>,
+        partial_truncated: &mut bool,
+        number: &mut usize,
+        invalid_utf8: InvalidUtf8,
+        events: &mut Vec<Event>,
+    ) {
+        *number += 1;
+        let truncated = std::mem::take(partial_truncated);
+        let decoded = reader::decode_line(partial, truncated, invalid_utf8);
+        partial.clear();
+
+        if let Decoded::Valid(text) | Decoded::Replaced(text) = decoded {
+            events.push(Event::Line(Line {
+                number: *number,
+                text,
+                truncated,
+            }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use std::path::Path;
+
+    fn append(path: &Path, text: &str) {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    fn follow(path: &Path, from_start: bool) -> Follower {
+        Follower::new(path.to_str().unwrap(), from_start).unwrap()
+    }
+
+    //the events with each line shown as its number and text, so they are easy to compare
+    fn poll(follower: &mut Follower) -> Vec<String> {
+        follower
+            .poll()
+            .unwrap()
+            .into_iter()
+            .map(|event| match event {
+                Event::Line(line) => format!("{}: {}", line.number, line.text),
+                event => format!("{:?}", event),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn returns_lines_appended_after_it_started() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        append(&path, "old line\n");
+
+        let mut follower = follow(&path, false);
+        assert!(poll(&mut follower).is_empty());
+        append(&path, "first\nsecond\r\n");
+        assert_eq!(poll(&mut follower), vec!["1: first", "2: second"]);
+        assert!(poll(&mut follower).is_empty());
+
+        let mut from_start = follow(&path, true);
+        assert_eq!(
+            poll(&mut from_start),
+            vec!["1: old line", "2: first", "3: second"]
+        );
+    }
+
+    #[test]
+    fn waits_for_the_rest_of_a_partial_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        append(&path, "");
+
+        let mut follower = follow(&path, false);
+        append(&path, "ERROR half a");
+        assert!(poll(&mut follower).is_empty());
+        append(&path, " line\nnext");
+        assert_eq!(poll(&mut follower), vec!["1: ERROR half a line"]);
+        append(&path, "\n");
+        assert_eq!(poll(&mut follower), vec!["2: next"]);
+    }
+
+    #[test]
+    fn starts_again_when_the_file_is_truncated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        append(&path, "");
+
+        let mut follower = follow(&path, false);
+        append(&path, "one\ntwo\n");
+        assert_eq!(poll(&mut follower), vec!["1: one", "2: two"]);
+
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(0)
+            .unwrap();
+        append(&path, "new\n");
+        assert_eq!(poll(&mut follower), vec!["Truncated", "1: new"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reads_the_old_file_to_its_end_and_then_the_new_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        append(&path, "");
+
+        let mut follower = follow(&path, false);
+        append(&path, "before\nunfinished");
+        assert_eq!(poll(&mut follower), vec!["1: before"]);
+
+        //written to the old file after it was moved, but before the follower noticed
+        append(&path, " last\ntail");
+        fs::rename(&path, dir.path().join("app.log.1")).unwrap();
+        append(&path, "rotated\n");
+        assert_eq!(
+            poll(&mut follower),
+            vec!["2: unfinished last", "3: tail", "Rotated", "1: rotated"]
+        );
+    }
+
+    #[test]
+    fn reads_a_limited_number_of_lines_per_poll() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        append(&path, "one\ntwo\nthree\nfour\nfive");
+
+        let mut follower = follow(&path, true).max_lines_per_poll(2);
+        assert_eq!(poll(&mut follower), vec!["1: one", "2: two"]);
+        assert!(follower.is_behind());
+        assert_eq!(poll(&mut follower), vec!["3: three", "4: four"]);
+        assert!(follower.is_behind());
+        assert!(poll(&mut follower).is_empty());
+        assert!(!follower.is_behind());
+        append(&path, "\n");
+        assert_eq!(poll(&mut follower), vec!["5: five"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn finishes_the_old_file_over_several_polls_before_the_new_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        append(&path, "");
+
+        let mut follower = follow(&path, false).max_lines_per_poll(2);
+        append(&path, "a\nb\nc\n");
+        fs::rename(&path, dir.path().join("app.log.1")).unwrap();
+        append(&path, "new\n");
+        assert_eq!(poll(&mut follower), vec!["1: a", "2: b"]);
+        assert_eq!(poll(&mut follower), vec!["3: c", "Rotated", "1: new"]);
+    }
+
+    #[test]
+    fn waits_for_a_missing_file_to_appear() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+
+        let mut follower = follow(&path, false);
+        assert!(follower.is_missing());
+        assert!(poll(&mut follower).is_empty());
+
+        append(&path, "hello\n");
+        assert_eq!(poll(&mut follower), vec!["Appeared", "1: hello"]);
+        assert!(!follower.is_missing());
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(poll(&mut follower), vec!["Missing"]);
+        assert!(poll(&mut follower).is_empty());
+        append(&path, "back\n");
+        assert_eq!(poll(&mut follower), vec!["Rotated", "1: back"]);
+    }
+}
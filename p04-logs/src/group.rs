@@ -0,0 +1,247 @@
+//Puts the lines of a stack trace or other multi-line message together with the record they belong to
+
+use crate::reader::Line;
+use crate::record::{self, LogRecord, ParseError};
+
+///A record keeps at most this many continuation lines, so a runaway trace can't use up all the memory.
+pub const MAX_CONTINUATION_LINES: usize = 1000;
+
+///A record with its continuation lines, or a line that isn't a record or part of one.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Entry {
+    Record(LogRecord),
+    Unparsed(Line, ParseError),
+}
+
+///Whether a line carries on the record before it instead of starting a new one: indented lines, Java's "at ..."
+///frames, "Caused by:", "Suppressed:" and "... 12 more", the exception's own name and message, and the parts of a
+///Rust panic, like "thread 'main' panicked at src/main.rs:2:5:" and its "stack backtrace:".
+pub fn is_continuation(text: &str) -> bool {
+    if text.starts_with([' ', '\t']) {
+        return true;
+    }
+
+    let prefixes = [
+        "at ",
+        "Caused by:",
+        "Suppressed:",
+        "stack backtrace:",
+        "note: run with `RUST_BACKTRACE",
+    ];
+    if prefixes.iter().any(|prefix| text.starts_with(prefix)) {
+        return true;
+    }
+    if text.starts_with("...") && text.trim_end().ends_with("more") {
+        return true;
+    }
+    if text.starts_with("thread '") && text.contains("' panicked at") {
+        return true;
+    }
+
+    //"java.lang.IllegalStateException: message", a class name ending in Exception or Error, with its package
+    let name = text.split([':', ' ']).next().unwrap_or("");
+    let is_class_name = name.contains('.')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '.' | '_' | '$'));
+    is_class_name
+        && ["Exception", "Error", "Throwable"]
+            .iter()
+            .any(|suffix| name.ends_with(suffix))
+}
+
+///Groups lines into entries as they are read, holding on to the latest record until a line that isn't part of it
+///shows up, since until then more of its trace could still follow.
+#[derive(Debug, Default)]
+pub struct Grouper {
+    pending: Option<LogRecord>,
+    //since Rust 1.73 a panic's message is on the line after "thread 'main' panicked at src/main.rs:2:5:", and can
+    //be any text at all
+    panic_message_next: bool,
+    //continuation lines left out for going over MAX_CONTINUATION_LINES
+    dropped: usize,
+}
+
+impl Grouper {
+    ///Adds the next line, returning the entries it finished, in order.  Blank lines are left out without ending the
+    ///record they are in.
+    pub fn push(&mut self, line: Line) -> Vec<Entry> {
+        if line.text.trim().is_empty() {
+            return vec![];
+        }
+
+        let panic_message = std::mem::take(&mut self.panic_message_next);
+        if let Some(pending) = self.pending.as_mut() {
+            if panic_message || is_continuation(&line.text) {
+                self.panic_message_next =
+                    line.text.starts_with("thread '") && line.text.trim_end().ends_with(':');
+                if pending.continuation.len() < MAX_CONTINUATION_LINES {
+                    pending.continuation.push(line.text);
+                } else {
+                    self.dropped += 1;
+                }
+                return vec![];
+            }
+        }
+
+        let mut finished: Vec<Entry> = self.finish().into_iter().collect();
+        match record::parse_line(line.number, &line.text) {
+            Ok(record) => self.pending = Some(record),
+            Err(error) => finished.push(Entry::Unparsed(line, error)),
+        }
+        finished
+    }
+
+    ///Hands out the record being held on to, at the end of the input or when a followed file has gone quiet.
+    pub fn finish(&mut self) -> Option<Entry> {
+        self.pending.take().map(Entry::Record)
+    }
+
+    ///The number of continuation lines left out so far for going over MAX_CONTINUATION_LINES.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //groups the lines, showing each record as its first line with its continuation lines after " | ", and lines
+    //that aren't records as "unparsed: " and the line
+    fn group(lines: &[&str]) -> Vec<String> {
+        let mut grouper = Grouper::default();
+        let mut entries = vec![];
+        for (index, text) in lines.iter().enumerate() {
+            entries.extend(grouper.push(Line {
+                number: index + 1,
+                text: text.to_string(),
+                truncated: false,
+            }));
+        }
+        entries.extend(grouper.finish());
+        entries
+            .into_iter()
+            .map(|entry| match entry {
+                Entry::Record(record) => {
+                    let mut shown = vec![record.text];
+                    shown.extend(record.continuation);
+                    shown.join(" | ")
+                }
+                Entry::Unparsed(line, _) => format!("unparsed: {}", line.text),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn recognizes_continuation_lines() {
+        for text in [
+            "    at com.example.Main.run(Main.java:12)",
+            "\tat com.example.Main.main(Main.java:5)",
+            "at com.example.Main.main(Main.java:5)",
+            "Caused by: java.io.IOException: disk full",
+            "Suppressed: java.lang.RuntimeException",
+            "... 12 more",
+            "java.lang.IllegalStateException: not started",
+            "com.example.Outer$InnerError",
+            "thread 'main' panicked at src/main.rs:2:5:",
+            "stack backtrace:",
+            "note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace",
+        ] {
+            assert!(is_continuation(text), "{}", text);
+        }
+        for text in [
+            "ERROR 14:33:45 disk full",
+            "[2024-01-15T14:30:25Z INFO  app] started",
+            "IllegalStateException: no package, so not a class name",
+            "example.com is unreachable",
+            "... and then some",
+        ] {
+            assert!(!is_continuation(text), "{}", text);
+        }
+    }
+
+    #[test]
+    fn keeps_a_java_trace_with_its_record() {
+        assert_eq!(
+            group(&[
+                "ERROR 14:33:45 request failed",
+                "java.lang.IllegalStateException: not started",
+                "    at com.example.Main.run(Main.java:12)",
+                "",
+                "Caused by: java.io.IOException: disk full",
+                "    ... 3 more",
+                "INFO 14:33:46 retrying",
+            ]),
+            vec![
+                "ERROR 14:33:45 request failed | java.lang.IllegalStateException: not started | \
+                 \x20   at com.example.Main.run(Main.java:12) | Caused by: java.io.IOException: disk full | \
+                 \x20   ... 3 more",
+                "INFO 14:33:46 retrying",
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_a_rust_panic_message_with_its_record() {
+        //the panic's message is on its own line and could be anything, including something that looks like a record
+        assert_eq!(
+            group(&[
+                "ERROR 14:33:45 worker stopped",
+                "thread 'main' panicked at src/main.rs:2:5:",
+                "ERROR index out of bounds",
+                "stack backtrace:",
+                "   0: rust_begin_unwind",
+                "WARN 14:33:46 restarting",
+            ]),
+            vec![
+                "ERROR 14:33:45 worker stopped | thread 'main' panicked at src/main.rs:2:5: | \
+                 ERROR index out of bounds | stack backtrace: |    0: rust_begin_unwind",
+                "WARN 14:33:46 restarting",
+            ]
+        );
+    }
+
+    #[test]
+    fn lines_that_are_not_records_or_part_of_one_stay_on_their_own() {
+        assert_eq!(
+            group(&[
+                "    at com.example.Main.run(Main.java:12)",
+                "INFO 14:33:45 started",
+                "just some words",
+                "    indented after an unparsed line",
+            ]),
+            vec![
+                "unparsed:     at com.example.Main.run(Main.java:12)",
+                "INFO 14:33:45 started",
+                "unparsed: just some words",
+                "unparsed:     indented after an unparsed line",
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_adding_to_a_runaway_trace() {
+        let mut lines = vec!["ERROR 14:33:45 looping".to_string()];
+        lines.extend((0..MAX_CONTINUATION_LINES + 5).map(|n| format!("    at frame{}", n)));
+
+        let mut grouper = Grouper::default();
+        for (index, text) in lines.into_iter().enumerate() {
+            assert!(grouper
+                .push(Line {
+                    number: index + 1,
+                    text,
+                    truncated: false,
+                })
+                .is_empty());
+        }
+        assert_eq!(grouper.dropped(), 5);
+        match grouper.finish() {
+            Some(Entry::Record(record)) => {
+                assert_eq!(record.continuation.len(), MAX_CONTINUATION_LINES)
+            }
+            _ => panic!("the record should still be held"),
+        }
+        assert!(grouper.finish().is_none());
+    }
+}
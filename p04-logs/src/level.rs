@@ -0,0 +1,225 @@
+//How serious a log record is, and filters like ">= WARN" or "warn,error" for picking records by it
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+///The level of a log record, from least to most serious, so levels can be compared with < and >=.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+//the names loggers use for each level, since they don't all agree
+const LEVEL_NAMES: [(&str, Level); 15] = [
+    ("trace", Level::Trace),
+    ("debug", Level::Debug),
+    ("info", Level::Info),
+    ("notice", Level::Info),
+    ("warn", Level::Warn),
+    ("warning", Level::Warn),
+    ("error", Level::Error),
+    ("err", Level::Error),
+    ("fatal", Level::Fatal),
+    ("critical", Level::Fatal),
+    ("crit", Level::Fatal),
+    ("alert", Level::Fatal),
+    ("emerg", Level::Fatal),
+    ("emergency", Level::Fatal),
+    ("panic", Level::Fatal),
+];
+
+impl Level {
+    pub const ALL: [Level; 6] = [
+        Level::Trace,
+        Level::Debug,
+        Level::Info,
+        Level::Warn,
+        Level::Error,
+        Level::Fatal,
+    ];
+
+    ///Reads a level name, ignoring case, including the other names loggers use like "warning" and "critical".
+    pub fn parse(text: &str) -> Option<Level> {
+        LEVEL_NAMES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(text.trim()))
+            .map(|(_, level)| *level)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Level::Trace => "TRACE",
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+            Level::Fatal => "FATAL",
+        }
+    }
+}
+
+//padding like "{:<5}" lines levels up in a column, which needs `pad` rather than `write!`
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+///Why text isn't a level filter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LevelFilterError {
+    Empty,
+    UnknownLevel(String),
+}
+
+impl fmt::Display for LevelFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelFilterError::Empty => write!(f, "the level filter is empty"),
+            LevelFilterError::UnknownLevel(name) => write!(
+                f,
+                "'{}' is not a level, use one of trace, debug, info, warn, error or fatal",
+                name
+            ),
+        }
+    }
+}
+
+///The levels of records to keep.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelFilter {
+    levels: BTreeSet<Level>,
+}
+
+impl LevelFilter {
+    ///Keeps records at the given level or anything more serious.
+    pub fn at_least(level: Level) -> Self {
+        LevelFilter {
+            levels: Level::ALL
+                .into_iter()
+                .filter(|other| *other >= level)
+                .collect(),
+        }
+    }
+
+    ///Reads a filter made of one or more parts separated by commas, keeping records that match any of them.
+    ///A part is a level like "error", or a level with a comparison in front like ">= warn", "> info", "<= debug",
+    ///"< warn", "= info" or "!= info".  Level names are matched ignoring case.
+    pub fn parse(text: &str) -> Result<LevelFilter, LevelFilterError> {
+        let mut levels = BTreeSet::new();
+
+        for part in text
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+        {
+            //two character operators are checked first, so ">=" isn't read as ">" followed by "=warn"
+            let (operator, name) = ["<=", ">=", "!=", "==", "<", ">", "="]
+                .iter()
+                .find_map(|operator| part.strip_prefix(operator).map(|name| (*operator, name)))
+                .unwrap_or(("=", part));
+
+            let level = Level::parse(name)
+                .ok_or_else(|| LevelFilterError::UnknownLevel(name.trim().to_string()))?;
+            levels.extend(Level::ALL.into_iter().filter(|other| match operator {
+                "<=" => *other <= level,
+                ">=" => *other >= level,
+                "!=" => *other != level,
+                "<" => *other < level,
+                ">" => *other > level,
+                _ => *other == level,
+            }));
+        }
+
+        if levels.is_empty() && text.split(',').all(|part| part.trim().is_empty()) {
+            return Err(LevelFilterError::Empty);
+        }
+        Ok(LevelFilter { levels })
+    }
+
+    ///Whether a record with this level is kept.  Records without a level never are.
+    pub fn matches(&self, level: Option<Level>) -> bool {
+        level.is_some_and(|level| self.levels.contains(&level))
+    }
+}
+
+//shown as the levels it keeps, like "WARN,ERROR,FATAL"
+impl fmt::Display for LevelFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&str> = self.levels.iter().map(Level::name).collect();
+        write!(f, "{}", names.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //the levels a filter keeps, shown the way Display shows them
+    fn keeps(filter: &str) -> String {
+        LevelFilter::parse(filter).unwrap().to_string()
+    }
+
+    #[test]
+    fn reads_level_names_and_their_other_names() {
+        assert_eq!(Level::parse("WARN"), Some(Level::Warn));
+        assert_eq!(Level::parse(" warning "), Some(Level::Warn));
+        assert_eq!(Level::parse("Err"), Some(Level::Error));
+        assert_eq!(Level::parse("notice"), Some(Level::Info));
+        assert_eq!(Level::parse("critical"), Some(Level::Fatal));
+        assert_eq!(Level::parse("verbose"), None);
+        assert_eq!(Level::parse(""), None);
+        assert!(Level::Trace < Level::Debug && Level::Error < Level::Fatal);
+        assert_eq!(format!("[{:<5}]", Level::Warn), "[WARN ]");
+    }
+
+    #[test]
+    fn reads_comparisons_and_lists() {
+        assert_eq!(keeps(">=warn"), "WARN,ERROR,FATAL");
+        assert_eq!(keeps(">= Warning"), "WARN,ERROR,FATAL");
+        assert_eq!(keeps("> info"), "WARN,ERROR,FATAL");
+        assert_eq!(keeps("<= debug"), "TRACE,DEBUG");
+        assert_eq!(keeps("<info"), "TRACE,DEBUG");
+        assert_eq!(keeps("=info"), "INFO");
+        assert_eq!(keeps("==info"), "INFO");
+        assert_eq!(keeps("!=info"), "TRACE,DEBUG,WARN,ERROR,FATAL");
+        assert_eq!(keeps("error"), "ERROR");
+        assert_eq!(keeps("warn, error,"), "WARN,ERROR");
+        assert_eq!(keeps("< debug, fatal"), "TRACE,FATAL");
+        assert_eq!(
+            LevelFilter::at_least(Level::Error).to_string(),
+            "ERROR,FATAL"
+        );
+    }
+
+    #[test]
+    fn refuses_empty_filters_and_unknown_levels() {
+        assert_eq!(LevelFilter::parse(""), Err(LevelFilterError::Empty));
+        assert_eq!(LevelFilter::parse(" , "), Err(LevelFilterError::Empty));
+        assert_eq!(
+            LevelFilter::parse(">= loud"),
+            Err(LevelFilterError::UnknownLevel("loud".to_string()))
+        );
+        assert_eq!(
+            LevelFilter::parse("warn,=>error"),
+            Err(LevelFilterError::UnknownLevel(">error".to_string()))
+        );
+        assert_eq!(
+            LevelFilterError::UnknownLevel("loud".to_string()).to_string(),
+            "'loud' is not a level, use one of trace, debug, info, warn, error or fatal"
+        );
+    }
+
+    #[test]
+    fn records_without_a_level_are_never_kept() {
+        let filter = LevelFilter::parse("!=info").unwrap();
+        assert!(filter.matches(Some(Level::Trace)));
+        assert!(!filter.matches(Some(Level::Info)));
+        assert!(!filter.matches(None));
+    }
+}
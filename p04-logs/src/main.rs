@@ -1,160 +1,726 @@
-use std::fs;
-use std::io::Error;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod filter;
+use filter::Filter;
+
+mod follow;
+use follow::{Event, Follower};
+
+mod group;
+use group::{Entry, Grouper};
+
+mod level;
+use level::LevelFilter;
+
+mod reader;
+use reader::InvalidUtf8;
+
+mod record;
+
+mod stats;
+use stats::{Bucket, Stats};
+
+mod timestamp;
+use timestamp::Timestamp;
+
+const USAGE: &str = "\
+Usage: p04-logs <command> [options] [file]...
+
+Reads each file one line at a time, or standard input if no file is given or a file is '-'.
+Gzip compressed files are decompressed as they are read.
+
+Commands:
+  extract [file]...      write the lines that match, as they were, ERROR and worse unless --level is given,
+                         along with the stack trace or other continuation lines after each one
+  parse [file]...        show every line split into its timestamp, level, target, message and fields,
+                         along with the lines that can't be parsed, every level unless --level is given
+  stats [file]...        count the records by level, by module and by time, and show the most common
+                         messages, grouped into templates with their numbers, IDs and UUIDs masked,
+                         every level unless --level is given
+  follow <file>          like extract, but keep watching the file and write matching lines as they are added,
+                         carrying on when it is rotated or truncated, until stopped with Ctrl+C
+
+Options:
+  --output <file>        write to a file instead of standard output
+  --level <levels>       the levels to keep, like 'error', '>=warn', '<info', 'warn,error', or 'all' for
+                         every line including ones without a level
+  --grep <text>          only lines with this text in them, can be given more than once to need all of them
+  --ignore-case          match --grep ignoring upper and lower case
+  --since <time>         only lines written at or after a time, like '2024-01-15T14:30:00Z', '2024-01-15 14:30'
+                         or just '14:30' to compare with the time of day
+  --until <time>         only lines written at or before a time, a date on its own includes the whole day
+  --by <minute|hour>     with stats, how long each time bucket is, an hour if not given
+  --top <count>          with stats, how many of the most common messages to show, 10 if not given
+  --json                 with stats, print the counts as JSON instead of tables
+  --from-start           with follow, read what is already in the file first instead of only new lines
+  --skip-invalid         leave out lines that aren't valid UTF-8 instead of replacing the invalid bytes with '�'
+
+Exit status:
+  0  at least one line matched
+  1  no lines matched
+  2  the command line was wrong
+  3  an input couldn't be read or the output couldn't be written
+";
+
+//how often a followed file is checked for new lines
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+//how long nothing has to be added to a followed file before the latest record is written without waiting for more of
+//its trace.  Loggers often write a trace a few lines at a time, so a single quiet poll isn't enough
+const TRACE_IDLE: Duration = Duration::from_secs(2);
+
+//options that are followed by a value, and switches like --ignore-case that aren't.  Anything else is a mistake
+const VALUE_OPTIONS: [&str; 7] = ["output", "level", "grep", "since", "until", "by", "top"];
+const SWITCHES: [&str; 5] = ["ignore-case", "from-start", "skip-invalid", "json", "help"];
+
+//options that only mean something to one command, which are refused with the others instead of being ignored
+const COMMAND_OPTIONS: [(&str, &[&str]); 4] = [
+    ("by", &["stats"]),
+    ("top", &["stats"]),
+    ("json", &["stats"]),
+    ("from-start", &["follow"]),
+];
+
+//how many of the most common messages stats shows when --top isn't given
+const DEFAULT_TOP_MESSAGES: usize = 10;
+
+///What went wrong running a command, each kind exits with its own code so scripts can tell them apart.
+enum CliError {
+    //the command line itself was wrong, the usage text is printed along with the message
+    Usage(String),
+    //everything worked but nothing matched, which isn't printed as an error, like grep
+    NoMatches,
+    Io(String),
+}
 
-fn main() -> Result<(), Error> {
-    //this returns an OK variant of the Result enum that contains a string of the text contained in logs.txt
-    let text = fs::read_to_string("logs.txt");
-    println!("{:#?}", text);
+///The command line split into positional arguments and --options.
+struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+    switches: Vec<String>,
+}
 
-    //This returns an Err variants of the Result enum and it contains an Os struct with more information
-    //such as an error code number, an error 'kind' like 'NotFound', and an error message like "the system
-    //cannot find the file specified"
-    let text_not_there = fs::read_to_string("logsNotExisting.txt");
-    println!("{:#?}", text_not_there);
+impl Args {
+    fn parse(raw: impl Iterator<Item = String>) -> Result<Args, CliError> {
+        let mut args = Args {
+            positional: vec![],
+            options: vec![],
+            switches: vec![],
+        };
+        let mut raw = raw.peekable();
+
+        while let Some(arg) = raw.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                args.positional.push(arg);
+                continue;
+            };
+
+            //both --level error and --level=error work
+            let (name, inline_value) = match name.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (name.to_string(), None),
+            };
+
+            if VALUE_OPTIONS.contains(&name.as_str()) {
+                let value = match inline_value {
+                    Some(value) => value,
+                    None => raw
+                        .next()
+                        .ok_or_else(|| CliError::Usage(format!("--{} needs a value", name)))?,
+                };
+                args.options.push((name, value));
+            } else if !SWITCHES.contains(&name.as_str()) {
+                return Err(CliError::Usage(format!("unknown option --{}", name)));
+            } else if inline_value.is_some() {
+                return Err(CliError::Usage(format!("--{} doesn't take a value", name)));
+            } else {
+                args.switches.push(name);
+            }
+        }
 
-    //I originally named the binding as 'textNotHere' and the rust-analyzer warned me that it should be
-    //written in snake case, which I appreciate
+        Ok(args)
+    }
 
-    //getting the logs.txt file and using a match statement to get the OK value into a string
+    ///Refuses options that were given to a command they don't apply to, like --json with extract.
+    fn check_options_for(&self, command: &str) -> Result<(), CliError> {
+        let given = self
+            .options
+            .iter()
+            .map(|(name, _)| name)
+            .chain(self.switches.iter());
+        for name in given {
+            if let Some((_, only_for)) = COMMAND_OPTIONS
+                .iter()
+                .find(|(option, only_for)| option == name && !only_for.contains(&command))
+            {
+                return Err(CliError::Usage(format!(
+                    "--{} only works with {}",
+                    name,
+                    only_for.join(" and ")
+                )));
+            }
+        }
+        Ok(())
+    }
 
-    // let logs_text = fs::read_to_string("logs.txt");
-    // match logs_text {
-    //     Ok(text) => println!("{}", text),
-    //     Err(error_info) => println!("{}", error_info),
-    // }
+    ///The last value given for an option, so a later --level overrides an earlier one.
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(option, _)| option == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    ///Every value given for an option that can be repeated, like --grep.
+    fn all(&self, name: &str) -> Vec<String> {
+        self.options
+            .iter()
+            .filter(|(option, _)| option == name)
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
 
+    fn switch(&self, name: &str) -> bool {
+        self.switches.iter().any(|switch| switch == name)
+    }
 
+    fn number<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>, CliError> {
+        match self.option(name) {
+            None => Ok(None),
+            Some(value) => value.parse().map(Some).map_err(|_| {
+                CliError::Usage(format!("'{}' is not a valid number for --{}", value, name))
+            }),
+        }
+    }
 
-    /*
+    fn timestamp(&self, name: &str) -> Result<Option<Timestamp>, CliError> {
+        match self.option(name) {
+            None => Ok(None),
+            Some(value) => Timestamp::parse(value).map(Some).ok_or_else(|| {
+                CliError::Usage(format!("'{}' is not a time --{} understands", value, name))
+            }),
+        }
+    }
+}
 
-    // a long way to write it out using nested match statements
+//what the lines that match are written as
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    //the lines exactly as they were in the log
+    Extract,
+    //each record split into its parts, and the lines that couldn't be parsed with the reason why
+    Parse,
+}
 
-    let mut error_logs = vec![];
+//counts kept across every input, for the notes printed at the end
+#[derive(Default)]
+struct Totals {
+    matched: usize,
+    unparsed: usize,
+    replaced: usize,
+    skipped: usize,
+    truncated: usize,
+    dropped: usize,
+    unreadable: usize,
+}
 
-    match fs::read_to_string("logs.txt") {
-        Ok(text_result) => {
-            error_logs = extract_error_logs(text_result.as_str());
+fn build_filter(args: &Args, mode: Mode) -> Result<Filter, CliError> {
+    let levels = match args.option("level") {
+        Some(levels) if levels.trim().eq_ignore_ascii_case("all") => None,
+        Some(levels) => {
+            Some(LevelFilter::parse(levels).map_err(|error| CliError::Usage(error.to_string()))?)
+        }
+        None if mode == Mode::Parse => None,
+        None => Filter::default().levels,
+    };
+
+    //a date on its own means the end of that day for --until, so "--until 2024-01-15" includes all of the 15th
+    let until = args.timestamp("until")?.map(|until| {
+        let date_only = args
+            .option("until")
+            .is_some_and(|value| !value.contains(':'));
+        if date_only {
+            until.end_of_day()
+        } else {
+            until
+        }
+    });
+
+    Ok(Filter {
+        levels,
+        patterns: args.all("grep"),
+        ignore_case: args.switch("ignore-case"),
+        since: args.timestamp("since")?,
+        until,
+    })
+}
 
-            match fs::write("error_logs.txt", error_logs.join("\n")) {
-                // Ok(..) and Ok(_) are almost interchangeable here, but the Ok(_) is said to more intentionally communicate that the empty tuple value being received here is being ignored
-                // Ok(_) => println!("Wrote error_logs.txt"),
-                Ok(..) => println!("Wrote error_logs.txt"),
-                Err(error) => println!("{:#?}", error),
+fn write_if_matching(
+    entry: &Entry,
+    filter: &Filter,
+    mode: Mode,
+    output: &mut dyn Write,
+    totals: &mut Totals,
+) -> io::Result<()> {
+    match entry {
+        Entry::Record(record) if filter.matches(record) => {
+            totals.matched += 1;
+            match mode {
+                Mode::Extract => writeln!(output, "{}", record.text)?,
+                Mode::Parse => writeln!(output, "{:>5}  {}", record.line, record)?,
+            }
+            //continuation lines are written as they were, indented under the record when it is shown parsed
+            for line in &record.continuation {
+                match mode {
+                    Mode::Extract => writeln!(output, "{}", line)?,
+                    Mode::Parse => writeln!(output, "       {}", line)?,
+                }
             }
+            Ok(())
         }
-        Err(what_went_wrong) => {
-            println!("{:#?}", what_went_wrong)
+        Entry::Record(_) => Ok(()),
+        Entry::Unparsed(line, error) => {
+            totals.unparsed += 1;
+            if !filter.matches_unparsed(&line.text) {
+                return Ok(());
+            }
+            totals.matched += 1;
+            match mode {
+                Mode::Extract => writeln!(output, "{}", line.text),
+                Mode::Parse => writeln!(output, "Could not parse {}", error),
+            }
         }
     }
+}
 
-    */
-
-
-    // a quicker way to retrieve the values in the Result::Ok() enum using .expect()
-    // but this doesn't have as robust of error handling and will be more prone to panics
-
-    //this however now lacks a way to println! that the file was read or written successfully
-
-    let text = fs::read_to_string("logs.txt")
-        .expect("Could not read file, program crashing");
-
-    let error_logs = extract_error_logs(text.as_str());
-
-    fs::write("error_logs.txt", error_logs.join("\n"))
-        .expect("Failed to write error_logs.txt, program crashing");
+//reads one input, handing each record or unparsed line to `handle` as soon as its continuation lines are known.  An
+//input that can't be read is reported and the others are still read, like grep and cat do
+fn process(
+    path: &str,
+    invalid_utf8: InvalidUtf8,
+    totals: &mut Totals,
+    handle: &mut dyn FnMut(&Entry, &mut Totals) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut lines = match reader::open(path) {
+        Ok(lines) => lines.invalid_utf8(invalid_utf8),
+        Err(error) => {
+            eprintln!("error: could not read {}: {}", path, error);
+            totals.unreadable += 1;
+            return Ok(());
+        }
+    };
+
+    let mut grouper = Grouper::default();
+    for line in lines.by_ref() {
+        let line = match line {
+            Ok(line) => line,
+            Err(error) => {
+                eprintln!("error: could not read {}: {}", path, error);
+                totals.unreadable += 1;
+                break;
+            }
+        };
+        for entry in grouper.push(line) {
+            handle(&entry, totals)?;
+        }
+    }
+    if let Some(entry) = grouper.finish() {
+        handle(&entry, totals)?;
+    }
 
+    totals.replaced += lines.replaced();
+    totals.skipped += lines.skipped();
+    totals.truncated += lines.truncated();
+    totals.dropped += grouper.dropped();
+    Ok(())
+}
 
+//adds the entry to the stats if the filter keeps it
+fn count_if_matching(entry: &Entry, filter: &Filter, stats: &mut Stats, totals: &mut Totals) {
+    match entry {
+        Entry::Record(record) if filter.matches(record) => {
+            totals.matched += 1;
+            stats.add(record);
+        }
+        Entry::Record(_) => {}
+        Entry::Unparsed(line, _) => {
+            totals.unparsed += 1;
+            if filter.matches_unparsed(&line.text) {
+                totals.matched += 1;
+                stats.add_unparsed();
+            }
+        }
+    }
+}
 
+///Writes the lines that match as they are added to the file, until the program is stopped.  The output is flushed
+///after every poll, so lines show up as soon as they are found even when writing to a file.
+fn follow(
+    path: &str,
+    filter: &Filter,
+    invalid_utf8: InvalidUtf8,
+    from_start: bool,
+    output: &mut dyn Write,
+) -> Result<(), CliError> {
+    let read_error = |error: io::Error| CliError::Io(format!("could not read {}: {}", path, error));
+    let write_error =
+        |error: io::Error| CliError::Io(format!("could not write the output: {}", error));
+
+    let mut follower = Follower::new(path, from_start)
+        .map_err(read_error)?
+        .invalid_utf8(invalid_utf8);
+    if follower.is_missing() {
+        eprintln!("note: {} doesn't exist yet, waiting for it", path);
+    }
 
-    //doing this same thing but with a Try operator instead
+    let mut totals = Totals::default();
+    let mut grouper = Grouper::default();
+    let mut last_line = Instant::now();
+    loop {
+        let events = follower.poll().map_err(read_error)?;
+        if events.iter().any(|event| matches!(event, Event::Line(_))) {
+            last_line = Instant::now();
+        }
 
+        for event in events {
+            match event {
+                Event::Line(line) => {
+                    for entry in grouper.push(line) {
+                        write_if_matching(&entry, filter, Mode::Extract, output, &mut totals)
+                            .map_err(write_error)?;
+                    }
+                }
+                event => {
+                    //a trace never carries on into a different file, or past the start of a truncated one
+                    if let Some(entry) = grouper.finish() {
+                        write_if_matching(&entry, filter, Mode::Extract, output, &mut totals)
+                            .map_err(write_error)?;
+                    }
+                    match event {
+                        Event::Truncated => {
+                            eprintln!("note: {} was truncated, following it from the start", path)
+                        }
+                        Event::Rotated => eprintln!(
+                            "note: {} was replaced by a new file, following the new one",
+                            path
+                        ),
+                        Event::Missing => {
+                            eprintln!("note: {} has gone, waiting for a new one", path)
+                        }
+                        _ => eprintln!("note: {} has appeared, following it", path),
+                    }
+                }
+            }
+        }
+        //once nothing more has been written for a while, the latest record's trace is taken to be complete
+        if last_line.elapsed() >= TRACE_IDLE {
+            if let Some(entry) = grouper.finish() {
+                write_if_matching(&entry, filter, Mode::Extract, output, &mut totals)
+                    .map_err(write_error)?;
+            }
+        }
+        output.flush().map_err(write_error)?;
+        //a poll that stopped at its line limit is carried on straight away rather than after the usual wait
+        if !follower.is_behind() {
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
 
-    //using the Try operator here will either assign a string to text_try if it succeeds,
-    //or if it fails, it will provide an Error type, but in order for this to work, the calling
-    //function of main() also needs to return a Result of Ok or Error, and the error will be printed to console 
-    let text_try = fs::read_to_string("logs.txt")?;
+fn run(args: &Args) -> Result<(), CliError> {
+    if args.switch("help") {
+        print!("{}", USAGE);
+        return Ok(());
+    }
 
-    let error_logs_try = extract_error_logs(text_try.as_str());
+    let (mode, files) = match args.positional.split_first() {
+        Some((command, files)) if command == "extract" || command == "follow" => {
+            (Mode::Extract, files)
+        }
+        Some((command, files)) if command == "parse" || command == "stats" => (Mode::Parse, files),
+        Some((command, _)) => {
+            return Err(CliError::Usage(format!("unknown command '{}'", command)))
+        }
+        None => return Err(CliError::Usage(String::from("no command given"))),
+    };
+    args.check_options_for(&args.positional[0])?;
+    let filter = build_filter(args, mode)?;
+    let invalid_utf8 = if args.switch("skip-invalid") {
+        InvalidUtf8::Skip
+    } else {
+        InvalidUtf8::Replace
+    };
+
+    let mut output: Box<dyn Write> = match args.option("output") {
+        Some(path) => Box::new(BufWriter::new(File::create(path).map_err(|error| {
+            CliError::Io(format!("could not write {}: {}", path, error))
+        })?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    if args.positional[0] == "follow" {
+        let [path] = files else {
+            return Err(CliError::Usage(String::from(
+                "follow needs exactly one file",
+            )));
+        };
+        return follow(
+            path,
+            &filter,
+            invalid_utf8,
+            args.switch("from-start"),
+            &mut output,
+        );
+    }
 
-    //adding a Try operator here will enable a brief way to get the value unwrapped in case of Ok,
-    //and if Err, it will propagate an error message up to main() which will print the error to console
-    //but for this to work, main() needs to have -> Result<(), Error>
-    //putting an invalid path here results in an error being printed, but the program also panics and ends
-    fs::write("error_logs_try.txt", error_logs_try.join("\n"))?;
+    let stdin = [String::from("-")];
+    let files = if files.is_empty() { &stdin[..] } else { files };
+
+    let write_error =
+        |error: io::Error| CliError::Io(format!("could not write the output: {}", error));
+    let mut totals = Totals::default();
+    if args.positional[0] == "stats" {
+        let bucket = match args.option("by") {
+            None => Bucket::Hour,
+            Some(by) => Bucket::parse(by).ok_or_else(|| {
+                CliError::Usage(format!("'{}' is not a time bucket, use minute or hour", by))
+            })?,
+        };
+        let top = args.number("top")?.unwrap_or(DEFAULT_TOP_MESSAGES);
+
+        let mut stats = Stats::new(bucket);
+        for path in files {
+            process(path, invalid_utf8, &mut totals, &mut |entry, totals| {
+                count_if_matching(entry, &filter, &mut stats, totals);
+                Ok(())
+            })
+            .map_err(write_error)?;
+        }
 
-    // FOR THESE TRY OPERATORS TO WORK, IT ALSO NEEDS A FINAL Ok(()) AT THE BOTTOM OF main() 
+        let report = stats.report(top);
+        if args.switch("json") {
+            let json = serde_json::to_string_pretty(&report)
+                .expect("a report should always serialize to JSON");
+            writeln!(output, "{}", json).map_err(write_error)?;
+        } else {
+            write!(output, "{}", report.to_text()).map_err(write_error)?;
+        }
+    } else {
+        for path in files {
+            process(path, invalid_utf8, &mut totals, &mut |entry, totals| {
+                write_if_matching(entry, &filter, mode, &mut output, totals)
+            })
+            .map_err(write_error)?;
+        }
+    }
+    output.flush().map_err(write_error)?;
+
+    //so it is clear when what was read isn't exactly what is in the files
+    let notes = [
+        (
+            totals.replaced,
+            "had invalid UTF-8 replaced with '\u{fffd}'",
+        ),
+        (totals.skipped, "were skipped for having invalid UTF-8"),
+        (totals.truncated, "were too long and were cut short"),
+        (
+            totals.dropped,
+            "of stack traces were left out for being past the first 1000 lines of one",
+        ),
+        (
+            if mode == Mode::Extract && filter.levels.is_some() {
+                totals.unparsed
+            } else {
+                0
+            },
+            "couldn't be parsed and were left out",
+        ),
+    ];
+    for (count, note) in notes.iter().filter(|(count, _)| *count > 0) {
+        eprintln!("note: {} line(s) {}", count, note);
+    }
 
+    if totals.unreadable > 0 {
+        Err(CliError::Io(format!(
+            "{} of {} inputs could not be read to the end",
+            totals.unreadable,
+            files.len()
+        )))
+    } else if totals.matched == 0 {
+        Err(CliError::NoMatches)
+    } else {
+        Ok(())
+    }
+}
 
+fn main() -> ExitCode {
+    let result = Args::parse(env::args().skip(1)).and_then(|args| run(&args));
 
-    /*
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(CliError::NoMatches) => ExitCode::from(1),
+        Err(CliError::Usage(message)) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            ExitCode::from(2)
+        }
+        Err(CliError::Io(message)) => {
+            eprintln!("error: {}", message);
+            ExitCode::from(3)
+        }
+    }
+}
 
-    //another way of doing file write with an error handling test on it using the if let construct
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::parse_line;
 
-    println!("{:#?}", error_logs);
+    fn parse_args(raw: &[&str]) -> Result<Args, CliError> {
+        Args::parse(raw.iter().map(|arg| arg.to_string()))
+    }
 
-    //write error logs to an error log file
-    //this one successfully writes the error_logs.txt file
-    //but this doesn't give off any indication that it succeeded because it only checks for the Err outcome
-    if let Err(e) = fs::write("error_logs.txt", error_logs.join("\n")) {
-        println!("Error writing file: {:#?}", e)
+    fn filter(raw: &[&str]) -> Filter {
+        let Ok(args) = parse_args(raw) else {
+            panic!("{:?} should parse", raw);
+        };
+        let Ok(filter) = build_filter(&args, Mode::Parse) else {
+            panic!("{:?} should build a filter", raw);
+        };
+        filter
     }
 
-    //this one intentionally picks a non-existing directory so that the Err will fire to see its output
-    if let Err(e) = fs::write("./nodirectory/error_logs.txt", error_logs.join("\n")) {
-        println!("Error writing file: {:#?}", e)
+    //the message of the usage error the command line gives, whether it is refused by Args::parse or by run
+    fn usage_error(raw: &[&str]) -> String {
+        match parse_args(raw).and_then(|args| run(&args)) {
+            Err(CliError::Usage(message)) => message,
+            Err(CliError::Io(message)) => panic!("{:?} failed to run: {}", raw, message),
+            _ => panic!("{:?} should be a usage error", raw),
+        }
     }
 
-    */
+    //a log file in a temporary directory, which is removed when the returned TempDir is dropped
+    fn temp_log(text: &str) -> (tempfile::TempDir, String, String) {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("app.log");
+        std::fs::write(&log, text).unwrap();
+        let out = dir.path().join("out.txt").to_str().unwrap().to_string();
+        (dir, log.to_str().unwrap().to_string(), out)
+    }
 
-    /*
+    fn keeps(filter: &Filter, line: &str) -> bool {
+        filter.matches(&parse_line(1, line).unwrap())
+    }
 
-    //Demonstration of the Result Enum
+    #[test]
+    fn until_with_only_a_date_includes_the_whole_day() {
+        let date_only = filter(&["parse", "--until", "2024-01-15"]);
+        assert!(keeps(&date_only, "2024-01-15T23:59:59.999Z INFO last"));
+        assert!(!keeps(&date_only, "2024-01-16T00:00:00Z INFO next day"));
 
-    // println!("{:#?}", divide(10.0, 0.0));
+        //with an offset the line is compared in UTC, so 01:00 on the 16th at +02:00 is still the 15th
+        assert!(keeps(&date_only, "2024-01-16T01:00:00+02:00 INFO east"));
 
-    match divide(3.5, 10.0) {
-        Ok(value) => println!("{:#?}", value),
-        Err(what_went_wrong) => println!("{:#?}", what_went_wrong),
+        let with_time = filter(&["parse", "--until=2024-01-15 14:30"]);
+        assert!(keeps(&with_time, "2024-01-15 14:30:00 INFO exactly"));
+        assert!(!keeps(&with_time, "2024-01-15 14:30:01 INFO after"));
     }
 
-    match divide(3.5, 0.0) {
-        Ok(value) => println!("{:#?}", value),
-        Err(what_went_wrong) => println!("{:#?}", what_went_wrong),
+    #[test]
+    fn unknown_options_are_refused() {
+        for (raw, expected) in [
+            (
+                &["extract", "--levle", "error"][..],
+                "unknown option --levle",
+            ),
+            (&["stats", "--jsn"][..], "unknown option --jsn"),
+            (&["parse", "--levle=error"][..], "unknown option --levle"),
+            (&["extract", "--"][..], "unknown option --"),
+            (
+                &["extract", "--json=yes"][..],
+                "--json doesn't take a value",
+            ),
+            (&["extract", "--level"][..], "--level needs a value"),
+        ] {
+            assert_eq!(usage_error(raw), expected, "{:?}", raw);
+        }
     }
 
-    //this function can be defined below where it is invoked, so it seems to have bootstrapping in Rust
-
-    //functions can also be defined either within the main() or outside the main() function and be called from main()
-
-    //this function returns a Result with an Ok() that wraps an f64, or else an Error that wraps a message
-    fn divide(a: f64, b: f64) -> Result<f64, Error> {
-        if b == 0.0 {
-            //uh oh division by 0
-            Err(Error::other("Can't divide by zero"))
-        } else {
-            Ok(a / b)
+    #[test]
+    fn options_for_other_commands_are_refused() {
+        for (raw, expected) in [
+            (&["extract", "--json"][..], "--json only works with stats"),
+            (
+                &["parse", "--by", "minute"][..],
+                "--by only works with stats",
+            ),
+            (
+                &["follow", "--top=5", "app.log"][..],
+                "--top only works with stats",
+            ),
+            (
+                &["extract", "--from-start"][..],
+                "--from-start only works with follow",
+            ),
+            (&["search"][..], "unknown command 'search'"),
+            (&[][..], "no command given"),
+            (
+                &["stats", "--by", "day"][..],
+                "'day' is not a time bucket, use minute or hour",
+            ),
+            (
+                &["stats", "--top", "many"][..],
+                "'many' is not a valid number for --top",
+            ),
+            (
+                &["parse", "--since", "soon"][..],
+                "'soon' is not a time --since understands",
+            ),
+            (&["follow"][..], "follow needs exactly one file"),
+        ] {
+            assert_eq!(usage_error(raw), expected, "{:?}", raw);
         }
     }
 
-    */
-
-    //this needs to be here as the final return value of main() in order to allow using the Try operator above here
-    Ok(())
-
-}
-
-/// Takes a string of logs text, breaks it by newline separator, extracts any that starts with "ERROR",
-/// returns a Vector of Strings of lines that meet that criterion
-fn extract_error_logs(text_to_parse: &str) -> Vec<String> {
-    let lines = text_to_parse.split("\n");
-
-    let mut error_lines = vec![];
-
-    for line in lines {
-        if line.starts_with("ERROR") {
-            error_lines.push(line.to_string());
-        }
+    #[test]
+    fn extract_writes_matching_records_with_their_traces() {
+        let (_dir, log, out) = temp_log(
+            "2024-01-15 14:30:25 INFO started\n\
+             2024-01-15 14:30:26 ERROR failed to connect\n\
+             \tat Db.connect(Db.java:10)\n\
+             2024-01-15 14:30:27 WARN slow\n",
+        );
+
+        let args = parse_args(&["extract", &log, "--output", &out])
+            .ok()
+            .unwrap();
+        assert!(run(&args).is_ok());
+        assert_eq!(
+            std::fs::read_to_string(&out).unwrap(),
+            "2024-01-15 14:30:26 ERROR failed to connect\n\tat Db.connect(Db.java:10)\n"
+        );
+
+        let args = parse_args(&["extract", "--grep", "nothing", &log, "--output", &out])
+            .ok()
+            .unwrap();
+        assert!(matches!(run(&args), Err(CliError::NoMatches)));
     }
 
-    error_lines
+    #[test]
+    fn stats_can_be_written_as_json() {
+        let (_dir, log, out) =
+            temp_log("2024-01-15 14:30:25 INFO took 3ms\n2024-01-15 14:31:25 INFO took 45ms\n");
+
+        let args = parse_args(&["stats", "--json", "--by=minute", &log, "--output", &out])
+            .ok()
+            .unwrap();
+        assert!(run(&args).is_ok());
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&out).unwrap()).unwrap();
+        assert!(json.is_object(), "{}", json);
+    }
 }
@@ -0,0 +1,321 @@
+//Reads logs one line at a time, so a log of any size can be processed without holding all of it in memory
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, ErrorKind};
+
+use flate2::bufread::MultiGzDecoder;
+
+///Lines longer than this are cut short, so one enormous line (like a whole file without any newlines) can't use up
+///all the memory.
+pub const MAX_LINE_BYTES: usize = 1024 * 1024;
+
+//gzip files start with these two bytes, which is how compressed input is recognized whatever the file is named
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+///What to do with lines that aren't valid UTF-8, which happens when a program writes binary data or text in
+///another encoding to its log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InvalidUtf8 {
+    //keep the line, with the invalid bytes replaced by '�'
+    #[default]
+    Replace,
+    Skip,
+}
+
+///One line of input, without its line ending.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    //line numbers start at 1 and count every line, including skipped ones, so they match the file
+    pub number: usize,
+    pub text: String,
+    //whether the line was longer than the reader's limit, so only the start of it is here
+    pub truncated: bool,
+}
+
+///The text of a line, and whether it had to be changed to be valid UTF-8.
+pub enum Decoded {
+    Valid(String),
+    Replaced(String),
+    Skipped,
+}
+
+///Turns the bytes of one line into text, taking off a '\r' left by a Windows line ending.  `truncated` is whether the
+///line was cut short, which can split its last character in half.
+pub fn decode_line(bytes: &mut Vec<u8>, truncated: bool, invalid_utf8: InvalidUtf8) -> Decoded {
+    //Windows line endings leave a '\r' before the '\n'
+    if !truncated && bytes.last() == Some(&b'\r') {
+        bytes.pop();
+    }
+    //cutting a line short can split a character in half, which shouldn't make the line count as invalid
+    if truncated {
+        if let Err(error) = std::str::from_utf8(bytes) {
+            if error.error_len().is_none() {
+                bytes.truncate(error.valid_up_to());
+            }
+        }
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Decoded::Valid(text.to_string()),
+        Err(_) if invalid_utf8 == InvalidUtf8::Skip => Decoded::Skipped,
+        Err(_) => Decoded::Replaced(String::from_utf8_lossy(bytes).into_owned()),
+    }
+}
+
+///Reads lines from any buffered input, reusing one buffer for every line.
+pub struct LineReader<R> {
+    input: R,
+    buffer: Vec<u8>,
+    number: usize,
+    invalid_utf8: InvalidUtf8,
+    max_line_bytes: usize,
+    replaced: usize,
+    skipped: usize,
+    truncated: usize,
+}
+
+///Opens a log file for reading, or standard input if the path is "-".  Gzip compressed input is decompressed as
+///it is read, including files made of several gzip streams one after another, like logrotate can make.
+pub fn open(path: &str) -> io::Result<LineReader<Box<dyn BufRead>>> {
+    let input: Box<dyn BufRead> = if path == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(path)?))
+    };
+    Ok(LineReader::new(decompress(input)?))
+}
+
+///Wraps the input in a gzip decoder if it starts like a gzip file, and otherwise returns it as it is.
+pub fn decompress(mut input: Box<dyn BufRead>) -> io::Result<Box<dyn BufRead>> {
+    //only looks at what is already buffered, so nothing is taken out of the input
+    if input.fill_buf()?.starts_with(&GZIP_MAGIC) {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(input))))
+    } else {
+        Ok(input)
+    }
+}
+
+impl<R: BufRead> LineReader<R> {
+    pub fn new(input: R) -> Self {
+        LineReader {
+            input,
+            buffer: vec![],
+            number: 0,
+            invalid_utf8: InvalidUtf8::default(),
+            max_line_bytes: MAX_LINE_BYTES,
+            replaced: 0,
+            skipped: 0,
+            truncated: 0,
+        }
+    }
+
+    pub fn invalid_utf8(mut self, invalid_utf8: InvalidUtf8) -> Self {
+        self.invalid_utf8 = invalid_utf8;
+        self
+    }
+
+    ///The number of lines so far that weren't valid UTF-8 and had the invalid bytes replaced.
+    pub fn replaced(&self) -> usize {
+        self.replaced
+    }
+
+    ///The number of lines so far that weren't valid UTF-8 and were skipped.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    ///The number of lines so far that were too long and were cut short.
+    pub fn truncated(&self) -> usize {
+        self.truncated
+    }
+
+    //reads the next line into the buffer without its newline, returning None at the end of the input and otherwise
+    //whether the line had to be cut short
+    fn read_line(&mut self) -> io::Result<Option<bool>> {
+        self.buffer.clear();
+        let mut read_any = false;
+        let mut truncated = false;
+
+        loop {
+            let available = match self.input.fill_buf() {
+                Ok(available) => available,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            };
+            if available.is_empty() {
+                return Ok(read_any.then_some(truncated));
+            }
+            read_any = true;
+
+            let newline = available.iter().position(|byte| *byte == b'\n');
+            let line_part = &available[..newline.unwrap_or(available.len())];
+
+            //the rest of a line past the limit is still read, so the next line starts in the right place, but not kept
+            let room = self.max_line_bytes.saturating_sub(self.buffer.len());
+            if line_part.len() > room {
+                truncated = true;
+            }
+            self.buffer
+                .extend_from_slice(&line_part[..line_part.len().min(room)]);
+
+            let used = newline
+                .map(|newline| newline + 1)
+                .unwrap_or(available.len());
+            self.input.consume(used);
+            if newline.is_some() {
+                return Ok(Some(truncated));
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for LineReader<R> {
+    type Item = io::Result<Line>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let truncated = match self.read_line() {
+                Ok(Some(truncated)) => truncated,
+                Ok(None) => return None,
+                Err(error) => return Some(Err(error)),
+            };
+            self.number += 1;
+
+            if truncated {
+                self.truncated += 1;
+            }
+            let text = match decode_line(&mut self.buffer, truncated, self.invalid_utf8) {
+                Decoded::Valid(text) => text,
+                Decoded::Replaced(text) => {
+                    self.replaced += 1;
+                    text
+                }
+                Decoded::Skipped => {
+                    self.skipped += 1;
+                    continue;
+                }
+            };
+
+            return Some(Ok(Line {
+                number: self.number,
+                text,
+                truncated,
+            }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::{Cursor, Write};
+
+    //the line number and text of every line read, with "+" after lines that were cut short
+    fn read(
+        bytes: &[u8],
+        reader: impl FnOnce(LineReader<Cursor<Vec<u8>>>) -> LineReader<Cursor<Vec<u8>>>,
+    ) -> Vec<String> {
+        reader(LineReader::new(Cursor::new(bytes.to_vec())))
+            .map(|line| {
+                let line = line.unwrap();
+                format!(
+                    "{}: {}{}",
+                    line.number,
+                    line.text,
+                    if line.truncated { "+" } else { "" }
+                )
+            })
+            .collect()
+    }
+
+    fn gzip(text: &str) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn reads_lines_with_any_line_ending() {
+        assert_eq!(
+            read(b"one\r\ntwo\n\nlast without a newline", |reader| reader),
+            vec!["1: one", "2: two", "3: ", "4: last without a newline"]
+        );
+        assert!(read(b"", |reader| reader).is_empty());
+        //a '\r' that isn't right before the newline is part of the line
+        assert_eq!(read(b"a\rb\n", |reader| reader), vec!["1: a\rb"]);
+    }
+
+    #[test]
+    fn replaces_or_skips_invalid_utf8() {
+        let bytes = b"good\nbad \xff\xfe line\nalso good\n";
+        let mut reader = LineReader::new(Cursor::new(bytes.to_vec()));
+        let lines: Vec<String> = reader.by_ref().map(|line| line.unwrap().text).collect();
+        assert_eq!(
+            lines,
+            vec!["good", "bad \u{fffd}\u{fffd} line", "also good"]
+        );
+        assert_eq!((reader.replaced(), reader.skipped()), (1, 0));
+
+        //skipped lines still count, so the numbers match the file
+        let skip = |reader: LineReader<_>| reader.invalid_utf8(InvalidUtf8::Skip);
+        assert_eq!(read(bytes, skip), vec!["1: good", "3: also good"]);
+        let mut reader = skip(LineReader::new(Cursor::new(bytes.to_vec())));
+        assert_eq!(reader.by_ref().count(), 2);
+        assert_eq!((reader.replaced(), reader.skipped()), (0, 1));
+    }
+
+    #[test]
+    fn cuts_long_lines_short_and_carries_on_after_them() {
+        let short = |mut reader: LineReader<_>| {
+            reader.max_line_bytes = 4;
+            reader
+        };
+        assert_eq!(
+            read(b"abcdefgh\nabcd\nxy\n", short),
+            vec!["1: abcd+", "2: abcd", "3: xy"]
+        );
+        //"é" is two bytes, and cutting between them leaves the line valid rather than replacing half a character
+        assert_eq!(read("abcé\n".as_bytes(), short), vec!["1: abc+"]);
+
+        let mut reader = short(LineReader::new(Cursor::new(b"abcdefgh\nabcdefgh".to_vec())));
+        assert_eq!(reader.by_ref().count(), 2);
+        assert_eq!(reader.truncated(), 2);
+    }
+
+    #[test]
+    fn decompresses_gzip_input_made_of_several_streams() {
+        let mut bytes = gzip("first\nsecond\n");
+        bytes.extend(gzip("third\n"));
+        let input = decompress(Box::new(Cursor::new(bytes))).unwrap();
+        let lines: Vec<String> = LineReader::new(input)
+            .map(|line| line.unwrap().text)
+            .collect();
+        assert_eq!(lines, vec!["first", "second", "third"]);
+
+        let input = decompress(Box::new(Cursor::new(b"plain\n".to_vec()))).unwrap();
+        let lines: Vec<String> = LineReader::new(input)
+            .map(|line| line.unwrap().text)
+            .collect();
+        assert_eq!(lines, vec!["plain"]);
+    }
+
+    #[test]
+    fn opens_files_and_reports_missing_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log.gz");
+        std::fs::write(&path, gzip("zipped\n")).unwrap();
+
+        let lines: Vec<String> = open(path.to_str().unwrap())
+            .unwrap()
+            .map(|line| line.unwrap().text)
+            .collect();
+        assert_eq!(lines, vec!["zipped"]);
+        let missing = open(dir.path().join("missing.log").to_str().unwrap());
+        assert_eq!(
+            missing.err().map(|error| error.kind()),
+            Some(ErrorKind::NotFound)
+        );
+    }
+}
@@ -0,0 +1,875 @@
+//Turns log lines into LogRecords, whichever of the common formats they were written in
+
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+use crate::level::Level;
+use crate::timestamp::{self, Timestamp};
+
+//the fields that JSON and logfmt loggers put the parts of a record in, under the names each logger uses
+const TIMESTAMP_KEYS: [&str; 5] = ["timestamp", "time", "ts", "@timestamp", "datetime"];
+const LEVEL_KEYS: [&str; 4] = ["level", "lvl", "severity", "levelname"];
+const TARGET_KEYS: [&str; 4] = ["target", "module", "logger", "logger_name"];
+const MESSAGE_KEYS: [&str; 3] = ["message", "msg", "@message"];
+
+///Which format a line was written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    //one JSON object per line, like tracing-subscriber's or bunyan's output
+    Json,
+    //RFC 5424 or the older RFC 3164 that most syslog daemons still write
+    Syslog,
+    //"[2024-01-15T14:30:25Z INFO  my_crate::db] message"
+    EnvLogger,
+    //time=... level=info msg="message" key=value
+    Logfmt,
+    //a level and message, with the timestamp and [target] before or after the level, like "ERROR 14:33:45 message"
+    Plain,
+}
+
+///One line of a log, split into its parts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogRecord {
+    //line numbers start at 1, like in an editor
+    pub line: usize,
+    pub format: Format,
+    pub timestamp: Option<Timestamp>,
+    pub level: Option<Level>,
+    //the module, logger or program that wrote the line
+    pub target: Option<String>,
+    pub message: String,
+    //key=value pairs, in the order they were written except in JSON lines, whose keys come out sorted
+    pub fields: Vec<(String, String)>,
+    //the line as it was in the log, so it can be written out unchanged
+    pub text: String,
+    //the lines after it that are part of the same record, like a stack trace, also as they were in the log
+    pub continuation: Vec<String>,
+}
+
+///A line that isn't in any format the parser knows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+//every record is shown the same way whatever format it was read from, with the parts it doesn't have left out
+impl fmt::Display for LogRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = vec![];
+        if let Some(timestamp) = &self.timestamp {
+            parts.push(timestamp.to_string());
+        }
+        if let Some(level) = self.level {
+            parts.push(format!("{:<5}", level));
+        }
+        if let Some(target) = &self.target {
+            parts.push(format!("[{}]", target));
+        }
+        if !self.message.is_empty() {
+            parts.push(self.message.clone());
+        }
+        //fields that were written in the message are already shown as part of it
+        for (key, value) in self
+            .fields
+            .iter()
+            .filter(|(key, _)| !self.message.contains(&format!("{}=", key)))
+        {
+            parts.push(format!("{}={}", key, quote(value)));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+///Parses one line of a log, working out which format it is in from how it starts.
+pub fn parse_line(line: usize, text: &str) -> Result<LogRecord, ParseError> {
+    let error = |reason: &str| ParseError {
+        line,
+        reason: reason.to_string(),
+    };
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return Err(error("the line is blank"));
+    }
+
+    let mut record = LogRecord {
+        line,
+        format: Format::Plain,
+        timestamp: None,
+        level: None,
+        target: None,
+        message: String::new(),
+        fields: vec![],
+        text: text.to_string(),
+        continuation: vec![],
+    };
+
+    let parsed = if trimmed.starts_with('{') {
+        parse_json(&mut record, trimmed)
+    } else if let Some(parsed) = parse_syslog(&mut record, trimmed) {
+        parsed
+    } else if parse_env_logger(&mut record, trimmed) {
+        Ok(())
+    } else if let Some(parsed) = parse_logfmt(&mut record, trimmed) {
+        parsed
+    } else {
+        parse_plain(&mut record, trimmed)
+    };
+
+    parsed.map(|_| record).map_err(|reason| error(&reason))
+}
+
+fn parse_json(record: &mut LogRecord, text: &str) -> Result<(), String> {
+    record.format = Format::Json;
+    let value: Value =
+        serde_json::from_str(text).map_err(|error| format!("invalid JSON: {}", error))?;
+    let Value::Object(mut object) = value else {
+        return Err(String::from("a JSON log line has to be an object"));
+    };
+
+    //tracing-subscriber puts the message and the other fields in a "fields" object of their own
+    if let Some(Value::Object(fields)) = object.remove("fields") {
+        for (key, value) in fields {
+            object.entry(key).or_insert(value);
+        }
+    }
+
+    if let Some(value) = take_any(&mut object, &TIMESTAMP_KEYS) {
+        record.timestamp = Some(
+            json_timestamp(&value)
+                .ok_or_else(|| format!("'{}' is not a timestamp", json_text(&value)))?,
+        );
+    }
+    if let Some(value) = take_any(&mut object, &LEVEL_KEYS) {
+        record.level = Some(
+            json_level(&value)
+                .ok_or_else(|| format!("'{}' is not a log level", json_text(&value)))?,
+        );
+    }
+    record.target = take_any(&mut object, &TARGET_KEYS).map(|value| json_text(&value));
+    record.message = take_any(&mut object, &MESSAGE_KEYS)
+        .map(|value| json_text(&value))
+        .unwrap_or_default();
+    record.fields = object
+        .into_iter()
+        .map(|(key, value)| (key, json_text(&value)))
+        .collect();
+    Ok(())
+}
+
+//removes the first of the keys the object has, so that what is left over becomes the record's fields
+fn take_any(object: &mut Map<String, Value>, keys: &[&str]) -> Option<Value> {
+    keys.iter().find_map(|key| object.remove(*key))
+}
+
+//strings without their quotes, anything else as JSON
+fn json_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        _ => value.to_string(),
+    }
+}
+
+fn json_timestamp(value: &Value) -> Option<Timestamp> {
+    match value {
+        Value::String(text) => Timestamp::parse(text),
+        //seconds since 1970, or milliseconds for numbers too big to be seconds
+        Value::Number(number) => match number.as_i64() {
+            Some(millis) if millis > 100_000_000_000 => Timestamp::from_unix_millis(millis),
+            _ => Timestamp::from_unix(number.as_f64()?),
+        },
+        _ => None,
+    }
+}
+
+fn json_level(value: &Value) -> Option<Level> {
+    match value {
+        Value::String(text) => Level::parse(text),
+        //bunyan and pino write levels as numbers, 10 for trace up to 60 for fatal
+        Value::Number(number) => match number.as_u64()? {
+            10 => Some(Level::Trace),
+            20 => Some(Level::Debug),
+            30 => Some(Level::Info),
+            40 => Some(Level::Warn),
+            50 => Some(Level::Error),
+            60 => Some(Level::Fatal),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+//returns None when the line isn't syslog at all, so another format can be tried
+fn parse_syslog(record: &mut LogRecord, text: &str) -> Option<Result<(), String>> {
+    //the priority is the facility times 8 plus the severity, like <34> for an auth message at severity 2
+    let (priority, rest) = match text.strip_prefix('<').and_then(|rest| rest.split_once('>')) {
+        Some((priority, rest))
+            if !priority.is_empty()
+                && priority.len() <= 3
+                && priority.bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            (Some(priority.parse::<u32>().ok()?), rest)
+        }
+        _ => (None, text),
+    };
+
+    if let Some(rest) = rest.strip_prefix("1 ") {
+        record.format = Format::Syslog;
+        record.level = priority.map(syslog_level);
+        return Some(parse_rfc5424(record, rest));
+    }
+
+    //RFC 3164 lines start with a timestamp like "Jan 15 14:30:25" that has no year
+    let (timestamp, length) = timestamp::parse_prefix(rest)?;
+    if !matches!(timestamp.date, Some(date) if date.year.is_none()) {
+        return None;
+    }
+    record.format = Format::Syslog;
+    record.level = priority.map(syslog_level);
+    record.timestamp = Some(timestamp);
+
+    let (host, rest) = split_token(&rest[length..]);
+    if host.is_empty() {
+        return Some(Err(String::from(
+            "a syslog line needs a host name after the timestamp",
+        )));
+    }
+    record.fields.push((String::from("host"), host.to_string()));
+
+    //"sshd[1234]: message", though some programs leave out the pid or even the whole tag
+    match rest.split_once(": ") {
+        Some((tag, message)) if !tag.is_empty() && !tag.contains(' ') => {
+            let (program, pid) = match tag.strip_suffix(']').and_then(|tag| tag.split_once('[')) {
+                Some((program, pid)) => (program, Some(pid)),
+                None => (tag, None),
+            };
+            record.target = Some(program.to_string());
+            if let Some(pid) = pid {
+                record.fields.push((String::from("pid"), pid.to_string()));
+            }
+            record.message = message.to_string();
+        }
+        _ => record.message = rest.to_string(),
+    }
+    record.fields.extend(message_fields(&record.message));
+    Some(Ok(()))
+}
+
+//TIMESTAMP HOST APP-NAME PROCID MSGID [STRUCTURED-DATA] MESSAGE, with "-" for any part that is left out
+fn parse_rfc5424(record: &mut LogRecord, text: &str) -> Result<(), String> {
+    let mut rest = text;
+    let mut parts = vec![];
+    for _ in 0..5 {
+        let (part, after) = split_token(rest);
+        if part.is_empty() {
+            return Err(String::from(
+                "a syslog line needs a timestamp, host, app, process id and message id",
+            ));
+        }
+        parts.push(part);
+        rest = after;
+    }
+
+    let nil = |part: &str| {
+        if part == "-" {
+            None
+        } else {
+            Some(part.to_string())
+        }
+    };
+    if parts[0] != "-" {
+        record.timestamp = Some(
+            Timestamp::parse(parts[0])
+                .ok_or_else(|| format!("'{}' is not a timestamp", parts[0]))?,
+        );
+    }
+    record.target = nil(parts[2]);
+    for (key, part) in [("host", parts[1]), ("pid", parts[3]), ("msgid", parts[4])] {
+        if let Some(value) = nil(part) {
+            record.fields.push((key.to_string(), value));
+        }
+    }
+
+    //structured data is "-" or one or more [id key="value" ...] elements, whose pairs become fields
+    if let Some(after) = rest.strip_prefix('-') {
+        rest = after;
+    } else {
+        while rest.starts_with('[') {
+            let end = structured_data_end(rest)
+                .ok_or("the structured data is missing its closing ']'")?;
+            let element = &rest[1..end];
+            let (_, params) = element.split_once(' ').unwrap_or((element, ""));
+            record
+                .fields
+                .extend(split_quoted(params).iter().filter_map(|token| pair(token)));
+            rest = &rest[end + 1..];
+        }
+    }
+
+    //the message can start with a byte order mark to say it is UTF-8
+    record.message = rest.trim_start().trim_start_matches('\u{feff}').to_string();
+    Ok(())
+}
+
+//the ']' that closes the first structured data element, skipping any escaped inside its quoted values
+fn structured_data_end(text: &str) -> Option<usize> {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (index, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            ']' if !in_quotes => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+//syslog severities go from 0 for emergencies to 7 for debug messages
+fn syslog_level(priority: u32) -> Level {
+    match priority % 8 {
+        0..=2 => Level::Fatal,
+        3 => Level::Error,
+        4 => Level::Warn,
+        5 | 6 => Level::Info,
+        _ => Level::Debug,
+    }
+}
+
+//"[timestamp LEVEL target] message", where the timestamp and target can each be left out
+fn parse_env_logger(record: &mut LogRecord, text: &str) -> bool {
+    let Some((header, message)) = text.strip_prefix('[').and_then(|rest| rest.split_once(']'))
+    else {
+        return false;
+    };
+    let mut tokens = header.split_whitespace().peekable();
+    let timestamp = tokens.peek().and_then(|token| Timestamp::parse(token));
+    if timestamp.is_some() {
+        tokens.next();
+    }
+    let Some(level) = tokens.next().and_then(Level::parse) else {
+        return false;
+    };
+    let target: Vec<&str> = tokens.collect();
+
+    record.format = Format::EnvLogger;
+    record.timestamp = timestamp;
+    record.level = Some(level);
+    record.target = (!target.is_empty()).then(|| target.join(" "));
+    record.message = message.trim_start().to_string();
+    record.fields = message_fields(&record.message);
+    true
+}
+
+//a line counts as logfmt when every part of it is a key=value pair and one of them is a level or message,
+//returning None when it doesn't so another format can be tried
+fn parse_logfmt(record: &mut LogRecord, text: &str) -> Option<Result<(), String>> {
+    let pairs = split_quoted(text)
+        .iter()
+        .map(|token| pair(token))
+        .collect::<Option<Vec<_>>>()?;
+    let is_key = |keys: &[&str], key: &str| keys.iter().any(|name| name.eq_ignore_ascii_case(key));
+    if !pairs
+        .iter()
+        .any(|(key, _)| is_key(&LEVEL_KEYS, key) || is_key(&MESSAGE_KEYS, key))
+    {
+        return None;
+    }
+
+    record.format = Format::Logfmt;
+    for (key, value) in pairs {
+        if is_key(&TIMESTAMP_KEYS, &key) && record.timestamp.is_none() {
+            match Timestamp::parse(&value) {
+                Some(timestamp) => record.timestamp = Some(timestamp),
+                None => return Some(Err(format!("'{}' is not a timestamp", value))),
+            }
+        } else if is_key(&LEVEL_KEYS, &key) && record.level.is_none() {
+            match Level::parse(&value) {
+                Some(level) => record.level = Some(level),
+                None => return Some(Err(format!("'{}' is not a log level", value))),
+            }
+        } else if is_key(&TARGET_KEYS, &key) && record.target.is_none() {
+            record.target = Some(value);
+        } else if is_key(&MESSAGE_KEYS, &key) && record.message.is_empty() {
+            record.message = value;
+        } else {
+            record.fields.push((key, value));
+        }
+    }
+    Some(Ok(()))
+}
+
+//anything else needs a level or a timestamp to be a log line.  The level doesn't have to be the first thing on the
+//line, it can come after the timestamp, a [thread] or [target], a process id or a "-", like in
+//"2024-01-15 14:30:25 [main] ERROR message" or "2024-01-15 14:30:25,123 - ERROR - message"
+fn parse_plain(record: &mut LogRecord, text: &str) -> Result<(), String> {
+    let mut rest = text;
+
+    if let Some((timestamp, length)) = timestamp::parse_prefix(rest) {
+        record.timestamp = Some(timestamp);
+        rest = rest[length..].trim_start();
+    }
+
+    //what is skipped over only counts if a level turns up after it, otherwise it is the start of the message
+    let mut ahead = rest;
+    let mut skipped_target = None;
+    loop {
+        let (token, after) = split_token(ahead);
+        if let Some(level) = Level::parse(token.trim_matches(['[', ']', '(', ')', ':'])) {
+            record.level = Some(level);
+            rest = after;
+            break;
+        }
+
+        if let Some(target) = token
+            .strip_prefix('[')
+            .and_then(|token| token.strip_suffix(']'))
+        {
+            skipped_target.get_or_insert(target);
+        } else if split_token(after).0 == "-"
+            && token
+                .chars()
+                .all(|c| c.is_alphanumeric() || matches!(c, '_' | '.'))
+        {
+            //python's "%(asctime)s - %(name)s - %(levelname)s - %(message)s"
+            skipped_target.get_or_insert(token);
+        } else if !(token == "-"
+            || token == "---"
+            || (!token.is_empty() && token.bytes().all(|b| b.is_ascii_digit())))
+        {
+            break;
+        }
+        ahead = after;
+    }
+
+    if record.timestamp.is_none() {
+        if let Some((timestamp, length)) = timestamp::parse_prefix(rest) {
+            record.timestamp = Some(timestamp);
+            rest = rest[length..].trim_start();
+        }
+    }
+
+    if record.level.is_none() && record.timestamp.is_none() {
+        return Err(String::from(
+            "no level or timestamp, so this is not a log line in a known format",
+        ));
+    }
+
+    //separators after the level, like in python's " - ERROR - " and Spring Boot's " ERROR 12345 --- "
+    rest = rest.strip_prefix("- ").unwrap_or(rest);
+    if let Some((pid, after)) = rest.split_once(" --- ") {
+        if !pid.is_empty() && pid.bytes().all(|b| b.is_ascii_digit()) {
+            record.fields.push((String::from("pid"), pid.to_string()));
+            rest = after;
+        }
+    }
+    let (token, after) = split_token(rest);
+    if let Some(target) = plain_target(token) {
+        record.target = Some(target.to_string());
+        rest = after;
+    } else if record.level.is_some() {
+        record.target = skipped_target
+            .filter(|target| !target.is_empty())
+            .map(String::from);
+    }
+
+    record.message = rest.to_string();
+    record.fields.extend(message_fields(&record.message));
+    Ok(())
+}
+
+//"[db]", or a module path followed by a colon like "my_crate::db:" or "com.example.Db:"
+fn plain_target(token: &str) -> Option<&str> {
+    if let Some(target) = token
+        .strip_prefix('[')
+        .and_then(|token| token.strip_suffix(']'))
+    {
+        return (!target.is_empty()).then_some(target);
+    }
+
+    let target = token.strip_suffix(':')?;
+    let is_path = target.contains("::") || (target.contains('.') && !target.ends_with('.'));
+    let allowed = |c: char| c.is_alphanumeric() || matches!(c, '_' | ':' | '.' | '-');
+    (is_path && target.chars().all(allowed)).then_some(target)
+}
+
+//the first whitespace separated part of the text, and the rest after the whitespace following it
+fn split_token(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    match text.find(char::is_whitespace) {
+        Some(end) => (&text[..end], text[end..].trim_start()),
+        None => (text, ""),
+    }
+}
+
+//key=value pairs written in a free text message, like "user=bob" in "login failed user=bob"
+fn message_fields(message: &str) -> Vec<(String, String)> {
+    split_quoted(message)
+        .iter()
+        .filter_map(|token| pair(token))
+        .collect()
+}
+
+//splits on whitespace, except inside double quotes
+fn split_quoted(text: &str) -> Vec<&str> {
+    let mut tokens = vec![];
+    let mut start = None;
+    let mut in_quotes = false;
+    let mut escaped = false;
+
+    for (index, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if let Some(start) = start.take() {
+                    tokens.push(&text[start..index]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(index);
+    }
+    if let Some(start) = start {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+//key=value or key="quoted value", where the key has to look like a name so "a == b" in a message isn't a field
+fn pair(token: &str) -> Option<(String, String)> {
+    let (key, value) = token.split_once('=')?;
+    let mut chars = key.chars();
+    let starts_well = chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '@');
+    if !starts_well || !chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '-')) {
+        return None;
+    }
+
+    let value = match value.strip_prefix('"') {
+        Some(quoted) => quoted
+            .strip_suffix('"')?
+            .replace("\\\"", "\"")
+            .replace("\\\\", "\\"),
+        None => value.to_string(),
+    };
+    Some((key.to_string(), value))
+}
+
+//values with spaces or quotes in them are quoted again when a record is shown, so they still read as one field
+fn quote(value: &str) -> String {
+    if value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == '"' || c == '=') {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //what a line is expected to parse into: format, timestamp as shown, level, target, message and fields
+    type Expected = (
+        Format,
+        Option<&'static str>,
+        Option<Level>,
+        Option<&'static str>,
+        &'static str,
+        &'static [(&'static str, &'static str)],
+    );
+
+    fn check(cases: &[(&str, Expected)]) {
+        for (text, (format, timestamp, level, target, message, fields)) in cases {
+            let record = parse_line(7, text).unwrap_or_else(|error| panic!("{}: {}", text, error));
+            let fields: Vec<(String, String)> = fields
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+
+            assert_eq!(record.line, 7);
+            assert_eq!(record.format, *format, "{}", text);
+            assert_eq!(
+                record
+                    .timestamp
+                    .map(|timestamp| timestamp.to_string())
+                    .as_deref(),
+                *timestamp,
+                "{}",
+                text
+            );
+            assert_eq!(record.level, *level, "{}", text);
+            assert_eq!(record.target.as_deref(), *target, "{}", text);
+            assert_eq!(record.message, *message, "{}", text);
+            assert_eq!(record.fields, fields, "{}", text);
+            assert_eq!(record.text, *text);
+        }
+    }
+
+    #[test]
+    fn json_lines() {
+        check(&[
+            (
+                r#"{"timestamp":"2024-01-15T14:30:25.123Z","level":"ERROR","target":"app::db","fields":{"message":"query failed","rows":3}}"#,
+                (
+                    Format::Json,
+                    Some("2024-01-15 14:30:25.123"),
+                    Some(Level::Error),
+                    Some("app::db"),
+                    "query failed",
+                    &[("rows", "3")],
+                ),
+            ),
+            //bunyan's numeric levels and epoch milliseconds
+            (
+                r#"{"time":1705329025000,"level":40,"name":"api","msg":"slow","hostname":"web1"}"#,
+                (
+                    Format::Json,
+                    Some("2024-01-15 14:30:25"),
+                    Some(Level::Warn),
+                    None,
+                    "slow",
+                    &[("hostname", "web1"), ("name", "api")],
+                ),
+            ),
+            (
+                r#"{"ts":1705329025.5,"severity":"info","logger":"jobs","message":"done"}"#,
+                (
+                    Format::Json,
+                    Some("2024-01-15 14:30:25.500"),
+                    Some(Level::Info),
+                    Some("jobs"),
+                    "done",
+                    &[],
+                ),
+            ),
+        ]);
+    }
+
+    #[test]
+    fn syslog_lines() {
+        check(&[
+            (
+                "<34>1 2024-01-15T14:30:25+02:00 web1 sshd 1234 ID47 [auth@1 user=\"bob\" tries=\"3\"] \u{feff}login failed",
+                (
+                    Format::Syslog,
+                    Some("2024-01-15 12:30:25"),
+                    Some(Level::Fatal),
+                    Some("sshd"),
+                    "login failed",
+                    &[
+                        ("host", "web1"),
+                        ("pid", "1234"),
+                        ("msgid", "ID47"),
+                        ("user", "bob"),
+                        ("tries", "3"),
+                    ],
+                ),
+            ),
+            (
+                "<165>1 - - - - - - no header fields",
+                (
+                    Format::Syslog,
+                    None,
+                    Some(Level::Info),
+                    None,
+                    "no header fields",
+                    &[],
+                ),
+            ),
+            (
+                "<28>Jan  5 14:30:25 web1 sshd[1234]: Failed password user=root",
+                (
+                    Format::Syslog,
+                    Some("--01-05 14:30:25"),
+                    Some(Level::Warn),
+                    Some("sshd"),
+                    "Failed password user=root",
+                    &[("host", "web1"), ("pid", "1234"), ("user", "root")],
+                ),
+            ),
+            (
+                "Jan 15 14:30:25 web1 kernel: eth0 link up",
+                (
+                    Format::Syslog,
+                    Some("--01-15 14:30:25"),
+                    None,
+                    Some("kernel"),
+                    "eth0 link up",
+                    &[("host", "web1")],
+                ),
+            ),
+        ]);
+    }
+
+    #[test]
+    fn env_logger_lines() {
+        check(&[
+            (
+                "[2024-01-15T14:30:25Z INFO  my_crate::db] connected pool=4",
+                (
+                    Format::EnvLogger,
+                    Some("2024-01-15 14:30:25"),
+                    Some(Level::Info),
+                    Some("my_crate::db"),
+                    "connected pool=4",
+                    &[("pool", "4")],
+                ),
+            ),
+            (
+                "[WARN] disk almost full",
+                (
+                    Format::EnvLogger,
+                    None,
+                    Some(Level::Warn),
+                    None,
+                    "disk almost full",
+                    &[],
+                ),
+            ),
+        ]);
+    }
+
+    #[test]
+    fn logfmt_lines() {
+        check(&[(
+            r#"time=2024-01-15T14:30:25Z level=warn module=cache msg="evicted \"old\" keys" count=12"#,
+            (
+                Format::Logfmt,
+                Some("2024-01-15 14:30:25"),
+                Some(Level::Warn),
+                Some("cache"),
+                "evicted \"old\" keys",
+                &[("count", "12")],
+            ),
+        )]);
+    }
+
+    #[test]
+    fn plain_lines() {
+        check(&[
+            (
+                "ERROR 14:33:45 payment declined",
+                (
+                    Format::Plain,
+                    Some("14:33:45"),
+                    Some(Level::Error),
+                    None,
+                    "payment declined",
+                    &[],
+                ),
+            ),
+            (
+                "2024-01-15 14:30:25 [main] ERROR com.example.Db: timeout after=30s",
+                (
+                    Format::Plain,
+                    Some("2024-01-15 14:30:25"),
+                    Some(Level::Error),
+                    Some("com.example.Db"),
+                    "timeout after=30s",
+                    &[("after", "30s")],
+                ),
+            ),
+            (
+                "2024-01-15 14:30:25,123 - billing - WARNING - retrying",
+                (
+                    Format::Plain,
+                    Some("2024-01-15 14:30:25.123"),
+                    Some(Level::Warn),
+                    Some("billing"),
+                    "retrying",
+                    &[],
+                ),
+            ),
+            (
+                "2024-01-15 14:30:25.123  INFO 12345 --- [main] o.s.b.App: Started",
+                (
+                    Format::Plain,
+                    Some("2024-01-15 14:30:25.123"),
+                    Some(Level::Info),
+                    Some("main"),
+                    "o.s.b.App: Started",
+                    &[("pid", "12345")],
+                ),
+            ),
+            (
+                "2024-01-15 14:30:25 started without a level",
+                (
+                    Format::Plain,
+                    Some("2024-01-15 14:30:25"),
+                    None,
+                    None,
+                    "started without a level",
+                    &[],
+                ),
+            ),
+        ]);
+    }
+
+    #[test]
+    fn failures_name_the_line() {
+        let cases = [
+            ("   ", "line 3: the line is blank"),
+            (
+                "{\"level\": \"info\"",
+                "line 3: invalid JSON: EOF while parsing an object at line 1 column 16",
+            ),
+            (r#"{"level":"loud"}"#, "line 3: 'loud' is not a log level"),
+            (
+                r#"{"time":"yesterday"}"#,
+                "line 3: 'yesterday' is not a timestamp",
+            ),
+            ("level=loud msg=hi", "line 3: 'loud' is not a log level"),
+            (
+                "<13>1 2024-01-15T14:30:25Z web1",
+                "line 3: a syslog line needs a timestamp, host, app, process id and message id",
+            ),
+            (
+                "<13>1 2024-01-15T14:30:25Z h a p m [sd x=\"1\" hi",
+                "line 3: the structured data is missing its closing ']'",
+            ),
+            (
+                "Jan 15 14:30:25",
+                "line 3: a syslog line needs a host name after the timestamp",
+            ),
+            (
+                "just some words",
+                "line 3: no level or timestamp, so this is not a log line in a known format",
+            ),
+        ];
+
+        for (text, expected) in cases {
+            let error = parse_line(3, text).unwrap_err();
+            assert_eq!(error.line, 3);
+            assert_eq!(error.to_string(), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn records_are_shown_the_same_way_whatever_the_format() {
+        let record =
+            parse_line(1, r#"level=info msg="user logged in" user="Ann Lee" id=4"#).unwrap();
+        assert_eq!(
+            record.to_string(),
+            "INFO  user logged in user=\"Ann Lee\" id=4"
+        );
+
+        let record = parse_line(1, "[2024-01-15T14:30:25Z ERROR app] failed code=7").unwrap();
+        assert_eq!(
+            record.to_string(),
+            "2024-01-15 14:30:25 ERROR [app] failed code=7"
+        );
+    }
+}
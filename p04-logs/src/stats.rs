@@ -0,0 +1,434 @@
+//Counts records by level, module and time, and finds the messages that come up most often
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::level::Level;
+use crate::record::LogRecord;
+use crate::timestamp::Timestamp;
+
+///Messages are grouped into at most this many templates, so a log full of unmaskable unique messages can't use up
+///all the memory.  Messages that would make a new template after that are only counted.
+pub const MAX_TEMPLATES: usize = 10_000;
+
+//shown in place of a level, module or time for records that don't have one
+const NONE: &str = "(none)";
+
+///How long each time bucket is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bucket {
+    Minute,
+    Hour,
+}
+
+impl Bucket {
+    pub fn parse(text: &str) -> Option<Bucket> {
+        match text.trim().to_lowercase().as_str() {
+            "minute" | "minutes" | "m" => Some(Bucket::Minute),
+            "hour" | "hours" | "h" => Some(Bucket::Hour),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Bucket::Minute => "minute",
+            Bucket::Hour => "hour",
+        }
+    }
+}
+
+///Counts kept while records are read, one record at a time.
+#[derive(Debug)]
+pub struct Stats {
+    bucket: Bucket,
+    records: usize,
+    unparsed: usize,
+    by_level: BTreeMap<Option<Level>, usize>,
+    by_module: HashMap<String, usize>,
+    //keyed by the start of each bucket, so they come out in order
+    by_time: BTreeMap<Option<Timestamp>, usize>,
+    //each template's count and the first message that fit it, to show what it looks like unmasked
+    templates: HashMap<String, (usize, String)>,
+    //messages that didn't get a template because there were already MAX_TEMPLATES
+    other_messages: usize,
+}
+
+///One row of a count table.
+#[derive(Debug, Serialize)]
+pub struct Count {
+    pub name: String,
+    pub count: usize,
+}
+
+///A message template and how many messages fit it.
+#[derive(Debug, Serialize)]
+pub struct Template {
+    pub template: String,
+    pub count: usize,
+    pub example: String,
+}
+
+///The finished counts, sorted for showing.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub records: usize,
+    pub unparsed: usize,
+    pub levels: Vec<Count>,
+    pub modules: Vec<Count>,
+    pub bucket: &'static str,
+    pub times: Vec<Count>,
+    pub top_messages: Vec<Template>,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub other_messages: usize,
+}
+
+fn is_zero(count: &usize) -> bool {
+    *count == 0
+}
+
+impl Stats {
+    pub fn new(bucket: Bucket) -> Self {
+        Stats {
+            bucket,
+            records: 0,
+            unparsed: 0,
+            by_level: BTreeMap::new(),
+            by_module: HashMap::new(),
+            by_time: BTreeMap::new(),
+            templates: HashMap::new(),
+            other_messages: 0,
+        }
+    }
+
+    pub fn add(&mut self, record: &LogRecord) {
+        self.records += 1;
+        *self.by_level.entry(record.level).or_default() += 1;
+        *self
+            .by_module
+            .entry(record.target.clone().unwrap_or_else(|| NONE.to_string()))
+            .or_default() += 1;
+        *self
+            .by_time
+            .entry(
+                record
+                    .timestamp
+                    .map(|timestamp| self.bucket_start(timestamp)),
+            )
+            .or_default() += 1;
+
+        let template = template(&record.message);
+        if let Some((count, _)) = self.templates.get_mut(&template) {
+            *count += 1;
+        } else if self.templates.len() < MAX_TEMPLATES {
+            self.templates.insert(template, (1, record.message.clone()));
+        } else {
+            self.other_messages += 1;
+        }
+    }
+
+    ///Counts a line that couldn't be parsed, which can't go in any of the tables.
+    pub fn add_unparsed(&mut self) {
+        self.unparsed += 1;
+    }
+
+    ///The counts with the most common levels, modules and messages first, the times in order, and only the `top`
+    ///most common message templates.
+    pub fn report(&self, top: usize) -> Report {
+        //levels are shown most serious first, which is the order people read them in
+        let levels = self
+            .by_level
+            .iter()
+            .rev()
+            .map(|(level, count)| Count {
+                name: level.map(|level| level.name()).unwrap_or(NONE).to_string(),
+                count: *count,
+            })
+            .collect();
+
+        let mut modules: Vec<Count> = self
+            .by_module
+            .iter()
+            .map(|(module, count)| Count {
+                name: module.clone(),
+                count: *count,
+            })
+            .collect();
+        modules.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+
+        let times = self
+            .by_time
+            .iter()
+            .map(|(start, count)| Count {
+                name: start
+                    .map(|start| self.bucket_name(&start))
+                    .unwrap_or_else(|| NONE.to_string()),
+                count: *count,
+            })
+            .collect();
+
+        let mut templates: Vec<Template> = self
+            .templates
+            .iter()
+            .map(|(template, (count, example))| Template {
+                template: template.clone(),
+                count: *count,
+                example: example.clone(),
+            })
+            .collect();
+        templates.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.template.cmp(&b.template))
+        });
+        templates.truncate(top);
+
+        Report {
+            records: self.records,
+            unparsed: self.unparsed,
+            levels,
+            modules,
+            bucket: self.bucket.name(),
+            times,
+            top_messages: templates,
+            other_messages: self.other_messages,
+        }
+    }
+
+    fn bucket_start(&self, timestamp: Timestamp) -> Timestamp {
+        let minute = match self.bucket {
+            Bucket::Minute => timestamp.minute,
+            Bucket::Hour => 0,
+        };
+        Timestamp {
+            minute,
+            second: 0,
+            nanosecond: 0,
+            ..timestamp
+        }
+    }
+
+    //"2024-01-15 14:30" without the seconds, which are always 0
+    fn bucket_name(&self, start: &Timestamp) -> String {
+        let text = start.to_string();
+        text[..text.len() - 3].to_string()
+    }
+}
+
+impl Report {
+    ///The report as plain text tables, one after another.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "{} records", self.records);
+        if self.unparsed > 0 {
+            let _ = writeln!(text, "{} lines couldn't be parsed", self.unparsed);
+        }
+
+        write_table(&mut text, "LEVEL", &self.levels);
+        write_table(&mut text, "MODULE", &self.modules);
+        write_table(&mut text, &self.bucket.to_uppercase(), &self.times);
+
+        if !self.top_messages.is_empty() {
+            let _ = writeln!(text, "\n{:>7}  MESSAGE", "COUNT");
+            for template in &self.top_messages {
+                let _ = writeln!(text, "{:>7}  {}", template.count, template.template);
+            }
+        }
+        if self.other_messages > 0 {
+            let _ = writeln!(
+                text,
+                "{} more messages didn't fit in the {} templates that are kept",
+                self.other_messages, MAX_TEMPLATES
+            );
+        }
+        text
+    }
+}
+
+fn write_table(text: &mut String, heading: &str, counts: &[Count]) {
+    if counts.is_empty() {
+        return;
+    }
+    let width = counts
+        .iter()
+        .map(|count| count.name.chars().count())
+        .chain([heading.len()])
+        .max()
+        .unwrap_or(0);
+
+    let _ = writeln!(text, "\n{:<width$}  {:>7}", heading, "COUNT", width = width);
+    for count in counts {
+        let _ = writeln!(
+            text,
+            "{:<width$}  {:>7}",
+            count.name,
+            count.count,
+            width = width
+        );
+    }
+}
+
+///The message with the parts that change from one message to the next masked, so that "user 42 logged in" and
+///"user 7 logged in" both become "user <n> logged in".  UUIDs become <uuid>, numbers (including negative numbers,
+///decimals and IP addresses) become <n>, numbers with a unit keep the unit, so "took 3ms" and "took 45ms" both become
+///"took <n>ms", and words of four or more characters with digits in them, like "req-8f3a2c" or "0x1f4a", become <id>.
+pub fn template(message: &str) -> String {
+    let mut template = String::with_capacity(message.len());
+    let mut word = String::new();
+
+    for c in message.chars() {
+        if c.is_alphanumeric() || matches!(c, '_' | '-' | '.') {
+            word.push(c);
+        } else {
+            push_masked(&mut template, &word);
+            word.clear();
+            template.push(c);
+        }
+    }
+    push_masked(&mut template, &word);
+    template
+}
+
+//units that are kept after a masked number, compared ignoring case
+const UNITS: [&str; 20] = [
+    "ns", "us", "µs", "ms", "s", "sec", "secs", "m", "min", "mins", "h", "d", "b", "kb", "kib",
+    "mb", "mib", "gb", "gib", "x",
+];
+
+//adds a word to the template, masked if it is a number or an ID, with a full stop or dash at its end left as it was
+fn push_masked(template: &mut String, word: &str) {
+    let trimmed = word.trim_end_matches(['.', '-']);
+    let ending = &word[trimmed.len()..];
+
+    let has_digit = trimmed.chars().any(|c| c.is_ascii_digit());
+    if !has_digit {
+        template.push_str(word);
+        return;
+    }
+
+    //a minus sign is part of the number, but dashes in front of anything else are left as they were
+    let unsigned = trimmed.strip_prefix('-').unwrap_or(trimmed);
+    let number = unsigned.trim_end_matches(|c: char| c.is_alphabetic());
+    let unit = &unsigned[number.len()..];
+
+    if is_uuid(trimmed) {
+        template.push_str("<uuid>");
+    } else if is_number(unsigned) {
+        template.push_str("<n>");
+    } else if is_number(number) && UNITS.iter().any(|known| known.eq_ignore_ascii_case(unit)) {
+        template.push_str("<n>");
+        template.push_str(unit);
+    } else if trimmed.chars().count() >= 4 {
+        template.push_str("<id>");
+    } else {
+        //short words like "v2" and "h2" are names more often than IDs
+        template.push_str(word);
+        return;
+    }
+    template.push_str(ending);
+}
+
+//digits with full stops between them, like 42, 1.5 or 10.0.0.1
+fn is_number(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_digit())
+        && word.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+//8-4-4-4-12 hex digits, like 123e4567-e89b-12d3-a456-426614174000
+fn is_uuid(word: &str) -> bool {
+    let parts: Vec<&str> = word.split('-').collect();
+    parts.len() == 5
+        && parts.iter().zip([8, 4, 4, 4, 12]).all(|(part, length)| {
+            part.len() == length && part.chars().all(|c| c.is_ascii_hexdigit())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::parse_line;
+
+    #[test]
+    fn masks_the_parts_that_change() {
+        let cases = [
+            ("user 42 logged in", "user <n> logged in"),
+            (
+                "request 123e4567-e89b-12d3-a456-426614174000 failed",
+                "request <uuid> failed",
+            ),
+            (
+                "REQUEST 123E4567-E89B-12D3-A456-426614174000",
+                "REQUEST <uuid>",
+            ),
+            ("connected to 10.0.0.1:8080", "connected to <n>:<n>"),
+            ("load is 0.75.", "load is <n>."),
+            ("balance -12.50 after -3", "balance <n> after <n>"),
+            ("trace req-8f3a2c and 0x1f4a", "trace <id> and <id>"),
+            ("deadbeef1 was freed", "<id> was freed"),
+            ("took 3ms", "took <n>ms"),
+            ("took 45ms", "took <n>ms"),
+            ("took 1.5s, read 200MB", "took <n>s, read <n>MB"),
+            ("retry in -5s", "retry in <n>s"),
+            //short names with digits in them and dashes that aren't minus signs are kept
+            ("using v2 over h2", "using v2 over h2"),
+            ("-- 3 left --", "-- <n> left --"),
+            ("step 1-", "step <n>-"),
+            ("no numbers here", "no numbers here"),
+            ("", ""),
+        ];
+        for (message, expected) in cases {
+            assert_eq!(template(message), expected, "masking {:?}", message);
+        }
+    }
+
+    #[test]
+    fn the_same_message_with_different_values_shares_a_template() {
+        assert_eq!(template("took 3ms"), template("took 12345ms"));
+        assert_eq!(template("id 7"), template("id 1234567"));
+        assert_ne!(template("took 3ms"), template("took 3s"));
+    }
+
+    #[test]
+    fn counts_levels_modules_times_and_templates() {
+        let mut stats = Stats::new(Bucket::Minute);
+        let lines = [
+            "[2024-01-15T14:30:01Z ERROR db] query took 30ms",
+            "[2024-01-15T14:30:59Z ERROR db] query took 45ms",
+            "[2024-01-15T14:31:00Z INFO web] user 7 logged in",
+        ];
+        for (index, line) in lines.iter().enumerate() {
+            stats.add(&parse_line(index + 1, line).unwrap());
+        }
+        stats.add_unparsed();
+
+        let report = stats.report(1);
+        assert_eq!((report.records, report.unparsed), (3, 1));
+        let names = |counts: &[Count]| -> Vec<(String, usize)> {
+            counts
+                .iter()
+                .map(|count| (count.name.clone(), count.count))
+                .collect()
+        };
+        assert_eq!(
+            names(&report.levels),
+            [("ERROR".to_string(), 2), ("INFO".to_string(), 1)]
+        );
+        assert_eq!(
+            names(&report.modules),
+            [("db".to_string(), 2), ("web".to_string(), 1)]
+        );
+        assert_eq!(
+            names(&report.times),
+            [
+                ("2024-01-15 14:30".to_string(), 2),
+                ("2024-01-15 14:31".to_string(), 1)
+            ]
+        );
+        assert_eq!(report.top_messages.len(), 1);
+        assert_eq!(report.top_messages[0].template, "query took <n>ms");
+        assert_eq!(report.top_messages[0].count, 2);
+        assert_eq!(report.top_messages[0].example, "query took 30ms");
+    }
+}
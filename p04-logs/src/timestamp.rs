@@ -0,0 +1,507 @@
+//Log lines write their times in many ways, these read the common ones without needing a date library
+
+use std::cmp::Ordering;
+use std::fmt;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+///A calendar date.  The year is None for formats that leave it out, like syslog's "Jan 15".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: Option<i32>,
+    pub month: u32,
+    pub day: u32,
+}
+
+///When a log line was written, in UTC if the line said which time zone it was in.
+///The date is None for logs that only write the time of day, like "14:30:25".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    pub date: Option<Date>,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub nanosecond: u32,
+}
+
+impl Timestamp {
+    ///Reads text that is only a timestamp, like "2024-01-15T14:30:25Z", "2024-01-15 14:30", "Jan 15 14:30:25" or "14:30:25".
+    pub fn parse(text: &str) -> Option<Timestamp> {
+        let text = text.trim();
+        match parse_prefix(text) {
+            Some((timestamp, length)) if length == text.len() => Some(timestamp),
+            _ => None,
+        }
+    }
+
+    ///Converts seconds since 1970-01-01 UTC, which is how some JSON loggers write their times.
+    pub fn from_unix(seconds: f64) -> Option<Timestamp> {
+        if !seconds.is_finite() || seconds < 0.0 {
+            return None;
+        }
+
+        let whole = seconds.trunc() as i64;
+        let nanosecond = ((seconds - seconds.trunc()) * 1e9)
+            .round()
+            .min(999_999_999.0) as u32;
+        Some(from_seconds(whole, nanosecond))
+    }
+
+    ///Converts milliseconds since 1970-01-01 UTC, kept as a whole number so no precision is lost on the way.
+    pub fn from_unix_millis(millis: i64) -> Option<Timestamp> {
+        if millis < 0 {
+            return None;
+        }
+        Some(from_seconds(
+            millis / 1000,
+            (millis % 1000) as u32 * 1_000_000,
+        ))
+    }
+
+    ///Compares by only the parts both timestamps have, so that "14:30" can be compared with the time of day of any
+    ///line, and "2024-01-15" with a syslog line's "Jan 15" that has no year.
+    pub fn compare_loosely(&self, other: &Timestamp) -> Ordering {
+        let dates = match (self.date, other.date) {
+            (Some(date), Some(other)) => {
+                let years = match (date.year, other.year) {
+                    (Some(year), Some(other)) => year.cmp(&other),
+                    _ => Ordering::Equal,
+                };
+                years.then((date.month, date.day).cmp(&(other.month, other.day)))
+            }
+            _ => Ordering::Equal,
+        };
+
+        dates.then(
+            (self.hour, self.minute, self.second, self.nanosecond).cmp(&(
+                other.hour,
+                other.minute,
+                other.second,
+                other.nanosecond,
+            )),
+        )
+    }
+
+    ///The last moment of the same day, for a bound like "until 2024-01-15" that should include the whole day.
+    pub fn end_of_day(&self) -> Timestamp {
+        Timestamp {
+            hour: 23,
+            minute: 59,
+            second: 59,
+            nanosecond: 999_999_999,
+            ..*self
+        }
+    }
+}
+
+//written like ISO 8601, with "--MM-DD" for a date without a year
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.date {
+            Some(Date {
+                year: Some(year),
+                month,
+                day,
+            }) => write!(f, "{:04}-{:02}-{:02} ", year, month, day)?,
+            Some(Date {
+                year: None,
+                month,
+                day,
+            }) => write!(f, "--{:02}-{:02} ", month, day)?,
+            None => {}
+        }
+        write!(f, "{:02}:{:02}:{:02}", self.hour, self.minute, self.second)?;
+        if self.nanosecond > 0 {
+            write!(f, ".{:03}", self.nanosecond / 1_000_000)?;
+        }
+        Ok(())
+    }
+}
+
+///Reads a timestamp at the start of the text, returning it and how many bytes of the text it took up.
+///The timestamp has to be followed by the end of the text or by something other than a letter or digit.
+pub fn parse_prefix(text: &str) -> Option<(Timestamp, usize)> {
+    let mut scanner = Scanner {
+        bytes: text.as_bytes(),
+        position: 0,
+    };
+
+    let timestamp = scanner
+        .attempt(Scanner::iso)
+        .or_else(|| scanner.attempt(Scanner::syslog))
+        .or_else(|| scanner.attempt(|scanner| scanner.time().map(|time| at_time(None, time))))?;
+
+    match scanner.peek() {
+        Some(next) if next.is_ascii_alphanumeric() => None,
+        _ => Some((timestamp, scanner.position)),
+    }
+}
+
+//hour, minute, second and nanosecond
+type Time = (u32, u32, u32, u32);
+
+fn at_time(date: Option<Date>, (hour, minute, second, nanosecond): Time) -> Timestamp {
+    Timestamp {
+        date,
+        hour,
+        minute,
+        second,
+        nanosecond,
+    }
+}
+
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Scanner<'_> {
+    //runs a parse and goes back to where it started if it fails, so another format can be tried
+    fn attempt<T>(&mut self, parse: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        let start = self.position;
+        let result = parse(self);
+        if result.is_none() {
+            self.position = start;
+        }
+        result
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        if self.peek() == Some(byte) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    //like eat, for a byte that has to be there
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        self.eat(byte).then_some(())
+    }
+
+    //reads between `min` and `max` digits
+    fn number(&mut self, min: usize, max: usize) -> Option<(u32, usize)> {
+        let mut value: u32 = 0;
+        let mut count = 0;
+        while count < max {
+            match self.peek() {
+                Some(byte) if byte.is_ascii_digit() => {
+                    value = value * 10 + (byte - b'0') as u32;
+                    self.position += 1;
+                    count += 1;
+                }
+                _ => break,
+            }
+        }
+        (count >= min).then_some((value, count))
+    }
+
+    fn digits(&mut self, count: usize) -> Option<u32> {
+        self.number(count, count).map(|(value, _)| value)
+    }
+
+    //2024-01-15, then optionally a time after a 'T' or a space, then optionally Z or an offset like +02:00
+    fn iso(&mut self) -> Option<Timestamp> {
+        let year = self.digits(4)? as i32;
+        self.expect(b'-')?;
+        let month = self.digits(2)?;
+        self.expect(b'-')?;
+        let day = self.digits(2)?;
+        let date = Date {
+            year: Some(year),
+            month,
+            day,
+        };
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+
+        let time = self.attempt(|scanner| {
+            if !(scanner.eat(b'T') || scanner.eat(b' ')) {
+                return None;
+            }
+            scanner.time()
+        });
+        let Some(time) = time else {
+            //just a date counts as its midnight
+            return Some(at_time(Some(date), (0, 0, 0, 0)));
+        };
+
+        let offset_minutes = if self.eat(b'Z') {
+            0
+        } else {
+            self.attempt(Scanner::offset).unwrap_or(0)
+        };
+
+        let timestamp = at_time(Some(date), time);
+        if offset_minutes == 0 {
+            return Some(timestamp);
+        }
+        //times with an offset are moved to UTC, so lines from servers in different time zones can be compared
+        let seconds = unix_seconds(&timestamp)? - offset_minutes as i64 * 60;
+        Some(from_seconds(seconds, timestamp.nanosecond))
+    }
+
+    //+02:00, -0500 or +02
+    fn offset(&mut self) -> Option<i32> {
+        let sign = match self.peek()? {
+            b'+' => 1,
+            b'-' => -1,
+            _ => return None,
+        };
+        self.position += 1;
+        let hours = self.digits(2)? as i32;
+        let _ = self.eat(b':');
+        let minutes = self.attempt(|scanner| scanner.digits(2)).unwrap_or(0) as i32;
+        Some(sign * (hours * 60 + minutes))
+    }
+
+    //Jan 15 14:30:25, with the day sometimes padded with a space like "Jan  5"
+    fn syslog(&mut self) -> Option<Timestamp> {
+        let name = std::str::from_utf8(self.bytes.get(self.position..self.position + 3)?).ok()?;
+        let month = MONTH_NAMES
+            .iter()
+            .position(|month| month.eq_ignore_ascii_case(name))? as u32
+            + 1;
+        self.position += 3;
+
+        self.expect(b' ')?;
+        let _ = self.eat(b' ');
+        let (day, _) = self.number(1, 2)?;
+        self.expect(b' ')?;
+        if day == 0 || day > 31 {
+            return None;
+        }
+
+        let time = self.time()?;
+        Some(at_time(
+            Some(Date {
+                year: None,
+                month,
+                day,
+            }),
+            time,
+        ))
+    }
+
+    //14:30 or 14:30:25, with an optional fraction of a second like .123 or ,123
+    fn time(&mut self) -> Option<Time> {
+        let hour = self.digits(2)?;
+        self.expect(b':')?;
+        let minute = self.digits(2)?;
+        let second = self
+            .attempt(|scanner| {
+                scanner.expect(b':')?;
+                scanner.digits(2)
+            })
+            .unwrap_or(0);
+        if hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+
+        let nanosecond = self
+            .attempt(|scanner| {
+                if !(scanner.eat(b'.') || scanner.eat(b',')) {
+                    return None;
+                }
+                let (fraction, count) = scanner.number(1, 9)?;
+                Some(fraction * 10u32.pow(9 - count as u32))
+            })
+            .unwrap_or(0);
+
+        Some((hour, minute, second, nanosecond))
+    }
+}
+
+fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+///The number of days from 1970-01-01 to the given date, using the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    //shifting the year to start in March puts the leap day at the very end of the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+///The (year, month, day) that the given number of days since 1970-01-01 falls on.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+//seconds since 1970-01-01 UTC, only for timestamps that have a full date
+fn unix_seconds(timestamp: &Timestamp) -> Option<i64> {
+    let date = timestamp.date?;
+    let days = days_from_civil(date.year? as i64, date.month, date.day);
+    Some(
+        days * SECONDS_PER_DAY
+            + (timestamp.hour * 3600 + timestamp.minute * 60 + timestamp.second) as i64,
+    )
+}
+
+fn from_seconds(seconds: i64, nanosecond: u32) -> Timestamp {
+    let (year, month, day) = civil_from_days(seconds.div_euclid(SECONDS_PER_DAY));
+    let second_of_day = seconds.rem_euclid(SECONDS_PER_DAY) as u32;
+
+    Timestamp {
+        date: Some(Date {
+            year: Some(year as i32),
+            month,
+            day,
+        }),
+        hour: second_of_day / 3600,
+        minute: second_of_day / 60 % 60,
+        second: second_of_day % 60,
+        nanosecond,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shown(text: &str) -> Option<String> {
+        Timestamp::parse(text).map(|timestamp| timestamp.to_string())
+    }
+
+    #[test]
+    fn reads_the_common_formats() {
+        let cases = [
+            ("2024-01-15T14:30:25Z", "2024-01-15 14:30:25"),
+            ("2024-01-15 14:30:25.123456", "2024-01-15 14:30:25.123"),
+            ("2024-01-15 14:30:25,5", "2024-01-15 14:30:25.500"),
+            ("2024-01-15 14:30", "2024-01-15 14:30:00"),
+            ("2024-01-15", "2024-01-15 00:00:00"),
+            ("Jan 15 14:30:25", "--01-15 14:30:25"),
+            ("dec  5 01:02:03", "--12-05 01:02:03"),
+            ("14:30:25", "14:30:25"),
+            ("  14:30  ", "14:30:00"),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(shown(text).as_deref(), Some(expected), "{}", text);
+        }
+    }
+
+    #[test]
+    fn offsets_are_moved_to_utc() {
+        let cases = [
+            ("2024-01-15T14:30:25+02:00", "2024-01-15 12:30:25"),
+            ("2024-01-15T14:30:25-0500", "2024-01-15 19:30:25"),
+            ("2024-01-15T14:30:25+02", "2024-01-15 12:30:25"),
+            ("2024-01-15T14:30:25+05:30", "2024-01-15 09:00:25"),
+            //across the end of a day, a leap day and a year
+            ("2024-01-15T23:30:00-01:00", "2024-01-16 00:30:00"),
+            ("2024-03-01T01:00:00.250+02:00", "2024-02-29 23:00:00.250"),
+            ("2023-12-31T22:00:00-03:00", "2024-01-01 01:00:00"),
+            ("2024-01-15T14:30:25+00:00", "2024-01-15 14:30:25"),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(shown(text).as_deref(), Some(expected), "{}", text);
+        }
+    }
+
+    #[test]
+    fn rejects_times_that_do_not_exist() {
+        for text in [
+            "2023-02-29",
+            "2024-13-01",
+            "2024-04-31T10:00:00Z",
+            "2024-01-15T24:00:00Z",
+            "14:60",
+            "Jan 32 10:00:00",
+            "Foo 15 10:00:00",
+            "2024-01-15T14:30:25Zulu",
+            "14:30:25 trailing",
+            "yesterday",
+        ] {
+            assert_eq!(Timestamp::parse(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn a_prefix_stops_before_what_follows() {
+        let (timestamp, length) = parse_prefix("2024-01-15 14:30:25 INFO started").unwrap();
+        assert_eq!(length, "2024-01-15 14:30:25".len());
+        assert_eq!(timestamp.to_string(), "2024-01-15 14:30:25");
+        assert_eq!(parse_prefix("14:30:25abc"), None);
+    }
+
+    #[test]
+    fn converts_unix_times() {
+        assert_eq!(
+            Timestamp::from_unix(1_705_329_025.25).map(|timestamp| timestamp.to_string()),
+            Some(String::from("2024-01-15 14:30:25.250"))
+        );
+        assert_eq!(
+            Timestamp::from_unix_millis(1_705_329_025_007).map(|timestamp| timestamp.to_string()),
+            Some(String::from("2024-01-15 14:30:25.007"))
+        );
+        assert_eq!(Timestamp::from_unix(-1.0), None);
+        assert_eq!(Timestamp::from_unix(f64::NAN), None);
+    }
+
+    #[test]
+    fn compares_only_the_parts_both_have() {
+        let full = Timestamp::parse("2024-01-15 14:30:25").unwrap();
+        let syslog = Timestamp::parse("Jan 15 14:30:25").unwrap();
+        let time = Timestamp::parse("14:30").unwrap();
+        let next_day = Timestamp::parse("2024-01-16").unwrap();
+
+        assert_eq!(full.compare_loosely(&syslog), Ordering::Equal);
+        assert_eq!(full.compare_loosely(&time), Ordering::Greater);
+        assert_eq!(full.compare_loosely(&next_day), Ordering::Less);
+        assert_eq!(syslog.compare_loosely(&next_day), Ordering::Less);
+    }
+
+    #[test]
+    fn the_end_of_a_day_includes_all_of_it() {
+        let day = Timestamp::parse("2024-01-15").unwrap().end_of_day();
+        assert_eq!(day.to_string(), "2024-01-15 23:59:59.999");
+        let last_line = Timestamp::parse("2024-01-15T23:59:59.999999Z").unwrap();
+        assert_eq!(last_line.compare_loosely(&day), Ordering::Less);
+        assert_eq!(
+            Timestamp::parse("2024-01-16T00:00:00Z")
+                .unwrap()
+                .compare_loosely(&day),
+            Ordering::Greater
+        );
+    }
+}